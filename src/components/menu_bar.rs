@@ -17,11 +17,14 @@ pub enum Message {
 #[derive(MenuOption, strum_macros::Display, Debug, Clone)]
 pub enum FileMenu {
     Open,
+    LoadEnumMaps,
 }
 
 #[derive(MenuOption, strum_macros::Display, Debug, Clone)]
 pub enum ViewMenu {
-    ImplMe,
+    ZoomIn,
+    ZoomOut,
+    ZoomToFit,
 }
 
 #[derive(Debug, Default)]
@@ -44,20 +47,24 @@ impl GlobalMenuBar {
             .push(
                 Section::new(
                     Text::new("File"),
-                    vec![Entry::Item(
-                        Text::new("New File").into(),
-                        Some(FileMenu::Open),
-                    )],
+                    vec![
+                        Entry::Item(Text::new("New File").into(), Some(FileMenu::Open)),
+                        Entry::Item(
+                            Text::new("Load Enum Mappings").into(),
+                            Some(FileMenu::LoadEnumMaps),
+                        ),
+                    ],
                 )
                 .map(Message::File),
             )
             .push(
                 Section::new(
                     Text::new("View"),
-                    vec![Entry::Item(
-                        Text::new("ImplMe").into(),
-                        Some(ViewMenu::ImplMe),
-                    )],
+                    vec![
+                        Entry::Item(Text::new("Zoom In").into(), Some(ViewMenu::ZoomIn)),
+                        Entry::Item(Text::new("Zoom Out").into(), Some(ViewMenu::ZoomOut)),
+                        Entry::Item(Text::new("Zoom to Fit").into(), Some(ViewMenu::ZoomToFit)),
+                    ],
                 )
                 .map(Message::View),
             )
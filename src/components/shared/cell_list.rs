@@ -137,6 +137,10 @@ where
         self.nodes.iter().map(|node| &node.payload).collect()
     }
 
+    pub fn payloads_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.nodes.iter_mut().map(|node| &mut node.payload)
+    }
+
     pub fn view(
         &mut self,
         on_click: impl Fn(ListNodeState) -> Box<dyn Fn(&T) -> O::Message + 'static> + Copy,
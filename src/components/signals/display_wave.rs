@@ -1,10 +1,16 @@
 use iced::canvas::Text;
 use iced::Color;
 use std::sync::Arc;
-use wave2_wavedb::formatting::{format_payload, WaveFormat};
+use wave2_wavedb::errors::Waverr;
+use wave2_wavedb::formatting::{
+    data_zx_state, format_enum, format_literal, format_payload, EnumMap, WaveFormat, ZxState,
+};
 use wave2_wavedb::storage::in_memory::InMemWave;
 
-use wave2_wavedb::puddle::{Droplet};
+use wave2_wavedb::puddle::{Droplet, Toffset};
+use wave2_wavedb::signals::SigType;
+
+use super::wavewindow::WAVEHEIGHT;
 
 /// Mininum x_delta between two "value" changes that must occur before we consider writing the
 /// wave's value on the line
@@ -12,10 +18,31 @@ const TEXT_THRESHOLD: f32 = 12.0;
 
 const TEXT_SIZE: f32 = 12.0;
 
+/// Longest value, in characters, shown next to the name of a wave in the sigviewer
+const CURSOR_VALUE_CHARS: usize = 32;
+
+/// Row height real signals start out with, so that their plots are readable
+pub const ANALOG_ROW_HEIGHT: f32 = 4.0 * WAVEHEIGHT;
+/// Bounds on the configurable row height
+pub const MIN_ROW_HEIGHT: f32 = WAVEHEIGHT;
+pub const MAX_ROW_HEIGHT: f32 = 32.0 * WAVEHEIGHT;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// How the values of a real signal are plotted
+pub enum AnalogStyle {
+    /// Consecutive values are joined by straight lines
+    Line,
+    /// Values are held until the next change, like a sample and hold
+    Step,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct WaveDisplayOptions {
     color: WaveColors,
     format: WaveFormat,
+    /// Plot the values instead of drawing a bus, if set
+    analog: Option<AnalogStyle>,
+    row_height: f32,
 }
 
 impl Default for WaveDisplayOptions {
@@ -23,10 +50,28 @@ impl Default for WaveDisplayOptions {
         WaveDisplayOptions {
             color: WaveColors::Green,
             format: WaveFormat::Hex,
+            analog: None,
+            row_height: WAVEHEIGHT,
         }
     }
 }
 
+impl WaveDisplayOptions {
+    pub fn format(&self) -> WaveFormat {
+        self.format
+    }
+
+    pub fn analog(&self) -> Option<AnalogStyle> {
+        self.analog
+    }
+
+    /// Height of the row the wave is drawn in; digital waves keep their usual height and sit at
+    /// the bottom of it
+    pub fn row_height(&self) -> f32 {
+        self.row_height
+    }
+}
+
 pub const fn to_color(opts: &WaveDisplayOptions) -> Color {
     match opts.color {
         WaveColors::Green => Color::from_rgba(0.0, 1.0, 0.0, 1.0),
@@ -39,6 +84,10 @@ pub const fn to_color(opts: &WaveDisplayOptions) -> Color {
 pub struct DisplayedWave {
     wave_content: Arc<InMemWave>,
     pub display_conf: Option<WaveDisplayOptions>,
+    /// Names of values, shown when the wave is formatted as an enum
+    enum_map: Option<Arc<EnumMap>>,
+    /// Value of the wave at the cursor of the wave window
+    cursor_value: Option<String>,
 }
 
 //FIXME: for testing only; this should be removed once sigwindow is stable
@@ -47,6 +96,8 @@ impl Default for DisplayedWave {
         DisplayedWave {
             wave_content: Arc::new(InMemWave::default()),
             display_conf: Option::default(),
+            enum_map: None,
+            cursor_value: None,
         }
     }
 }
@@ -55,20 +106,107 @@ impl DisplayedWave {
     pub fn get_wave(&self) -> &Arc<InMemWave> {
         &self.wave_content
     }
+
+    pub fn get_enum_map(&self) -> Option<&EnumMap> {
+        self.enum_map.as_deref()
+    }
+
+    /// Show the values of this wave by their names in `enum_map`
+    pub fn set_enum_map(&mut self, enum_map: Arc<EnumMap>) {
+        self.enum_map = Some(enum_map);
+        self.set_format(WaveFormat::Enum);
+    }
+
+    /// Name a single value of this wave
+    pub fn add_enum_entry(&mut self, value: u64, name: String) {
+        let enum_map = self.enum_map.get_or_insert_with(Arc::default);
+        Arc::make_mut(enum_map).insert(value, name);
+        self.set_format(WaveFormat::Enum);
+    }
+
+    /// Show the values of this wave as text in `format`; this switches off analog plotting
+    pub fn set_format(&mut self, format: WaveFormat) {
+        let mut display_conf = self.display_conf.unwrap_or_default();
+        display_conf.format = format;
+        display_conf.analog = None;
+        self.display_conf = Some(display_conf);
+    }
+
+    /// Plot the values of this wave, scaled to fit its row
+    pub fn set_analog(&mut self, style: AnalogStyle) {
+        let mut display_conf = self.display_conf.unwrap_or_default();
+        display_conf.analog = Some(style);
+        self.display_conf = Some(display_conf);
+    }
+
+    pub fn set_row_height(&mut self, row_height: f32) {
+        let mut display_conf = self.display_conf.unwrap_or_default();
+        display_conf.row_height = row_height.clamp(MIN_ROW_HEIGHT, MAX_ROW_HEIGHT);
+        self.display_conf = Some(display_conf);
+    }
+
+    /// Value of this wave at `time`, formatted the same way as its waveform
+    pub fn value_at(&self, time: Toffset) -> Option<String> {
+        let wave = &self.wave_content;
+        let format = |droplet: Droplet| {
+            format_value(
+                droplet,
+                self.display_conf.unwrap_or_default(),
+                wave.get_literals(),
+                self.get_enum_map(),
+                wave.get_width(),
+                CURSOR_VALUE_CHARS,
+            )
+        };
+        match wave.value_at(time) {
+            Some((_, droplet)) => Some(format(droplet)),
+            // the cursor may have been left outside of the window
+            None => wave
+                .window(time, time.saturating_add(1))
+                .ok()
+                .and_then(|window| window.value_at(time).map(|(_, droplet)| format(droplet))),
+        }
+    }
+
+    pub fn set_cursor_value(&mut self, cursor_value: Option<String>) {
+        self.cursor_value = cursor_value;
+    }
+
+    /// Load the puddles covering `[start, end]`, releasing the ones outside of it
+    pub fn set_window(&mut self, start: Toffset, end: Toffset) -> Result<(), Waverr> {
+        self.wave_content = Arc::new(self.wave_content.window(start, end.saturating_add(1))?);
+        Ok(())
+    }
 }
 
 impl From<Arc<InMemWave>> for DisplayedWave {
     fn from(imw: Arc<InMemWave>) -> Self {
-        DisplayedWave {
-            wave_content: imw,
+        let mut wave = DisplayedWave {
+            enum_map: None,
             display_conf: Option::default(),
+            wave_content: imw.clone(),
+            cursor_value: None,
+        };
+        if let Some(enum_map) = imw.get_enum_map() {
+            wave.set_enum_map(enum_map.clone());
+        }
+        // reals are usually analog quantities, so plot them by default
+        if imw.get_sig_type() == SigType::Float {
+            wave.set_format(WaveFormat::F64);
+            wave.set_analog(AnalogStyle::Line);
+            wave.set_row_height(ANALOG_ROW_HEIGHT);
         }
+        wave
     }
 }
 
 impl std::fmt::Display for DisplayedWave {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.wave_content.fmt(f)
+        self.wave_content.fmt(f)?;
+        if let Some(value) = self.cursor_value.as_ref() {
+            write!(f, " = {}", value)?;
+        }
+        Ok(())
    }
 }
 
@@ -96,21 +234,50 @@ impl std::fmt::Display for WaveColors {
 pub fn generate_canvas_text(
     data: Droplet,
     display_options: WaveDisplayOptions,
+    literals: Option<&[String]>,
+    enum_map: Option<&EnumMap>,
     bitwidth: usize,
     space: f32,
 ) -> Option<Text> {
-    let str_format = display_options.format;
     if space < TEXT_SIZE {
         return None;
     }
     let visible_chars = (space / TEXT_SIZE).ceil() as usize;
     log::info!("payload is {:?}", data.get_data());
 
-    let value = format_payload(data, str_format,bitwidth,visible_chars);
+    let value = format_value(
+        data,
+        display_options,
+        literals,
+        enum_map,
+        bitwidth,
+        visible_chars,
+    );
    log::info!("string value is {}",value);
     Some(Text::from(value))
 }
 
+/// Format a value of a wave, in at most `visible_chars` characters. Enumerated signals are shown
+/// by the name of their literal, unless they are explicitly shown as text
+pub fn format_value(
+    data: Droplet,
+    display_options: WaveDisplayOptions,
+    literals: Option<&[String]>,
+    enum_map: Option<&EnumMap>,
+    bitwidth: usize,
+    visible_chars: usize,
+) -> String {
+    match (literals, enum_map, display_options.format) {
+        (_, Some(enum_map), WaveFormat::Enum) => {
+            format_enum(data, enum_map, bitwidth, visible_chars)
+        }
+        (Some(literals), _, format) if format != WaveFormat::Ascii => {
+            format_literal(data, literals, bitwidth, visible_chars)
+        }
+        (_, _, format) => format_payload(data, format, bitwidth, visible_chars),
+    }
+}
+
 #[derive(Clone, Debug)]
 /// Wave state for single bit signals
 ///
@@ -123,6 +290,28 @@ pub enum SBWaveState {
     Z,
 }
 
+impl SBWaveState {
+    /// State of a single bit signal that changed to `data`
+    pub fn from_data(data: &[u8]) -> SBWaveState {
+        match data_zx_state(data, 1) {
+            Some(ZxState::X) => SBWaveState::X,
+            Some(ZxState::Z) => SBWaveState::Z,
+            None if data[0] & 0x1 != 0 => SBWaveState::High,
+            None => SBWaveState::Low,
+        }
+    }
+
+    /// Height of the wave above the bottom of its row; unknown values sit at the bottom, under
+    /// the band that covers them
+    pub fn level(&self) -> f32 {
+        match self {
+            SBWaveState::High => WAVEHEIGHT,
+            SBWaveState::Z => WAVEHEIGHT / 2.0,
+            SBWaveState::Beginning | SBWaveState::Low | SBWaveState::X => 0.0,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 enum WaveColors {
     Green,
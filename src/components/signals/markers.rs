@@ -0,0 +1,80 @@
+use wave2_wavedb::puddle::Toffset;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// A named point in time the user pinned to the wave window
+pub struct Marker {
+    pub name: String,
+    pub time: Toffset,
+}
+
+#[derive(Clone, Debug, Default)]
+/// Markers of the wave window, kept in time order. The active marker is the one last placed or
+/// jumped to; it is the one that gets renamed and deleted
+pub struct Markers {
+    markers: Vec<Marker>,
+    active: Option<usize>,
+    /// Number used to name the next marker placed
+    next_id: usize,
+}
+
+impl Markers {
+    /// Every marker, in time order, along with whether it is the active one
+    pub fn iter(&self) -> impl Iterator<Item = (&Marker, bool)> {
+        let active = self.active;
+        self.markers
+            .iter()
+            .enumerate()
+            .map(move |(idx, marker)| (marker, Some(idx) == active))
+    }
+
+    /// Place a marker at `time`, named `M<n>` until renamed, and make it the active one
+    pub fn add(&mut self, time: Toffset) {
+        self.next_id += 1;
+        let idx = self.markers.partition_point(|marker| marker.time <= time);
+        self.markers.insert(
+            idx,
+            Marker {
+                name: format!("M{}", self.next_id),
+                time,
+            },
+        );
+        self.active = Some(idx);
+    }
+
+    pub fn remove_active(&mut self) -> Option<Marker> {
+        let idx = self.active.take()?;
+        Some(self.markers.remove(idx))
+    }
+
+    /// Rename the active marker; blank names are ignored
+    pub fn rename_active(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if let Some(marker) = self.active.and_then(|idx| self.markers.get_mut(idx)) {
+            marker.name = name.to_string();
+        }
+    }
+
+    /// Activate the first marker after `time`, returning where it is
+    pub fn next_after(&mut self, time: Toffset) -> Option<Toffset> {
+        let idx = self.markers.partition_point(|marker| marker.time <= time);
+        self.activate(idx)
+    }
+
+    /// Activate the last marker before `time`, returning where it is
+    pub fn prev_before(&mut self, time: Toffset) -> Option<Toffset> {
+        let idx = self
+            .markers
+            .partition_point(|marker| marker.time < time)
+            .checked_sub(1)?;
+        self.activate(idx)
+    }
+
+    fn activate(&mut self, idx: usize) -> Option<Toffset> {
+        let time = self.markers.get(idx)?.time;
+        self.active = Some(idx);
+        Some(time)
+    }
+}
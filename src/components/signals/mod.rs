@@ -1,12 +1,17 @@
+pub mod markers;
 pub mod sigwindow;
 pub mod wavewindow;
 
 #[allow(dead_code)]
 /// Module for managing colors -> wave status
 pub mod display_wave;
+use display_wave::AnalogStyle;
 use std::sync::Arc;
 use wave2_wavedb::errors::Waverr;
-use wave2_wavedb::storage::in_memory::InMemWave;
+use wave2_wavedb::formatting::{EnumMap, WaveFormat};
+use wave2_wavedb::puddle::{SignalId, Toffset};
+use wave2_wavedb::timescale::Timescale;
+use wave2_wavedb::storage::in_memory::{Edge, InMemWave};
 
 
 
@@ -17,10 +22,54 @@ pub enum Message {
     SelectedWave(usize), 
     ClearWaves,
     RemoveSelected,
+    /// Show the selected wave in the given format
+    SetFormat(WaveFormat),
+    /// Show the selected wave as fixed point, using the fractional bit count entered in the
+    /// sigviewer; signed if true
+    SetFixedPoint(bool),
+    UpdateFracBits(String),
+    /// Plot the selected wave in the given style
+    SetAnalog(AnalogStyle),
+    /// Resize the row of the selected wave; the entry is a height in pixels
+    UpdateRowHeight(String),
+    /// Enum maps were loaded for these signals
+    UpdateEnumMaps(Result<Arc<Vec<(SignalId, EnumMap)>>, Arc<Waverr>>),
+    /// Name a value of the selected wave; the entry reads `value = name`
+    AddEnumEntry(String),
+    UpdateEnumEntry(String),
+    EnumMapSaved(Result<(), Arc<Waverr>>),
+    /// Name the active marker of the wave window
+    RenameMarker(String),
+    UpdateMarkerName(String),
+    /// Add a wave computed from the waves of the wave window; the entry reads `name = expression`
+    AddDerived(String),
+    UpdateDerived(String),
 
     ///Messages that are only used by wavewindow
-    UpdateCursor(u32),
-    UpdateBounds((u32, u32)),
+    UpdateCursor(Toffset),
+    /// Place the secondary cursor; times are measured from the cursor to it and to every marker
+    UpdateSecondaryCursor(Toffset),
+    /// Place a marker at the cursor
+    AddMarker,
+    /// Delete the active marker
+    RemoveMarker,
+    /// Move the cursor to the next marker after it, or to the previous one if false
+    JumpToMarker(bool),
+    /// Move the cursor to the next edge of the selected wave, or to the previous one if false
+    JumpToEdge(Edge, bool),
+    /// Move the cursor to the next time the selected wave changes to a value matching the
+    /// search bar, or the condition in it starts to hold; to the previous one if false
+    Search(bool),
+    UpdateSearch(String),
+    /// First and last timestamps of the signal dump
+    UpdateBounds((Toffset, Toffset)),
+    /// Show this range of time in the wave window
+    UpdateView((Toffset, Toffset)),
+    /// Show the whole signal dump
+    ZoomToFit,
+    /// Scale the visible range around its center; factors below 1 zoom in
+    Zoom(f64),
+    UpdateTimescale(Timescale),
 
 
     ///Messages that are only used by sigviewer
@@ -1,18 +1,58 @@
-use super::display_wave::DisplayedWave;
+use super::display_wave::{AnalogStyle, DisplayedWave};
 use crate::components::shared::cell_list::{CellList, ListNodeState};
-use iced::{Column, Container, Element, Row};
+use iced::{text_input, Column, Container, Element, Row, TextInput};
 use strum_macros;
 use wave2_custom_widgets::traits::CellOption;
+use wave2_wavedb::formatting::WaveFormat;
 use super::Message;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
-//TODO: add options, move to its own module?
+//TODO: move to its own module?
 pub enum WaveOptions {
+    Binary,
+    Octal,
+    Hex,
+    Decimal,
+    #[strum(serialize = "Signed Decimal")]
+    SDecimal,
+    #[strum(serialize = "Fixed Point")]
+    UFixed,
+    #[strum(serialize = "Signed Fixed Point")]
+    SFixed,
+    #[strum(serialize = "Half Float")]
+    F16,
+    #[strum(serialize = "Float")]
+    F32,
+    #[strum(serialize = "Double")]
+    F64,
+    #[strum(serialize = "ASCII")]
+    Ascii,
+    Enum,
+    #[strum(serialize = "Analog Line")]
+    AnalogLine,
+    #[strum(serialize = "Analog Step")]
+    AnalogStep,
     Delete,
 }
 
 impl WaveOptions {
-    const ALL: [WaveOptions; 1] = [WaveOptions::Delete];
+    const ALL: [WaveOptions; 15] = [
+        WaveOptions::Binary,
+        WaveOptions::Octal,
+        WaveOptions::Hex,
+        WaveOptions::Decimal,
+        WaveOptions::SDecimal,
+        WaveOptions::UFixed,
+        WaveOptions::SFixed,
+        WaveOptions::F16,
+        WaveOptions::F32,
+        WaveOptions::F64,
+        WaveOptions::Ascii,
+        WaveOptions::Enum,
+        WaveOptions::AnalogLine,
+        WaveOptions::AnalogStep,
+        WaveOptions::Delete,
+    ];
 }
 
 impl CellOption for WaveOptions {
@@ -24,6 +64,20 @@ impl CellOption for WaveOptions {
 
     fn to_message(&self) -> Self::Message {
         match self {
+            WaveOptions::Binary => Message::SetFormat(WaveFormat::Binary),
+            WaveOptions::Octal => Message::SetFormat(WaveFormat::Octal),
+            WaveOptions::Hex => Message::SetFormat(WaveFormat::Hex),
+            WaveOptions::Decimal => Message::SetFormat(WaveFormat::Decimal),
+            WaveOptions::SDecimal => Message::SetFormat(WaveFormat::SDecimal),
+            WaveOptions::UFixed => Message::SetFixedPoint(false),
+            WaveOptions::SFixed => Message::SetFixedPoint(true),
+            WaveOptions::F16 => Message::SetFormat(WaveFormat::F16),
+            WaveOptions::F32 => Message::SetFormat(WaveFormat::F32),
+            WaveOptions::F64 => Message::SetFormat(WaveFormat::F64),
+            WaveOptions::Ascii => Message::SetFormat(WaveFormat::Ascii),
+            WaveOptions::Enum => Message::SetFormat(WaveFormat::Enum),
+            WaveOptions::AnalogLine => Message::SetAnalog(AnalogStyle::Line),
+            WaveOptions::AnalogStep => Message::SetAnalog(AnalogStyle::Step),
             WaveOptions::Delete => Message::RemoveSelected,
         }
     }
@@ -33,6 +87,21 @@ impl CellOption for WaveOptions {
 pub struct SigViewer {
     waves_state: CellList<DisplayedWave, WaveOptions>,
     selected: Option<Vec<usize>>,
+    /// Fractional bits used when a wave is shown as fixed point
+    frac_bits: String,
+    frac_bits_state: text_input::State,
+    /// `value = name` entry for the enum map of the selected wave
+    enum_entry: String,
+    enum_entry_state: text_input::State,
+    /// Row height, in pixels, for the selected wave
+    row_height: String,
+    row_height_state: text_input::State,
+    /// New name for the active marker of the wave window
+    marker_name: String,
+    marker_name_state: text_input::State,
+    /// `name = expression` definition of a derived wave
+    derived: String,
+    derived_state: text_input::State,
 }
 
 impl Default for SigViewer {
@@ -41,6 +110,16 @@ impl Default for SigViewer {
             waves_state: CellList::default().set_cell_padding(4).set_text_size(11),
             //.set_spacing(wavewindow::BUFFER_PX as u16),
             selected: Option::default(),
+            frac_bits: String::from("0"),
+            frac_bits_state: text_input::State::default(),
+            enum_entry: String::new(),
+            enum_entry_state: text_input::State::default(),
+            row_height: String::new(),
+            row_height_state: text_input::State::default(),
+            marker_name: String::new(),
+            marker_name_state: text_input::State::default(),
+            derived: String::new(),
+            derived_state: text_input::State::default(),
         }
     }
 }
@@ -70,6 +149,36 @@ impl SigViewer {
                 }
                 self.selected = None;
             }
+            Message::UpdateFracBits(frac_bits) => {
+                self.frac_bits = frac_bits;
+            }
+            Message::UpdateEnumEntry(enum_entry) => {
+                self.enum_entry = enum_entry;
+            }
+            Message::AddEnumEntry(_) => {
+                self.enum_entry.clear();
+            }
+            Message::UpdateRowHeight(row_height) => {
+                self.row_height = row_height;
+            }
+            Message::UpdateMarkerName(marker_name) => {
+                self.marker_name = marker_name;
+            }
+            Message::RenameMarker(_) => {
+                self.marker_name.clear();
+            }
+            Message::UpdateDerived(derived) => {
+                self.derived = derived;
+            }
+            Message::AddDerived(_) => {
+                self.derived.clear();
+            }
+            // formats only change how the wavewindow draws values
+            Message::SetFormat(_)
+            | Message::SetFixedPoint(_)
+            | Message::SetAnalog(_)
+            | Message::UpdateEnumMaps(_)
+            | Message::EnumMapSaved(_) => {}
             Message::CellListPlaceholder => {
                 println!("Cell list interaction, impl me");
             }
@@ -89,9 +198,26 @@ impl SigViewer {
             }
         }
     }
+    /// Show the value of each wave at the cursor of the wave window next to its name
+    pub fn set_cursor_values(&mut self, values: Vec<Option<String>>) {
+        for (wave, value) in self.waves_state.payloads_mut().zip(values) {
+            wave.set_cursor_value(value);
+        }
+    }
+
     pub fn view(&mut self) -> Element<Message> {
         let SigViewer {
             waves_state,
+            frac_bits,
+            frac_bits_state,
+            enum_entry,
+            enum_entry_state,
+            row_height,
+            row_height_state,
+            marker_name,
+            marker_name_state,
+            derived,
+            derived_state,
             ..
             //wavewindow,
             //live_waves,
@@ -115,6 +241,49 @@ impl SigViewer {
         }
 
         let cl = waves_state.view(click_func, double_click);
+        let frac_bits_input = TextInput::new(
+            frac_bits_state,
+            "Fractional bits",
+            frac_bits.as_str(),
+            Message::UpdateFracBits,
+        )
+        .size(11)
+        .padding(4);
+        let enum_entry_input = TextInput::new(
+            enum_entry_state,
+            "Name a value: 0x3 = IDLE",
+            enum_entry.as_str(),
+            Message::UpdateEnumEntry,
+        )
+        .on_submit(Message::AddEnumEntry(enum_entry.clone()))
+        .size(11)
+        .padding(4);
+        let row_height_input = TextInput::new(
+            row_height_state,
+            "Row height",
+            row_height.as_str(),
+            Message::UpdateRowHeight,
+        )
+        .size(11)
+        .padding(4);
+        let marker_name_input = TextInput::new(
+            marker_name_state,
+            "Rename the active marker",
+            marker_name.as_str(),
+            Message::UpdateMarkerName,
+        )
+        .on_submit(Message::RenameMarker(marker_name.clone()))
+        .size(11)
+        .padding(4);
+        let derived_input = TextInput::new(
+            derived_state,
+            "Derive a wave: hi = bus[15:8]",
+            derived.as_str(),
+            Message::UpdateDerived,
+        )
+        .on_submit(Message::AddDerived(derived.clone()))
+        .size(11)
+        .padding(4);
 
         let pick_list = Column::new()
             //.push(
@@ -125,6 +294,11 @@ impl SigViewer {
             //        .size(wavewindow::TS_FONT_SIZE as u16),
             //)
             .push(cl)
+            .push(frac_bits_input)
+            .push(enum_entry_input)
+            .push(row_height_input)
+            .push(marker_name_input)
+            .push(derived_input)
             .width(iced::Length::Fill)
             .height(iced::Length::Fill)
             .max_width(400)
@@ -1,13 +1,24 @@
 use iced::{
-    canvas::{self, event, Canvas, Cursor, Event, Frame, Geometry, Path, Stroke},
-    mouse, Color, Element, HorizontalAlignment, Length, Point, Rectangle,
+    canvas::{self, event, path, Canvas, Cursor, Event, Frame, Geometry, Path, Stroke, Text},
+    keyboard, mouse, Color, Column, Container, Element, HorizontalAlignment, Length, Point,
+    Rectangle, Size, VerticalAlignment,
 };
 
-use super::display_wave::{generate_canvas_text, DisplayedWave, SBWaveState};
+use super::display_wave::{
+    generate_canvas_text, AnalogStyle, DisplayedWave, SBWaveState, WaveDisplayOptions,
+};
+use super::markers::Markers;
 use super::Message;
+use crate::components::widget_bar::WidgetBar;
 use log::info;
-use wave2_custom_widgets::widget::hscroll;
-use wave2_custom_widgets::widget::hscroll::HScroll;
+use std::sync::Arc;
+use wave2_wavedb::errors::Waverr;
+use wave2_wavedb::expr::Expression;
+use wave2_wavedb::formatting::{data_zx_state, zx_state, EnumMap, WaveFormat, ZxState};
+use wave2_wavedb::puddle::{SignalId, Toffset};
+use wave2_wavedb::search::ValueQuery;
+use wave2_wavedb::storage::in_memory::{ColumnChanges, Edge, InMemWave};
+use wave2_wavedb::timescale::{step_1_2_5, TimeUnit, Timescale};
 
 pub const BUFFER_PX: f32 = 1.5;
 pub const WAVEHEIGHT: f32 = 16.0;
@@ -18,6 +29,19 @@ pub const TS_FONT_SIZE: f32 = 12.0;
 /// it clips the black bounding box of the wave window and looks bad
 const TS_CLIP_RANGE: f32 = 5.0;
 
+/// Minimum distance, in pixels, between two delimiters on the ruler
+const RULER_SPACING: f32 = 100.0;
+/// Narrowest range of ticks the wave window can zoom in to
+const MIN_VIEW_SPAN: Toffset = 10;
+/// Fraction of the visible range a single zoom step keeps
+pub const ZOOM_STEP: f64 = 0.8;
+/// Command and the arrow keys pan by a 1/PAN_FRACTION of the visible range
+const PAN_FRACTION: i64 = 4;
+/// Pixels panned per line scrolled
+const SCROLL_LINE_PX: f32 = 60.0;
+/// Drags shorter than this, in pixels, are treated as clicks that place the cursor
+const DRAG_THRESHOLD: f32 = 4.0;
+
 const BLUE: Color = Color::from_rgba(
     0x1b as f32 / 255.0,
     0x0a as f32 / 255.0,
@@ -25,12 +49,26 @@ const BLUE: Color = Color::from_rgba(
     0.25,
 );
 const GREEN: Color = Color::from_rgb(0.0, 1.0, 0.0);
+/// Fill for unknown values
+const RED: Color = Color::from_rgba(1.0, 0.0, 0.0, 0.6);
+/// Fill for pixel columns the wave changes more than once in
+const BUSY: Color = Color::from_rgba(0.0, 1.0, 0.0, 0.5);
 const ORANGE: Color = Color::from_rgba(
     0xf5 as f32 / 255.0,
     0xc1 as f32 / 255.0,
     0x87 as f32 / 255.0,
     0.4,
 );
+const ORANGE_TEXT: Color = Color::from_rgb(
+    0xf5 as f32 / 255.0,
+    0xc1 as f32 / 255.0,
+    0x87 as f32 / 255.0,
+);
+const SELECTION: Color = Color::from_rgba(1.0, 1.0, 1.0, 0.15);
+const MARKER: Color = Color::from_rgba(0.4, 0.8, 1.0, 0.6);
+const MARKER_TEXT: Color = Color::from_rgb(0.4, 0.8, 1.0);
+const SECONDARY: Color = Color::from_rgba(1.0, 0.4, 1.0, 0.6);
+const SECONDARY_TEXT: Color = Color::from_rgb(1.0, 0.4, 1.0);
 
 pub struct WaveWindow<'a> {
     signals: &'a [DisplayedWave],
@@ -45,17 +83,29 @@ pub struct WaveWindowState {
     cache: canvas::Cache,
     cursor_cache: canvas::Cache,
     frame_state: FrameState,
-    scroll_state: hscroll::State,
+    selected: Option<usize>,
+    /// Fractional bits used when a wave is shown as fixed point
+    frac_bits: u32,
+    widget_bar: WidgetBar,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 /// State for handling zoom state
 pub struct FrameState {
-    start_time: u32,
-    end_time: u32,
-    ns_per_unit: f32,
-    pub cursor_location: u32,
-    offset: f32,
+    /// First and last time shown in the wave window; how many ticks a pixel covers follows from
+    /// these and the width of the window
+    start_time: Toffset,
+    end_time: Toffset,
+    pub cursor_location: Toffset,
+    /// Placed with a right click; the time from the cursor to it is shown along with the markers
+    secondary_cursor: Option<Toffset>,
+    markers: Markers,
+    /// First and last timestamps of the signal dump
+    bounds: (Toffset, Toffset),
+    /// Horizontal extent, in pixels, of the region being dragged out to zoom into
+    selection: Option<(f32, f32)>,
+    modifiers: keyboard::Modifiers,
+    timescale: Timescale,
 }
 
 impl Default for FrameState {
@@ -63,31 +113,70 @@ impl Default for FrameState {
         FrameState {
             start_time: 0,
             end_time: 1000,
-            ns_per_unit: 1.0,
             cursor_location: 0,
-            offset: 0.0,
+            secondary_cursor: None,
+            markers: Markers::default(),
+            bounds: (0, 1000),
+            selection: None,
+            modifiers: keyboard::Modifiers::default(),
+            timescale: Timescale::default(),
         }
     }
 }
 
-impl WaveWindowState {
-    pub fn view(&mut self) -> Element<Message> {
+impl FrameState {
+    /// Clamp the range `[start, end]` to the signal dump, keeping its span where possible
+    fn clamp_view(&self, start: Toffset, end: Toffset) -> (Toffset, Toffset) {
+        let (first, last) = self.bounds;
+        let span = end.saturating_sub(start).max(MIN_VIEW_SPAN);
+        if span >= last - first {
+            return (first, last.max(first + MIN_VIEW_SPAN));
+        }
+        let start = start.max(first).min(last - span);
+        (start, start + span)
+    }
 
+    /// Range shown after zooming by `factor` around `center`; factors below 1 zoom in
+    fn zoomed(&self, center: Toffset, factor: f64) -> (Toffset, Toffset) {
+        let scale = |distance: Toffset| (distance as f64 * factor).round() as Toffset;
+        let center = center.max(self.start_time).min(self.end_time);
+        self.clamp_view(
+            center.saturating_sub(scale(center - self.start_time)),
+            center.saturating_add(scale(self.end_time - center)),
+        )
+    }
 
-        log::info!("offset is {}", self.scroll_state.get_offset());
-        let val = HScroll::new(&mut self.scroll_state).scrollbar_width(10);
+    /// Range shown after moving the window by `ticks`; negative values move towards the start
+    fn panned(&self, ticks: i64) -> (Toffset, Toffset) {
+        let shift = |time: Toffset| {
+            if ticks < 0 {
+                time.saturating_sub(ticks.unsigned_abs())
+            } else {
+                time.saturating_add(ticks as Toffset)
+            }
+        };
+        let start = shift(self.start_time);
+        self.clamp_view(start, start.saturating_add(self.end_time - self.start_time))
+    }
+}
 
-        val.push(
-            Canvas::new(WaveWindow {
-                signals: &self.live_waves[..],
-                frame_state: &mut self.frame_state,
-                wave_cache: &self.cache,
-                cursor_cache: &self.cursor_cache,
-            })
-            .width(Length::Units(u16::MAX))
-            .height(Length::Fill),
+impl WaveWindowState {
+    pub fn view(&mut self) -> Element<Message> {
+        let canvas = Canvas::new(WaveWindow {
+            signals: &self.live_waves[..],
+            frame_state: &mut self.frame_state,
+            wave_cache: &self.cache,
+            cursor_cache: &self.cursor_cache,
+        })
+        .width(Length::Fill)
+        .height(Length::Fill);
+        Container::new(
+            Column::new()
+                .push(self.widget_bar.view())
+                .push(canvas)
+                .spacing(4),
         )
-        .width(Length::Shrink)
+        .width(Length::Fill)
         .height(Length::Fill)
         .padding(10)
         .into()
@@ -99,23 +188,281 @@ impl WaveWindowState {
                 self.frame_state.cursor_location = cursor_location;
                 self.redraw_cursor();
             }
-            Message::UpdateBounds((start, end)) => {
-                self.frame_state.start_time = start;
-                self.frame_state.end_time = end;
+            Message::UpdateSecondaryCursor(time) => {
+                self.frame_state.secondary_cursor = Some(time);
+                self.redraw_cursor();
+            }
+            Message::AddMarker => {
+                self.frame_state
+                    .markers
+                    .add(self.frame_state.cursor_location);
+                self.redraw_cursor();
+            }
+            Message::RemoveMarker => {
+                self.frame_state.markers.remove_active();
+                self.redraw_cursor();
+            }
+            Message::RenameMarker(name) => {
+                self.frame_state.markers.rename_active(name.as_str());
+                self.redraw_cursor();
+            }
+            Message::JumpToMarker(forward) => {
+                let cursor_location = self.frame_state.cursor_location;
+                let markers = &mut self.frame_state.markers;
+                let target = if forward {
+                    markers.next_after(cursor_location)
+                } else {
+                    markers.prev_before(cursor_location)
+                };
+                if let Some(time) = target {
+                    self.frame_state.cursor_location = time;
+                    self.show_time(time);
+                }
+                self.redraw_cursor();
+            }
+            Message::JumpToEdge(edge, forward) => {
+                let cursor_location = self.frame_state.cursor_location;
+                let wave = match self.selected.and_then(|offset| self.live_waves.get(offset)) {
+                    Some(wave) => wave.get_wave(),
+                    None => return,
+                };
+                let target = if forward {
+                    wave.next_edge(cursor_location, edge)
+                } else {
+                    wave.prev_edge(cursor_location, edge)
+                };
+                match target {
+                    Ok(Some(time)) => {
+                        self.frame_state.cursor_location = time;
+                        self.show_time(time);
+                        self.redraw_cursor();
+                    }
+                    Ok(None) => {}
+                    Err(err) => log::info!("Failed to find the next edge with err {:?}", err),
+                }
+            }
+            Message::UpdateSearch(search) => self.widget_bar.set_search(search),
+            Message::Search(forward) => {
+                let cursor_location = self.frame_state.cursor_location;
+                // searches starting with a comparison query the selected wave; anything else is
+                // a condition over the wave window
+                let target = if ValueQuery::is_value_query(self.widget_bar.search()) {
+                    self.search_selected(cursor_location, forward)
+                } else {
+                    self.search_start(cursor_location, forward)
+                };
+                self.widget_bar
+                    .set_search_error(target.as_ref().err().map(|err| err.to_string()));
+                match target {
+                    Ok(Some(time)) => {
+                        self.frame_state.cursor_location = time;
+                        self.show_time(time);
+                        self.redraw_cursor();
+                    }
+                    Ok(None) => {}
+                    Err(err) => log::warn!("Failed to search the waves with err {:?}", err),
+                }
+            }
+            Message::UpdateBounds(bounds) => {
+                self.frame_state.bounds = bounds;
+                self.set_view(bounds);
+            }
+            Message::UpdateView(view) => self.set_view(view),
+            Message::ZoomToFit => self.set_view(self.frame_state.bounds),
+            Message::Zoom(factor) => {
+                let FrameState {
+                    start_time,
+                    end_time,
+                    ..
+                } = self.frame_state;
+                let center = start_time + (end_time - start_time) / 2;
+                self.set_view(self.frame_state.zoomed(center, factor));
+            }
+            Message::UpdateTimescale(timescale) => {
+                self.frame_state.timescale = timescale;
+                self.request_redraw();
+                self.redraw_cursor();
             }
             Message::AddWave(imw) => match imw {
                 Ok(wave) => {
-                    self.live_waves.push(DisplayedWave::from(wave));
-                    self.request_redraw();
+                    let mut wave = DisplayedWave::from(wave);
+                    match wave.set_window(self.frame_state.start_time, self.frame_state.end_time) {
+                        Ok(()) => {
+                            self.live_waves.push(wave);
+                            self.request_redraw();
+                        }
+                        Err(err) => log::info!("Failed to load {} with err {:?}", wave, err),
+                    }
                 }
                 Err(err) => log::info!("Failed to add wave with err {:?}", err),
             },
+            Message::SelectedWave(offset) => {
+                self.selected = Some(offset);
+            }
+            Message::RemoveSelected => {
+                if let Some(offset) = self.selected.take() {
+                    if offset < self.live_waves.len() {
+                        self.live_waves.remove(offset);
+                        self.request_redraw();
+                    }
+                }
+            }
+            Message::UpdateFracBits(frac_bits) => {
+                if let Ok(frac_bits) = frac_bits.trim().parse() {
+                    self.frac_bits = frac_bits;
+                }
+            }
+            Message::SetFixedPoint(signed) => {
+                let format = if signed {
+                    WaveFormat::SFixed(self.frac_bits)
+                } else {
+                    WaveFormat::UFixed(self.frac_bits)
+                };
+                self.set_selected_format(format);
+            }
+            Message::SetFormat(format) => self.set_selected_format(format),
+            Message::SetAnalog(style) => {
+                if let Some(wave) = self.selected_wave_mut() {
+                    wave.set_analog(style);
+                    self.request_redraw();
+                }
+            }
+            Message::UpdateRowHeight(row_height) => {
+                if let Ok(row_height) = row_height.trim().parse() {
+                    if let Some(wave) = self.selected_wave_mut() {
+                        wave.set_row_height(row_height);
+                        self.request_redraw();
+                    }
+                }
+            }
+            Message::UpdateEnumMaps(Ok(enum_maps)) => {
+                for wave in self.live_waves.iter_mut() {
+                    let signal_id = wave.get_wave().get_signal_id();
+                    if let Some((_, enum_map)) = enum_maps.iter().find(|(id, _)| *id == signal_id) {
+                        wave.set_enum_map(Arc::new(enum_map.clone()));
+                    }
+                }
+                self.request_redraw();
+            }
+            Message::UpdateEnumMaps(Err(err)) => {
+                log::info!("Failed to load enum maps with err {:?}", err)
+            }
+            Message::AddEnumEntry(entry) => match EnumMap::parse_entry(entry.as_str()) {
+                Ok((value, name)) => {
+                    if let Some(wave) = self.selected_wave_mut() {
+                        wave.add_enum_entry(value, name);
+                        self.request_redraw();
+                    }
+                }
+                Err(err) => log::info!("Ignoring enum entry {}: {}", entry, err),
+            },
+            Message::EnumMapSaved(Err(err)) => {
+                log::info!("Failed to save enum map with err {:?}", err)
+            }
             _ => {
                 log::info!("Not covered");
             }
         }
     }
 
+    /// Value of every wave at the cursor, in the order the waves are listed
+    pub fn cursor_values(&self) -> Vec<Option<String>> {
+        self.live_waves
+            .iter()
+            .map(|wave| wave.value_at(self.frame_state.cursor_location))
+            .collect()
+    }
+
+    /// Every wave of the wave window, in the order the waves are listed
+    pub fn waves(&self) -> Vec<Arc<InMemWave>> {
+        self.live_waves
+            .iter()
+            .map(|wave| wave.get_wave().clone())
+            .collect()
+    }
+
+    /// Signal id and enum map of the selected wave, if it has one
+    pub fn selected_enum_map(&self) -> Option<(SignalId, EnumMap)> {
+        let wave = self.live_waves.get(self.selected?)?;
+        let enum_map = wave.get_enum_map()?;
+        Some((wave.get_wave().get_signal_id(), enum_map.clone()))
+    }
+
+    /// Show `[start, end]`, clamped to the signal dump, loading the puddles that cover it
+    fn set_view(&mut self, (start, end): (Toffset, Toffset)) {
+        let (start, end) = self.frame_state.clamp_view(start, end);
+        self.frame_state.start_time = start;
+        self.frame_state.end_time = end;
+        for wave in self.live_waves.iter_mut() {
+            if let Err(err) = wave.set_window(start, end) {
+                log::info!("Failed to load {} with err {:?}", wave, err);
+            }
+        }
+        self.request_redraw();
+        self.redraw_cursor();
+    }
+
+    /// Next time after `time` that the selected wave matches the value query in the search bar,
+    /// or the previous one if not `forward`
+    fn search_selected(&self, time: Toffset, forward: bool) -> Result<Option<Toffset>, Waverr> {
+        let query = ValueQuery::parse(self.widget_bar.search())?;
+        let wave = match self.selected.and_then(|offset| self.live_waves.get(offset)) {
+            Some(wave) => wave.get_wave(),
+            None => return Err(Waverr::SearchErr("select a wave to search".into())),
+        };
+        if forward {
+            query.next_match(wave, time)
+        } else {
+            query.prev_match(wave, time)
+        }
+    }
+
+    /// Next time after `time` that the condition in the search bar starts to hold, or the
+    /// previous one if not `forward`; every signal it names must be in the wave window. The
+    /// condition is only evaluated from `time` up to the hit
+    fn search_start(&self, time: Toffset, forward: bool) -> Result<Option<Toffset>, Waverr> {
+        let expression = Expression::parse(self.widget_bar.search())?;
+        let live_waves: Vec<&InMemWave> = self
+            .live_waves
+            .iter()
+            .map(|wave| wave.get_wave().as_ref())
+            .collect();
+        let waves = expression.resolve(&live_waves)?;
+        if forward {
+            expression.next_start(&waves, time)
+        } else {
+            expression.prev_start(&waves, time)
+        }
+    }
+
+    /// Center the view on `time` if it is out of sight, keeping the zoom level
+    fn show_time(&mut self, time: Toffset) {
+        let FrameState {
+            start_time,
+            end_time,
+            ..
+        } = self.frame_state;
+        if time < start_time || time > end_time {
+            let half_span = (end_time - start_time) / 2;
+            self.set_view((
+                time.saturating_sub(half_span),
+                time.saturating_add(half_span),
+            ));
+        }
+    }
+
+    fn selected_wave_mut(&mut self) -> Option<&mut DisplayedWave> {
+        let offset = self.selected?;
+        self.live_waves.get_mut(offset)
+    }
+
+    fn set_selected_format(&mut self, format: WaveFormat) {
+        if let Some(wave) = self.selected_wave_mut() {
+            wave.set_format(format);
+            self.request_redraw();
+        }
+    }
+
     pub fn request_redraw(&mut self) {
         self.cache.clear()
     }
@@ -126,49 +473,59 @@ impl WaveWindowState {
 }
 
 impl<'a> WaveWindow<'a> {
-    fn start_time(&self) -> u32 {
+    fn start_time(&self) -> Toffset {
         self.frame_state.start_time
     }
 
-    fn end_time(&self) -> u32 {
+    fn end_time(&self) -> Toffset {
         self.frame_state.end_time
     }
 
-    fn offset(&self) -> f32 {
-        self.frame_state.offset
+    /// Number of ticks covered by a single pixel at the current zoom level
+    fn ns_per_unit(&self, bounds: &Rectangle) -> f32 {
+        (self.end_time() - self.start_time()) as f32 / bounds.width.max(1.0)
     }
 
-    fn get_timestamp(&self, xcoord: f32) -> u32 {
-        let offset = self.offset();
-
-        ((offset + xcoord) * self.frame_state.ns_per_unit).round() as u32
+    fn get_timestamp(&self, xcoord: f32, bounds: &Rectangle) -> Toffset {
+        self.start_time() + (xcoord.max(0.0) * self.ns_per_unit(bounds)).round() as Toffset
     }
 
-    fn end_window_time(&self) -> u32 {
+    fn end_window_time(&self) -> Toffset {
         return self.frame_state.end_time;
     }
 
-    fn x_abs(&self, ts: u32) -> f32 {
-        ts as f32 / (self.frame_state.ns_per_unit)
-    }
-
-    fn x_abs_cursor(&self) -> f32 {
-        self.x_abs(self.frame_state.cursor_location)
+    fn x_abs(&self, ts: Toffset, bounds: &Rectangle) -> f32 {
+        let from_start = if ts >= self.start_time() {
+            (ts - self.start_time()) as f32
+        } else {
+            -((self.start_time() - ts) as f32)
+        };
+        from_start / self.ns_per_unit(bounds)
     }
 
     /// Util for finding the x offset in the wave window where a wave should change values
     /// Used in the context of streaming through a container of "changed value" instances
-    fn xdelt_from_prev(&self, ts: u32, prev_ts: u32, _bounds: &Rectangle) -> f32 {
-        (ts - prev_ts) as f32 * self.frame_state.ns_per_unit
+    fn xdelt_from_prev(&self, ts: Toffset, prev_ts: Toffset, bounds: &Rectangle) -> f32 {
+        (ts - prev_ts) as f32 / self.ns_per_unit(bounds)
     }
 
-    fn draw_header(&self, frame: &mut Frame, bounds: Rectangle) {
-        //FIXME: need to think of way to generate uniform timestamp delimiters
-        //       probably something probably something like 1,2,5
-        let ts_width: u32 = (200.0 * self.frame_state.ns_per_unit) as u32;
+    /// Distance, in simulation ticks, between two delimiters on the ruler; picked from a 1-2-5
+    /// sequence so that delimiters are at least `RULER_SPACING` pixels apart
+    fn ruler_step(&self, bounds: &Rectangle) -> Toffset {
+        step_1_2_5((RULER_SPACING * self.ns_per_unit(bounds)) as f64)
+    }
+
+    /// Time unit that ruler labels and the cursor readout are rendered in; chosen from the ruler
+    /// step so that the unit follows the zoom level
+    fn display_unit(&self, bounds: &Rectangle) -> TimeUnit {
+        self.frame_state
+            .timescale
+            .auto_unit(self.ruler_step(bounds))
+    }
 
-        let mut prev_ts = self.start_time();
-        let mut xpos: f32 = 0.0;
+    fn draw_header(&self, frame: &mut Frame, bounds: Rectangle) {
+        let ts_width = self.ruler_step(&bounds);
+        let unit = self.display_unit(&bounds);
 
         let hdr_line = Point {
             x: 0.0,
@@ -185,11 +542,16 @@ impl<'a> WaveWindow<'a> {
         let bg_stroke = Stroke::default().with_width(1.0).with_color(BLUE);
         frame.stroke(&boundary_line, bg_stroke);
 
-        for ts in (self.start_time()..self.end_time()).step_by(ts_width as usize) {
-            xpos += self.xdelt_from_prev(ts, prev_ts, &bounds);
+        // delimiters sit on multiples of the step, wherever the window starts
+        let first_ts = match self.start_time() % ts_width {
+            0 => self.start_time(),
+            rem => self.start_time().saturating_add(ts_width - rem),
+        };
+        for ts in (first_ts..=self.end_time()).step_by(ts_width as usize) {
+            let xpos = self.x_abs(ts, &bounds);
             if xpos > TS_CLIP_RANGE {
                 frame.fill_text(canvas::Text {
-                    content: format!("{}ns", ts),
+                    content: self.frame_state.timescale.format_time(ts, unit),
                     position: Point {
                         x: xpos,
                         y: 0.0, //bounds.y,
@@ -205,16 +567,75 @@ impl<'a> WaveWindow<'a> {
                 p.line_to([xpos, bounds.y + bounds.height].into());
             });
             frame.stroke(&vert_path, bg_stroke);
-            prev_ts = ts;
+        }
+
+        // markers are flagged with their name in the ruler; the active one stands out
+        for (marker, active) in self.frame_state.markers.iter() {
+            if marker.time < self.start_time() || self.out_of_range(marker.time) {
+                continue;
+            }
+            let xpos = self.x_abs(marker.time, &bounds);
+            let marker_line = Path::new(|p| {
+                p.move_to([xpos, 0.0].into());
+                p.line_to([xpos, bounds.height].into());
+            });
+            let width = if active { 2.0 } else { 1.0 };
+            frame.stroke(
+                &marker_line,
+                Stroke::default().with_width(width).with_color(MARKER),
+            );
+            let flag_width = marker.name.chars().count() as f32 * TS_FONT_SIZE * 0.6;
+            frame.fill(
+                &Path::rectangle(
+                    Point { x: xpos, y: 0.0 },
+                    Size::new(flag_width + 2.0 * BUFFER_PX, TS_FONT_SIZE),
+                ),
+                if active { MARKER } else { Color::BLACK },
+            );
+            frame.fill_text(canvas::Text {
+                content: marker.name.clone(),
+                position: Point {
+                    x: xpos + BUFFER_PX,
+                    y: 0.0,
+                },
+                color: if active { Color::BLACK } else { MARKER_TEXT },
+                size: TS_FONT_SIZE,
+                horizontal_alignment: HorizontalAlignment::Left,
+                ..canvas::Text::default()
+            });
         }
     }
 
-    fn out_of_range(&self, time: u32) -> bool {
+    fn out_of_range(&self, time: Toffset) -> bool {
         time > self.end_window_time()
     }
 
     fn draw_cursor(&self, frame: &mut Frame, bounds: Rectangle) {
-        let cur_pos: Point = [self.x_abs_cursor(), TS_FONT_SIZE].into();
+        if let Some(secondary_cursor) = self.frame_state.secondary_cursor {
+            self.draw_time_line(frame, bounds, secondary_cursor, SECONDARY, SECONDARY_TEXT);
+        }
+        self.draw_time_line(
+            frame,
+            bounds,
+            self.frame_state.cursor_location,
+            ORANGE,
+            ORANGE_TEXT,
+        );
+    }
+
+    /// Draw a cursor at `time`, labelled with the time it is at
+    fn draw_time_line(
+        &self,
+        frame: &mut Frame,
+        bounds: Rectangle,
+        time: Toffset,
+        color: Color,
+        text_color: Color,
+    ) {
+        if time < self.start_time() || self.out_of_range(time) {
+            return;
+        }
+        let cur_pos: Point = [self.x_abs(time, &bounds), TS_FONT_SIZE].into();
         let cursor_line = Path::new(|p| {
             p.move_to(cur_pos);
             p.line_to(Point {
@@ -224,8 +645,270 @@ impl<'a> WaveWindow<'a> {
         });
         frame.stroke(
             &cursor_line,
-            Stroke::default().with_width(2.0).with_color(ORANGE),
+            Stroke::default().with_width(2.0).with_color(color),
         );
+        frame.fill_text(canvas::Text {
+            content: self
+                .frame_state
+                .timescale
+                .format_time(time, self.display_unit(&bounds)),
+            position: Point {
+                x: cur_pos.x + BUFFER_PX,
+                y: TS_FONT_SIZE + BUFFER_PX,
+            },
+            color: text_color,
+            size: TS_FONT_SIZE,
+            horizontal_alignment: HorizontalAlignment::Left,
+            ..canvas::Text::default()
+        });
+    }
+
+    /// Time from the cursor to `time`, along with the frequency something with that period
+    /// runs at
+    fn delta_readout(&self, name: &str, time: Toffset) -> String {
+        let cursor_location = self.frame_state.cursor_location;
+        let timescale = self.frame_state.timescale;
+        let (sign, delta) = if time >= cursor_location {
+            ('+', time - cursor_location)
+        } else {
+            ('-', cursor_location - time)
+        };
+        let mut readout = format!("{}: {}{}", name, sign, timescale.format_time_auto(delta));
+        if let Some(frequency) = timescale.format_frequency(delta) {
+            readout.push_str(format!(" ({})", frequency).as_str());
+        }
+        readout
+    }
+
+    /// List the time from the cursor to the secondary cursor and to every marker in the bottom
+    /// left corner of the window
+    fn draw_deltas(&self, frame: &mut Frame, bounds: Rectangle) {
+        let readouts: Vec<(String, Color)> =
+            self.frame_state
+                .secondary_cursor
+                .map(|time| (self.delta_readout("Secondary", time), SECONDARY_TEXT))
+                .into_iter()
+                .chain(self.frame_state.markers.iter().map(|(marker, _)| {
+                    (self.delta_readout(&marker.name, marker.time), MARKER_TEXT)
+                }))
+                .collect();
+        let mut position = Point {
+            x: BUFFER_PX,
+            y: bounds.height - BUFFER_PX,
+        };
+        for (content, color) in readouts.into_iter().rev() {
+            frame.fill_text(Text {
+                content,
+                position,
+                color,
+                size: TS_FONT_SIZE,
+                vertical_alignment: VerticalAlignment::Bottom,
+                ..Text::default()
+            });
+            position.y -= TS_FONT_SIZE + BUFFER_PX;
+        }
+    }
+
+    /// Shade the region being dragged out to zoom into
+    fn draw_selection(&self, frame: &mut Frame, bounds: Rectangle) {
+        if let Some((from, to)) = self.frame_state.selection {
+            let region = Path::rectangle(
+                Point {
+                    x: from.min(to),
+                    y: TS_FONT_SIZE,
+                },
+                Size::new((to - from).abs(), bounds.height - TS_FONT_SIZE),
+            );
+            frame.fill(&region, SELECTION);
+        }
+    }
+
+    /// Plot the values of a real signal in the row whose bottom left corner is `baseline`; the
+    /// plot is scaled to the smallest and largest value in the visible range
+    fn draw_analog(
+        &self,
+        p: &mut path::Builder,
+        text_vec: &mut Vec<Text>,
+        wave: &InMemWave,
+        display_options: WaveDisplayOptions,
+        baseline: Point,
+        bounds: &Rectangle,
+    ) {
+        let style = match display_options.analog() {
+            Some(style) => style,
+            None => return,
+        };
+        let height = display_options.row_height();
+        let (min, max) = match wave.real_bounds(self.start_time(), self.end_time()) {
+            Some(range) => range,
+            None => return,
+        };
+        // leave room for the stroke at the top and bottom of the row
+        let plot_height = height - 2.0 * BUFFER_PX;
+        let to_y = |value: f64| {
+            if max > min {
+                baseline.y - BUFFER_PX - ((value - min) / (max - min)) as f32 * plot_height
+            } else {
+                // flat signals go through the middle of the row
+                baseline.y - height / 2.0
+            }
+        };
+
+        for (value, y, alignment) in [
+            (max, baseline.y - height, VerticalAlignment::Top),
+            (min, baseline.y, VerticalAlignment::Bottom),
+        ]
+        .iter()
+        {
+            text_vec.push(Text {
+                content: format!("{:.4}", value),
+                position: Point {
+                    x: baseline.x + BUFFER_PX,
+                    y: *y,
+                },
+                color: ORANGE_TEXT,
+                size: TS_FONT_SIZE,
+                vertical_alignment: *alignment,
+                ..Text::default()
+            });
+        }
+
+        let mut point = baseline;
+        let mut prev_time = self.start_time();
+        let mut started = match wave.real_before(self.start_time()) {
+            Some(value) if value.is_finite() => {
+                point.y = to_y(value);
+                p.move_to(point);
+                true
+            }
+            _ => false,
+        };
+        for (time, value) in wave.reals_in_range(self.start_time(), self.end_time()) {
+            if self.out_of_range(time) {
+                break;
+            }
+            point.x += self.xdelt_from_prev(time, prev_time, bounds);
+            prev_time = time;
+            // values that can't be plotted, such as NaN, leave the previous value in place
+            if !value.is_finite() {
+                continue;
+            }
+            if !started {
+                point.y = to_y(value);
+                p.move_to(point);
+                started = true;
+                continue;
+            }
+            if style == AnalogStyle::Step {
+                p.line_to(point);
+            }
+            point.y = to_y(value);
+            p.line_to(point);
+        }
+        if started {
+            point.x += self.xdelt_from_prev(self.end_window_time(), prev_time, bounds);
+            // the next value isn't known yet, so hold the last one
+            p.line_to(point);
+        }
+    }
+
+    /// Hold a single bit wave in `state` for `x_delt` pixels. Unknown bits are covered by a red
+    /// band instead of being drawn as a level
+    fn hold_bit(
+        &self,
+        p: &mut path::Builder,
+        x_bands: &mut Vec<Path>,
+        state: &SBWaveState,
+        working_pt: &mut Point,
+        x_delt: f32,
+    ) {
+        let start = *working_pt;
+        working_pt.x += x_delt;
+        match state {
+            SBWaveState::Beginning => p.move_to(*working_pt),
+            SBWaveState::X => {
+                x_bands.push(Path::rectangle(
+                    Point {
+                        y: start.y - WAVEHEIGHT,
+                        ..start
+                    },
+                    Size::new(x_delt, WAVEHEIGHT),
+                ));
+                p.move_to(*working_pt);
+            }
+            _ => p.line_to(*working_pt),
+        }
+    }
+
+    /// Mark the `x_delt` pixels of a bus after `top_left` as undefined; x values are covered by
+    /// a red band, z values get a line through the middle of the bus
+    fn fill_undefined(
+        &self,
+        p: &mut path::Builder,
+        x_bands: &mut Vec<Path>,
+        zx: Option<ZxState>,
+        top_left: Point,
+        x_delt: f32,
+    ) {
+        match zx {
+            Some(ZxState::X) => {
+                x_bands.push(Path::rectangle(top_left, Size::new(x_delt, WAVEHEIGHT)));
+            }
+            Some(ZxState::Z) => {
+                let middle = Point {
+                    y: top_left.y + WAVEHEIGHT / 2.0,
+                    ..top_left
+                };
+                p.move_to(middle);
+                p.line_to(Point {
+                    x: middle.x + x_delt,
+                    ..middle
+                });
+            }
+            None => {}
+        }
+    }
+
+    /// Hold the value of a bus for `x_delt` pixels, moving both of its rails along
+    fn hold_bus(
+        &self,
+        p: &mut path::Builder,
+        x_bands: &mut Vec<Path>,
+        zx: Option<ZxState>,
+        working_pts: &mut [Point; 2],
+        x_delt: f32,
+    ) {
+        self.fill_undefined(p, x_bands, zx, working_pts[0], x_delt);
+        for point in working_pts.iter_mut() {
+            p.move_to(*point);
+            point.x += x_delt;
+            if zx != Some(ZxState::Z) {
+                p.line_to(*point);
+            }
+        }
+    }
+
+    /// Changes of `wave` within the window. Once a pixel spans more than a tick, the changes are
+    /// summarized per pixel column, so that busy waves take as long to draw as idle ones
+    fn changes<'w>(
+        &self,
+        wave: &'w InMemWave,
+        bounds: &Rectangle,
+    ) -> Box<dyn Iterator<Item = ColumnChanges<'w>> + 'w> {
+        let ns_per_unit = self.ns_per_unit(bounds);
+        if ns_per_unit > 1.0 {
+            let columns = wave.summarize(
+                self.start_time(),
+                self.end_time(),
+                ns_per_unit.ceil() as Toffset,
+            );
+            Box::new(columns.into_iter())
+        } else {
+            Box::new(
+                wave.droplets_in_range(self.start_time(), self.end_time())
+                    .map(|(time, droplet)| ColumnChanges::Single(time, droplet)),
+            )
+        }
     }
 
     //TODO: only redraw "dirty" signals
@@ -234,6 +917,8 @@ impl<'a> WaveWindow<'a> {
         leftmost_pt.y += WAVEHEIGHT + 2.0 * BUFFER_PX + TS_FONT_SIZE;
         let background = Path::rectangle(Point::default(), bounds.size());
         let mut text_vec = Vec::new();
+        let mut x_bands = Vec::new();
+        let mut busy_blocks = Vec::new();
         frame.fill(&background, Color::BLACK);
         let wave_list: Vec<Path> = self
             .signals
@@ -241,53 +926,92 @@ impl<'a> WaveWindow<'a> {
             .map(|display| {
                 Path::new(|p| {
                     let wave = display.get_wave();
-                    let mut working_pt = leftmost_pt.clone();
                     let display_options = display.display_conf.unwrap_or_default();
+                    // waves are drawn up from the bottom of their row
+                    leftmost_pt.y += display_options.row_height() - WAVEHEIGHT;
+                    let mut working_pt = leftmost_pt.clone();
                     p.move_to(leftmost_pt);
                     let mut prev_xcoord = self.start_time();
                     let width = wave.get_width();
                     match width {
-                        1 => {
-                            let mut sb_state = SBWaveState::Beginning;
+                        _ if display_options.analog().is_some() => {
+                            self.draw_analog(
+                                p,
+                                &mut text_vec,
+                                wave,
+                                display_options,
+                                working_pt,
+                                &bounds,
+                            );
+                        }
+                        // single bit enums, such as booleans, are drawn as vectors to show their names
+                        1 if wave.get_literals().is_none() => {
+                            let bottom = working_pt.y;
+                            // pick up the value the signal holds coming into the window
+                            let mut sb_state = match wave.get_prev_time(self.start_time()) {
+                                Some((_, data)) => SBWaveState::from_data(data),
+                                None => SBWaveState::Beginning,
+                            };
+                            working_pt.y = bottom - sb_state.level();
+                            p.move_to(working_pt);
 
-                            for (time, sig_payload) in
-                                wave.data_in_range(self.start_time(), self.end_time())
-                            {
-                                if self.out_of_range(time.clone()) {
-                                    break;
-                                }
-                                working_pt.x += self.xdelt_from_prev(time, prev_xcoord, &bounds);
-                                p.line_to(working_pt);
-                                p.move_to(working_pt);
-                                //TODO: handle z/x case
-                                match (&mut sb_state, sig_payload[0] & 0x1) {
-                                    (SBWaveState::Beginning, 0) => {
-                                        sb_state = SBWaveState::Low;
-                                    }
-                                    (SBWaveState::Beginning, 1) => {
-                                        working_pt.y -= WAVEHEIGHT;
-                                        sb_state = SBWaveState::High;
+                            for change in self.changes(wave, &bounds) {
+                                match change {
+                                    ColumnChanges::Single(time, droplet) => {
+                                        if self.out_of_range(time) {
+                                            break;
+                                        }
+                                        let x_delt =
+                                            self.xdelt_from_prev(time, prev_xcoord, &bounds);
+                                        self.hold_bit(
+                                            p,
+                                            &mut x_bands,
+                                            &sb_state,
+                                            &mut working_pt,
+                                            x_delt,
+                                        );
+                                        let next_state =
+                                            SBWaveState::from_data(droplet.take_data());
+                                        working_pt.y = bottom - next_state.level();
+                                        // the first change has no edge leading into it
+                                        match sb_state {
+                                            SBWaveState::Beginning => p.move_to(working_pt),
+                                            _ => p.line_to(working_pt),
+                                        }
+                                        sb_state = next_state;
+                                        prev_xcoord = time;
                                     }
-                                    (SBWaveState::High, 0) => {
-                                        working_pt.y += WAVEHEIGHT;
-                                        sb_state = SBWaveState::Low;
-                                    }
-                                    (SBWaveState::Low, 1) => {
-                                        working_pt.y -= WAVEHEIGHT;
-                                        sb_state = SBWaveState::High;
-                                    }
-                                    (_, _) => {
-                                        unreachable!("We should never get here!");
+                                    ColumnChanges::Busy {
+                                        begin, end, last, ..
+                                    } => {
+                                        let x_delt =
+                                            self.xdelt_from_prev(begin, prev_xcoord, &bounds);
+                                        self.hold_bit(
+                                            p,
+                                            &mut x_bands,
+                                            &sb_state,
+                                            &mut working_pt,
+                                            x_delt,
+                                        );
+                                        let busy_width = self.xdelt_from_prev(end, begin, &bounds);
+                                        busy_blocks.push(Path::rectangle(
+                                            Point {
+                                                y: bottom - WAVEHEIGHT,
+                                                ..working_pt
+                                            },
+                                            Size::new(busy_width, WAVEHEIGHT),
+                                        ));
+                                        working_pt.x += busy_width;
+                                        sb_state = SBWaveState::from_data(last.take_data());
+                                        working_pt.y = bottom - sb_state.level();
+                                        p.move_to(working_pt);
+                                        prev_xcoord = end;
                                     }
                                 }
-                                prev_xcoord = time;
-                                p.line_to(working_pt);
-                                p.move_to(working_pt);
                             }
                             let fin_x_delt =
                                 self.xdelt_from_prev(self.end_window_time(), prev_xcoord, &bounds);
-                            working_pt.x += fin_x_delt;
-                            p.line_to(working_pt);
+                            self.hold_bit(p, &mut x_bands, &sb_state, &mut working_pt, fin_x_delt);
                         }
                         _ => {
                             let working_pt_top = Point {
@@ -295,9 +1019,38 @@ impl<'a> WaveWindow<'a> {
                                 ..working_pt
                             };
                             let mut working_pts = [working_pt_top, working_pt];
-                            for (time, sig_payload) in
-                                wave.droplets_in_range(self.start_time(), self.end_time())
-                            {
+                            // undefined state of the value currently being drawn
+                            let mut bus_zx = wave
+                                .get_prev_time(self.start_time())
+                                .and_then(|(_, data)| data_zx_state(data, width));
+                            for change in self.changes(wave, &bounds) {
+                                let (time, sig_payload) = match change {
+                                    ColumnChanges::Single(time, droplet) => (time, droplet),
+                                    ColumnChanges::Busy {
+                                        begin, end, last, ..
+                                    } => {
+                                        let x_delt =
+                                            self.xdelt_from_prev(begin, prev_xcoord, &bounds);
+                                        self.hold_bus(
+                                            p,
+                                            &mut x_bands,
+                                            bus_zx,
+                                            &mut working_pts,
+                                            x_delt,
+                                        );
+                                        let busy_width = self.xdelt_from_prev(end, begin, &bounds);
+                                        busy_blocks.push(Path::rectangle(
+                                            working_pts[0],
+                                            Size::new(busy_width, WAVEHEIGHT),
+                                        ));
+                                        for point in working_pts.iter_mut() {
+                                            point.x += busy_width;
+                                        }
+                                        bus_zx = zx_state(last, width);
+                                        prev_xcoord = end;
+                                        continue;
+                                    }
+                                };
                                 if self.out_of_range(time) {
                                     break;
                                 }
@@ -305,9 +1058,19 @@ impl<'a> WaveWindow<'a> {
                                 let x_delt = self.xdelt_from_prev(time, prev_xcoord, &bounds)
                                     - VEC_SHIFT_WIDTH / 2.0;
 
+                                self.fill_undefined(
+                                    p,
+                                    &mut x_bands,
+                                    bus_zx,
+                                    working_pts[0],
+                                    x_delt,
+                                );
+                                let next_zx = zx_state(sig_payload, width);
                                 let mut value_text = generate_canvas_text(
                                     sig_payload,
                                     display_options,
+                                    wave.get_literals(),
+                                    display.get_enum_map(),
                                     width,
                                     x_delt,
                                 );
@@ -317,7 +1080,12 @@ impl<'a> WaveWindow<'a> {
                                 {
                                     p.move_to(*point);
                                     point.x += x_delt;
-                                    p.line_to(*point);
+                                    // high impedance values are drawn as a single line in the middle
+                                    if bus_zx == Some(ZxState::Z) {
+                                        p.move_to(*point);
+                                    } else {
+                                        p.line_to(*point);
+                                    }
                                     point.y += WAVEHEIGHT * direction;
                                     //TODO: logic for when really zoomed out, so we dont move past the next
                                     //delta
@@ -339,16 +1107,13 @@ impl<'a> WaveWindow<'a> {
                                     text_vec.push(text);
                                 }
 
+                                bus_zx = next_zx;
                                 prev_xcoord = time
                             }
                             // This draws a line towards the end of frame
                             let fin_x_delt =
                                 self.xdelt_from_prev(self.end_window_time(), prev_xcoord, &bounds);
-                            for point in working_pts.iter_mut() {
-                                p.move_to(*point);
-                                point.x += fin_x_delt;
-                                p.line_to(*point);
-                            }
+                            self.hold_bus(p, &mut x_bands, bus_zx, &mut working_pts, fin_x_delt);
                         }
                     }
                     leftmost_pt.y += WAVEHEIGHT + 2.0 * BUFFER_PX;
@@ -358,6 +1123,12 @@ impl<'a> WaveWindow<'a> {
 
         //TODO: cache wavelist in the case of append only?
 
+        for band in x_bands {
+            frame.fill(&band, RED);
+        }
+        for block in busy_blocks {
+            frame.fill(&block, BUSY);
+        }
         for text in text_vec {
             frame.fill_text(text);
         }
@@ -374,28 +1145,130 @@ impl<'a> canvas::Program<Message> for WaveWindow<'a> {
         bounds: Rectangle,
         cursor: Cursor,
     ) -> (event::Status, Option<Message>) {
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            self.frame_state.modifiers = modifiers;
+        }
         // TODO: Is there a more idiomatic way to do this?
         let cursor_position = if let Some(pos) = cursor.position_in(&bounds) {
             pos
         } else {
+            // leaving the window abandons a zoom selection
+            self.frame_state.selection = None;
             return (event::Status::Ignored, None);
         };
 
-        match event {
+        let span = (self.end_time() - self.start_time()) as i64;
+        let view = match event {
             Event::Mouse(mouse_event) => match mouse_event {
+                mouse::Event::ButtonPressed(mouse::Button::Right) => {
+                    let time = self.get_timestamp(cursor_position.x, &bounds);
+                    self.frame_state.secondary_cursor = Some(time);
+                    return (
+                        event::Status::Captured,
+                        Some(Message::UpdateSecondaryCursor(time)),
+                    );
+                }
+                mouse::Event::ButtonPressed(mouse::Button::Left) => {
+                    self.frame_state.selection = Some((cursor_position.x, cursor_position.x));
+                    None
+                }
+                mouse::Event::CursorMoved { .. } => {
+                    if let Some((_, to)) = self.frame_state.selection.as_mut() {
+                        *to = cursor_position.x;
+                    }
+                    None
+                }
                 mouse::Event::ButtonReleased(mouse::Button::Left) => {
-                    self.frame_state.cursor_location = self.get_timestamp(cursor_position.x);
-                    info!("click location is {}", self.frame_state.cursor_location,);
-                    (
+                    match self.frame_state.selection.take() {
+                        Some((from, to)) if (to - from).abs() > DRAG_THRESHOLD => Some((
+                            self.get_timestamp(from.min(to), &bounds),
+                            self.get_timestamp(from.max(to), &bounds),
+                        )),
+                        _ => {
+                            self.frame_state.cursor_location =
+                                self.get_timestamp(cursor_position.x, &bounds);
+                            info!("click location is {}", self.frame_state.cursor_location,);
+                            return (
+                                event::Status::Captured,
+                                Some(Message::UpdateCursor(self.frame_state.cursor_location)),
+                            );
+                        }
+                    }
+                }
+                mouse::Event::WheelScrolled { delta } => {
+                    let (lines, pixels) = match delta {
+                        mouse::ScrollDelta::Lines { y, .. } => (y, y * SCROLL_LINE_PX),
+                        mouse::ScrollDelta::Pixels { y, .. } => (y / SCROLL_LINE_PX, y),
+                    };
+                    if self.frame_state.modifiers.is_command_pressed() {
+                        // scrolling up zooms in on whatever is under the mouse
+                        let center = self.get_timestamp(cursor_position.x, &bounds);
+                        Some(
+                            self.frame_state
+                                .zoomed(center, ZOOM_STEP.powf(lines as f64)),
+                        )
+                    } else {
+                        let ticks = pixels * self.ns_per_unit(&bounds);
+                        Some(self.frame_state.panned(-ticks.round() as i64))
+                    }
+                }
+                _ => None,
+            },
+            // shortcuts need the command key held, so that typing into a text input while the
+            // mouse is over the waves doesn't move the view or the cursor
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code,
+                modifiers,
+            }) if modifiers.is_command_pressed() => match key_code {
+                keyboard::KeyCode::Left => Some(self.frame_state.panned(-span / PAN_FRACTION)),
+                keyboard::KeyCode::Right => Some(self.frame_state.panned(span / PAN_FRACTION)),
+                keyboard::KeyCode::Home => Some(self.frame_state.panned(i64::MIN)),
+                keyboard::KeyCode::End => Some(self.frame_state.panned(i64::MAX)),
+                keyboard::KeyCode::Plus
+                | keyboard::KeyCode::Equals
+                | keyboard::KeyCode::NumpadAdd => {
+                    return (event::Status::Captured, Some(Message::Zoom(ZOOM_STEP)));
+                }
+                keyboard::KeyCode::Minus | keyboard::KeyCode::NumpadSubtract => {
+                    return (
                         event::Status::Captured,
-                        Some(Message::UpdateCursor(self.frame_state.cursor_location)),
-                    )
+                        Some(Message::Zoom(1.0 / ZOOM_STEP)),
+                    );
                 }
-
-                _ => (event::Status::Captured, None),
+                keyboard::KeyCode::F => {
+                    return (event::Status::Captured, Some(Message::ZoomToFit));
+                }
+                keyboard::KeyCode::M => {
+                    return (event::Status::Captured, Some(Message::AddMarker));
+                }
+                keyboard::KeyCode::Delete => {
+                    return (event::Status::Captured, Some(Message::RemoveMarker));
+                }
+                // edges of the selected wave; shift searches backwards
+                keyboard::KeyCode::N | keyboard::KeyCode::H | keyboard::KeyCode::L => {
+                    let edge = match key_code {
+                        keyboard::KeyCode::H => Edge::Rising,
+                        keyboard::KeyCode::L => Edge::Falling,
+                        _ => Edge::Any,
+                    };
+                    let forward = !modifiers.shift;
+                    return (
+                        event::Status::Captured,
+                        Some(Message::JumpToEdge(edge, forward)),
+                    );
+                }
+                keyboard::KeyCode::LBracket | keyboard::KeyCode::RBracket => {
+                    let forward = key_code == keyboard::KeyCode::RBracket;
+                    return (
+                        event::Status::Captured,
+                        Some(Message::JumpToMarker(forward)),
+                    );
+                }
+                _ => return (event::Status::Ignored, None),
             },
-            _ => (event::Status::Ignored, None),
-        }
+            _ => return (event::Status::Ignored, None),
+        };
+        (event::Status::Captured, view.map(Message::UpdateView))
     }
 
     fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
@@ -406,8 +1279,13 @@ impl<'a> canvas::Program<Message> for WaveWindow<'a> {
         let cursors = self.cursor_cache.draw(bounds.size(), |frame: &mut Frame| {
             self.draw_header(frame, bounds);
             self.draw_cursor(frame, bounds);
+            self.draw_deltas(frame, bounds);
         });
 
-        vec![content, cursors]
+        // the selection follows the mouse, so it is never cached
+        let mut selection = Frame::new(bounds.size());
+        self.draw_selection(&mut selection, bounds);
+
+        vec![content, cursors, selection.into_geometry()]
     }
 }
@@ -1 +1,98 @@
+use crate::components::signals::Message;
+use crate::components::style;
+use iced::{button, text_input, Button, Color, Element, Length, Row, Text, TextInput};
+use wave2_wavedb::storage::in_memory::Edge;
 
+const TEXT_SIZE: u16 = 11;
+const PADDING: u16 = 4;
+const ERROR_TEXT: Color = Color::from_rgb(1.0, 0.3, 0.3);
+
+/// Buttons that move the cursor between the edges of the selected wave
+const EDGE_BUTTONS: [(&str, Edge, bool); 6] = [
+    ("< Edge", Edge::Any, false),
+    ("Edge >", Edge::Any, true),
+    ("< Rise", Edge::Rising, false),
+    ("Rise >", Edge::Rising, true),
+    ("< Fall", Edge::Falling, false),
+    ("Fall >", Edge::Falling, true),
+];
+
+/// Toolbar shown above the wave window
+#[derive(Debug, Default)]
+pub struct WidgetBar {
+    edge_buttons: [button::State; 6],
+    /// Value query for the selected wave, such as `== 0x4000_1000`, or a condition over the
+    /// waves of the wave window, such as `valid && ready`
+    search: String,
+    search_state: text_input::State,
+    find_buttons: [button::State; 2],
+    /// Why the last search failed, shown until the search changes
+    search_error: Option<String>,
+}
+
+impl WidgetBar {
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    pub fn set_search(&mut self, search: String) {
+        self.search = search;
+        self.search_error = None;
+    }
+
+    pub fn set_search_error(&mut self, search_error: Option<String>) {
+        self.search_error = search_error;
+    }
+
+    pub fn view(&mut self) -> Element<Message> {
+        let WidgetBar {
+            edge_buttons,
+            search,
+            search_state,
+            find_buttons: [find_prev, find_next],
+            search_error,
+        } = self;
+        let row = edge_buttons.iter_mut().zip(EDGE_BUTTONS.iter()).fold(
+            Row::new().spacing(PADDING).width(Length::Fill),
+            |row, (state, (label, edge, forward))| {
+                row.push(
+                    Button::new(state, Text::new(*label).size(TEXT_SIZE))
+                        .padding(PADDING)
+                        .style(style::Button::Primary)
+                        .on_press(Message::JumpToEdge(*edge, *forward)),
+                )
+            },
+        );
+        row.push(
+            TextInput::new(
+                search_state,
+                "Search: == 0x4000_1000, or valid && ready",
+                search.as_str(),
+                Message::UpdateSearch,
+            )
+            .on_submit(Message::Search(true))
+            .size(TEXT_SIZE)
+            .padding(PADDING)
+            .width(Length::Units(300)),
+        )
+        .push(find_button(find_prev, "< Find", false))
+        .push(find_button(find_next, "Find >", true))
+        .push(
+            Text::new(search_error.as_deref().unwrap_or_default())
+                .size(TEXT_SIZE)
+                .color(ERROR_TEXT),
+        )
+        .into()
+    }
+}
+
+fn find_button<'a>(
+    state: &'a mut button::State,
+    label: &str,
+    forward: bool,
+) -> Button<'a, Message> {
+    Button::new(state, Text::new(label).size(TEXT_SIZE))
+        .padding(PADDING)
+        .style(style::Button::Primary)
+        .on_press(Message::Search(forward))
+}
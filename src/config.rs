@@ -1,6 +1,8 @@
 use crate::components::menu_bar::{Message as MenuMessage, FileMenu, ViewMenu};
-use wave2_wavedb::inout::wave_loader::load_vcd;
+use wave2_wavedb::inout::wave_loader::{load_enum_maps, load_vcd};
 use log::info;
+use crate::components::signals;
+use crate::components::signals::wavewindow::ZOOM_STEP;
 use crate::{Message, State};
 use iced::Command;
 
@@ -19,15 +21,24 @@ pub fn menu_update(
                         Command::perform(load_vcd(), Message::LoadWDB)
                     }
                 }
+                FileMenu::LoadEnumMaps => match app_state.wdb_api.as_ref() {
+                    Some(api) => Command::perform(load_enum_maps(api.clone()), |enum_maps| {
+                        Message::SignalsMessage(signals::Message::UpdateEnumMaps(enum_maps))
+                    }),
+                    None => {
+                        info!("Open a signal dump before loading enum mappings");
+                        Command::none()
+                    }
+                },
             }
         }
         MenuMessage::View(view_menu) => {
-            match view_menu {
-                ViewMenu::ImplMe => {
-                    info!("Unimplimented menu option! only here to show what multiple tabs look like");
-                    Command::none()
-                }
-            }
+            let message = match view_menu {
+                ViewMenu::ZoomIn => signals::Message::Zoom(ZOOM_STEP),
+                ViewMenu::ZoomOut => signals::Message::Zoom(1.0 / ZOOM_STEP),
+                ViewMenu::ZoomToFit => signals::Message::ZoomToFit,
+            };
+            Command::perform(async move { message }, Message::SignalsMessage)
         }
     }
 }
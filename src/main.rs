@@ -29,6 +29,7 @@ struct Opts {
     #[clap(short, long)]
     wdbpath: Option<PathBuf>,
 
+    /// Signal dump to open; .fst and .ghw files are read as FST and GHW, anything else as VCD
     #[clap(short, long)]
     vcdpath: Option<PathBuf>,
 }
@@ -254,6 +255,33 @@ impl Application for Wave2 {
                         state.focused_pane = Some(state.sv_pane);
                         state.panes.get_mut(&state.sv_pane).unwrap().update(Message::SignalsMessage(inner_message.clone()));
                         state.panes.get_mut(&state.ww_pane).unwrap().update(Message::SignalsMessage(inner_message.clone()));
+                        // the sigviewer lists the value every wave has at the cursor
+                        if let Some(Content::WaveWindow(wavewindow)) = state.panes.get(&state.ww_pane) {
+                            let values = wavewindow.cursor_values();
+                            if let Some(Content::SigView(sig_viewer)) = state.panes.get_mut(&state.sv_pane) {
+                                sig_viewer.set_cursor_values(values);
+                            }
+                        }
+                        // names entered by hand are persisted with the wavedb
+                        if let signals::Message::AddEnumEntry(_) = inner_message {
+                            if let Some(Content::WaveWindow(wavewindow)) = state.panes.get(&state.ww_pane) {
+                                if let Some((signal_id, enum_map)) = wavewindow.selected_enum_map() {
+                                    return Command::perform(
+                                        WdbApi::set_enum_map(state.get_api(), signal_id, enum_map),
+                                        |saved| Message::SignalsMessage(signals::Message::EnumMapSaved(saved)),
+                                    );
+                                }
+                            }
+                        }
+                        // derived waves are computed from the waves already in the wave window
+                        if let signals::Message::AddDerived(definition) = inner_message {
+                            if let Some(Content::WaveWindow(wavewindow)) = state.panes.get(&state.ww_pane) {
+                                return Command::perform(
+                                    WdbApi::derive_signal(state.get_api(), definition, wavewindow.waves()),
+                                    |wave| Message::SignalsMessage(signals::Message::AddWave(wave)),
+                                );
+                            }
+                        }
 
                     }
                     Message::HNMessage(hn_message) => {
@@ -313,12 +341,19 @@ impl Application for Wave2 {
                                     state.wdb_api.as_ref().unwrap().get_hier_map().clone(),
                                 )),
                             );
-                            return Command::perform(
-                                WdbApi::bounds(state.get_api()),
-                                move |bounds| {
+                            return Command::batch(vec![
+                                Command::perform(WdbApi::bounds(state.get_api()), move |bounds| {
                                     Message::SignalsMessage(signals::Message::UpdateBounds(bounds))
-                                },
-                            );
+                                }),
+                                Command::perform(
+                                    WdbApi::timescale(state.get_api()),
+                                    move |timescale| {
+                                        Message::SignalsMessage(signals::Message::UpdateTimescale(
+                                            timescale,
+                                        ))
+                                    },
+                                ),
+                            ]);
                         }
                         Err(waverr) => {
                             state.set_file_pending(false);
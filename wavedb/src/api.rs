@@ -1,17 +1,25 @@
 use crate::errors::Waverr;
+use crate::expr::Expression;
+use crate::formatting::EnumMap;
 use crate::wavedb::WaveDb;
+use crate::storage::derived::{derive_wave, parse_definition, DERIVED_ID_BASE};
 use crate::storage::in_memory::InMemWave;
 
 use crate::hier_map::{HierMap, SignalItem};
+use crate::puddle::{SignalId, Toffset};
+use crate::timescale::Timescale;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 /// Interface provided to wave2 for querying signal hierarchy
 #[derive(Debug)]
 pub struct WdbApi {
     wdb: WaveDb,
+    /// Number of derived waves handed out so far
+    derived: AtomicU32,
 }
 
 ///Helper to hash type -> String
@@ -25,7 +33,10 @@ fn quick_hash<T: Hash>(t: &T) -> String {
 
 impl From<WaveDb> for WdbApi {
     fn from(indb: WaveDb) -> WdbApi {
-        WdbApi { wdb: indb }
+        WdbApi {
+            wdb: indb,
+            derived: AtomicU32::new(0),
+        }
     }
 }
 
@@ -35,10 +46,11 @@ impl WdbApi {
     pub fn open_from_vcd(path_to_vcd: &str) -> Result<WdbApi, Waverr> {
         let wdb_path = format!("/tmp/wavedb/{}/wdb", quick_hash(&path_to_vcd));
         Ok(WdbApi {
-            wdb: WaveDb::from_vcd(
+            wdb: WaveDb::from_file(
                 path_to_vcd.into(),
                 Path::new(wdb_path.as_str()),
             )?,
+            derived: AtomicU32::new(0),
         })
     }
 
@@ -65,13 +77,57 @@ impl WdbApi {
     /// Get the starting and ending time of the signal dump represented by this WaveDB
     pub async fn bounds(
         api: Arc<WdbApi>,
-    ) -> (u32,u32) {
+    ) -> (Toffset, Toffset) {
         api.wdb.get_bounds()
     }
 
+    /// Get the timescale of the signal dump; every time handed out by this API is in these units
+    pub async fn timescale(api: Arc<WdbApi>) -> Timescale {
+        api.wdb.get_timescale()
+    }
 
+    /// Attach the enum maps of the TOML file at `path` to their signals, returning every signal
+    /// that got one
+    pub async fn load_enum_maps(
+        api: Arc<WdbApi>,
+        path: PathBuf,
+    ) -> Result<Arc<Vec<(SignalId, EnumMap)>>, Arc<Waverr>> {
+        api.wdb
+            .load_enum_maps(path.as_path())
+            .map(Arc::new)
+            .map_err(Arc::new)
+    }
+
+    /// Persist the value names of a signal along with the wavedb
+    pub async fn set_enum_map(
+        api: Arc<WdbApi>,
+        signal_id: SignalId,
+        enum_map: EnumMap,
+    ) -> Result<(), Arc<Waverr>> {
+        api.wdb.set_enum_map(signal_id, &enum_map).map_err(Arc::new)
+    }
 
+    /// Compute a wave from a definition such as `hi = bus[15:8]`; the signals it refers to are
+    /// looked up among `waves` by path or name, as by [`Expression::resolve`]
+    pub async fn derive_signal(
+        api: Arc<WdbApi>,
+        definition: String,
+        waves: Vec<Arc<InMemWave>>,
+    ) -> Result<Arc<InMemWave>, Arc<Waverr>> {
+        let (name, expression) = parse_definition(definition.as_str());
+        let expression = Expression::parse(expression).map_err(Arc::new)?;
+        let waves = expression.resolve(&waves).map_err(Arc::new)?;
+        let signal_id = DERIVED_ID_BASE - api.derived.fetch_add(1, Ordering::Relaxed);
+        let cache = api.wdb.puddle_cache();
+        derive_wave(name.to_string(), signal_id, expression, waves, cache)
+            .map(Arc::new)
+            .map_err(Arc::new)
+    }
 
+    /// Cap the memory used to hold puddles of the signals being viewed, derived waves included
+    pub fn set_cache_budget(&self, bytes: usize) {
+        self.wdb.set_cache_budget(bytes)
+    }
 
     /// Get module names underneath module_path
     /// TODO: encode if there is a submodule here
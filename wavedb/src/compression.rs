@@ -0,0 +1,168 @@
+/*! Optional compression of puddles before they are written to disk.
+
+The [`Compression`] used is chosen when a wavedb is built and persisted in its config, so
+puddles are transparently decompressed when they are read back.
+!*/
+use crate::errors::Waverr;
+use crate::puddle::Toffset;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// zstd level used for puddles; favors conversion speed over ratio
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Codec applied to every encoded puddle
+pub enum Compression {
+    #[default]
+    None,
+    /// Fast, with modest ratios; a good fit for interactive use
+    Lz4,
+    /// Slower, with much better ratios; a good fit for archiving
+    Zstd,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Lz4, Compression::Zstd];
+
+    pub fn compress(&self, raw: Vec<u8>) -> Result<Vec<u8>, Waverr> {
+        match self {
+            Compression::None => Ok(raw),
+            Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(raw.as_slice())),
+            Compression::Zstd => {
+                zstd::stream::encode_all(raw.as_slice(), ZSTD_LEVEL).map_err(Waverr::IoErr)
+            }
+        }
+    }
+
+    pub fn decompress(&self, stored: &[u8]) -> Result<Vec<u8>, Waverr> {
+        match self {
+            Compression::None => Ok(stored.to_vec()),
+            Compression::Lz4 => lz4_flex::decompress_size_prepended(stored)
+                .map_err(|_| Waverr::CompressionErr("Corrupt lz4 puddle")),
+            Compression::Zstd => zstd::stream::decode_all(stored).map_err(Waverr::IoErr),
+        }
+    }
+}
+
+impl std::fmt::Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Zstd => "zstd",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Compression {
+    type Err = Waverr;
+    fn from_str(name: &str) -> Result<Compression, Waverr> {
+        Compression::ALL
+            .iter()
+            .find(|compression| compression.to_string().eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or(Waverr::CompressionErr(
+                "Unknown compression; expected none, lz4 or zstd",
+            ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// On-disk footprint of the puddles in a single time slice tree
+pub struct TreeSize {
+    /// Base time of the slice; for totals, this is the base of the first slice
+    pub btree_idx: Toffset,
+    pub puddles: usize,
+    /// Size of the puddles before compression
+    pub raw_bytes: u64,
+    /// Size of the puddles as stored
+    pub stored_bytes: u64,
+}
+
+impl TreeSize {
+    /// Stored size as a fraction of the raw size
+    pub fn ratio(&self) -> f64 {
+        if self.raw_bytes == 0 {
+            1.0
+        } else {
+            self.stored_bytes as f64 / self.raw_bytes as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Raw vs compressed size of every tree in a wavedb, ordered by time
+pub struct SizeReport {
+    pub compression: Compression,
+    pub trees: Vec<TreeSize>,
+}
+
+impl SizeReport {
+    pub fn total(&self) -> TreeSize {
+        self.trees.iter().fold(
+            TreeSize {
+                btree_idx: self.trees.first().map_or(0, |tree| tree.btree_idx),
+                ..TreeSize::default()
+            },
+            |total, tree| TreeSize {
+                puddles: total.puddles + tree.puddles,
+                raw_bytes: total.raw_bytes + tree.raw_bytes,
+                stored_bytes: total.stored_bytes + tree.stored_bytes,
+                ..total
+            },
+        )
+    }
+}
+
+impl std::fmt::Display for SizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "compression: {}", self.compression)?;
+        writeln!(
+            f,
+            "{:>12} {:>8} {:>12} {:>12} {:>7}",
+            "tree", "puddles", "raw", "stored", "ratio"
+        )?;
+        let row = |f: &mut std::fmt::Formatter<'_>, name: String, tree: &TreeSize| {
+            writeln!(
+                f,
+                "{:>12} {:>8} {:>12} {:>12} {:>7.3}",
+                name,
+                tree.puddles,
+                tree.raw_bytes,
+                tree.stored_bytes,
+                tree.ratio()
+            )
+        };
+        for tree in self.trees.iter() {
+            row(f, tree.btree_idx.to_string(), tree)?;
+        }
+        row(f, String::from("total"), &self.total())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+    use test_case::test_case;
+
+    #[test_case(Compression::None; "none")]
+    #[test_case(Compression::Lz4; "lz4")]
+    #[test_case(Compression::Zstd; "zstd")]
+    fn round_trip(compression: Compression) {
+        let raw: Vec<u8> = (0..4096u32).map(|idx| (idx % 7) as u8).collect();
+        let stored = compression.compress(raw.clone()).unwrap();
+        if compression != Compression::None {
+            assert!(stored.len() < raw.len());
+        }
+        assert_eq!(compression.decompress(stored.as_slice()).unwrap(), raw);
+    }
+
+    #[test_case("lz4", Some(Compression::Lz4); "lower case")]
+    #[test_case("ZSTD", Some(Compression::Zstd); "upper case")]
+    #[test_case("gzip", None; "unknown")]
+    fn parse(name: &str, baseline: Option<Compression>) {
+        assert_eq!(name.parse::<Compression>().ok(), baseline);
+    }
+}
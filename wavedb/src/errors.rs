@@ -8,7 +8,7 @@ Uses [`thiserror`] to derive different error types as they appear
 !*/
 use std::io;
 use thiserror::Error;
-use crate::puddle::SignalId;
+use crate::puddle::{SignalId, Toffset};
 
 ///Generic error type for any error that can manifest within wavedb or wave2
 #[derive(Debug, Error)]
@@ -18,6 +18,12 @@ pub enum Waverr {
         "VCDError found, issue is `{0}`. TODO: make a better error enum here!"
     )]
     VcdErr(&'static str),
+    /// Error during FST parsing
+    #[error("FSTError found, issue is `{0}`")]
+    FstErr(&'static str),
+    /// Error during GHW parsing
+    #[error("GHWError found, issue is `{0}`")]
+    GhwErr(&'static str),
     ///TODO: depricated, remove
     #[error("Wdb Bucket error for bucket id : {id:?}, ts : {ts_range:?}. context: {context:?}")]
     BucketErr {
@@ -28,7 +34,7 @@ pub enum Waverr {
     #[error("Puddle error: Puddle time : {time:?}, base_sigid: {base_sigid:?}.\n
         context : {context:?}")]
     PuddleErr {
-        time: u32,
+        time: Toffset,
         base_sigid: SignalId,
         context: &'static str,
     },
@@ -61,6 +67,18 @@ pub enum Waverr {
     IoErr(io::Error),
     #[error("Some cfg err `{0}`")]
     WdbCfgErr(&'static str),
+    #[error("WaveDB has on-disk format version {found}, expected {expected}; rebuild it from the signal dump")]
+    WdbVersionErr { found: u32, expected: u32 },
+    #[error("Compression error, issue is `{0}`")]
+    CompressionErr(&'static str),
+    #[error("Ingest error, issue is `{0}`")]
+    IngestErr(&'static str),
+    #[error("Enum mapping error, issue is `{0}`")]
+    EnumMapErr(String),
+    #[error("Search error, issue is `{0}`")]
+    SearchErr(String),
+    #[error("Expression error, issue is `{0}`")]
+    ExprErr(String),
     #[error("Generic error. This should be removed. Refactor this now")]
     GenericErr(&'static str),
 }
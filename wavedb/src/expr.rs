@@ -0,0 +1,1016 @@
+/*! Expressions over several waves, such as `valid && ready && data[7:0] == 8'hff`.
+
+An expression is made of signals, bit selects of signals (`data[7]`, `data[7:0]`), values
+(`42`, `0xff`, `0b1010`, Verilog style `8'hff`) and concatenations (`{hi, lo}`), combined with
+the operators below, loosest binding first, and grouped with parentheses:
+
+* `||`, then `&&`
+* comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=`
+* `|`, then `^`, then `&`
+* `+` and `-`, then `*`
+* `!` and `~`
+
+Values are unsigned. The width of a result is enough to hold it, except for `-`, which wraps
+around at the width of its widest operand, and `~`, which inverts the bits of its operand. Signals
+holding unknown or high impedance bits have no value, and neither does anything computed from
+them, with the exception of `&&` and `||` when the other side decides the result.
+
+Expressions are searched for the times they hold, that is when they are nonzero, and are
+turned into derived waves by [`crate::storage::derived`].
+!*/
+use crate::errors::Waverr;
+use crate::formatting::get_bit;
+use crate::puddle::Toffset;
+use crate::storage::in_memory::{CarriedValue, Changes, InMemWave};
+use num_bigint::BigUint;
+use std::borrow::Borrow;
+use std::collections::BinaryHeap;
+
+/// Tokens that aren't signals or values, longest first so that `<=` isn't read as `<`
+const OPERATORS: [&str; 24] = [
+    "&&", "||", "==", "!=", "<=", ">=", "!", "~", "&", "|", "^", "+", "-", "*", "<", ">", "(", ")",
+    "[", "]", "{", "}", ":", ",",
+];
+
+const COMPARISONS: [(&str, BinOp); 6] = [
+    ("==", BinOp::Eq),
+    ("!=", BinOp::Ne),
+    ("<=", BinOp::Le),
+    (">=", BinOp::Ge),
+    ("<", BinOp::Lt),
+    (">", BinOp::Gt),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Signal(String),
+    Value(String),
+    Op(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Add,
+    Sub,
+    Mul,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    /// Bits `msb` through `lsb` of a signal, or all of them; `index` is into the signals of the
+    /// expression
+    Signal {
+        index: usize,
+        bits: Option<(usize, usize)>,
+    },
+    /// A value, along with its width if it was given one
+    Value(BigUint, Option<usize>),
+    Not(Box<Expr>),
+    Invert(Box<Expr>),
+    /// Operands are concatenated most significant first
+    Concat(Vec<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+/// A parsed expression, along with the names of the signals it refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    expr: Expr,
+    signals: Vec<String>,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Expression, Waverr> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: tokens.as_slice(),
+            pos: 0,
+            signals: Vec::new(),
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(Waverr::ExprErr(format!(
+                "unexpected {:?} in {}",
+                token, text
+            )));
+        }
+        Ok(Expression {
+            expr,
+            signals: parser.signals,
+        })
+    }
+
+    /// Names of the signals the expression refers to, in the order their waves are expected by
+    /// [`Expression::intervals`] and [`Expression::width`]
+    pub fn signals(&self) -> &[String] {
+        &self.signals
+    }
+
+    /// Pick the waves of [`Expression::signals`] out of `waves`. Signals named by a dotted
+    /// path, such as `TOP.vga.x_addr`, match the wave with that path; others match waves by
+    /// name, and must name a single signal
+    pub fn resolve<W>(&self, waves: &[W]) -> Result<Vec<W>, Waverr>
+    where
+        W: Borrow<InMemWave> + Clone,
+    {
+        self.signals
+            .iter()
+            .map(|signal| {
+                let mut candidates: Vec<&W> = waves
+                    .iter()
+                    .filter(|wave| {
+                        let wave: &InMemWave = Borrow::borrow(*wave);
+                        if signal.contains('.') {
+                            wave.get_path() == signal
+                        } else {
+                            wave.get_name() == signal
+                        }
+                    })
+                    .collect();
+                // a signal may be shown more than once
+                let path = |wave: &&W| -> String {
+                    Borrow::<InMemWave>::borrow(*wave).get_path().to_string()
+                };
+                candidates.sort_by_key(path);
+                candidates.dedup_by_key(|wave| path(wave));
+                match candidates.as_slice() {
+                    [] => Err(Waverr::ExprErr(format!(
+                        "there is no wave named {}",
+                        signal
+                    ))),
+                    [wave] => Ok((*wave).clone()),
+                    _ => {
+                        let paths: Vec<String> = candidates.iter().map(path).collect();
+                        Err(Waverr::ExprErr(format!(
+                            "{} could be any of {}",
+                            signal,
+                            paths.join(", ")
+                        )))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Number of bits needed to hold the value of the expression
+    pub fn width(&self, waves: &[&InMemWave]) -> Result<usize, Waverr> {
+        Ok(self.expr.width(&self.widths(waves)?))
+    }
+
+    /// Intervals of `[begin, end)` in which the expression holds, in time order
+    pub fn intervals(
+        &self,
+        waves: &[&InMemWave],
+        begin: Toffset,
+        end: Toffset,
+    ) -> Result<Vec<(Toffset, Toffset)>, Waverr> {
+        let mut intervals = Vec::new();
+        let mut start = None;
+        self.for_each_value(waves, begin, end, |time, value| {
+            let holds = value.is_some_and(|value| value.bits() != 0);
+            match (holds, start) {
+                (true, None) => start = Some(time),
+                (false, Some(from)) => {
+                    intervals.push((from, time));
+                    start = None;
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        intervals.extend(start.map(|from| (from, end)));
+        Ok(intervals)
+    }
+
+    /// Call `visit` with `begin` and each later time in `[begin, end)` that any of `waves`, the
+    /// waves of [`Expression::signals`], changes, along with the value of the expression from
+    /// then on. The changes of the waves are merged as they are walked, so the work done
+    /// depends on the number of changes rather than on the length of the range, and only the
+    /// puddles of the slices being walked are held
+    pub(crate) fn for_each_value<F>(
+        &self,
+        waves: &[&InMemWave],
+        begin: Toffset,
+        end: Toffset,
+        mut visit: F,
+    ) -> Result<(), Waverr>
+    where
+        F: FnMut(Toffset, Option<BigUint>) -> Result<(), Waverr>,
+    {
+        let widths = self.widths(waves)?;
+        let mut values = waves
+            .iter()
+            .map(|wave| wave.value_before(begin))
+            .collect::<Result<Vec<_>, Waverr>>()?;
+        let mut merged = Merged::new(waves, begin, true)?;
+        // changes at `begin` itself hold from the start
+        if merged.peek() == Some(begin) {
+            merged.step(&mut values)?;
+        }
+        let mut time = begin;
+        loop {
+            visit(time, self.expr.value(&values, &widths))?;
+            time = match merged.peek() {
+                Some(next) if next < end => next,
+                _ => return Ok(()),
+            };
+            merged.step(&mut values)?;
+        }
+    }
+
+    /// First time after `time` that the expression starts to hold, evaluating forward from
+    /// `time` only as far as needed. Holding at the start of the signal dump counts as starting
+    /// to hold there
+    pub fn next_start(
+        &self,
+        waves: &[&InMemWave],
+        time: Toffset,
+    ) -> Result<Option<Toffset>, Waverr> {
+        self.next_change_where(waves, time, starts_to_hold)
+    }
+
+    /// Last time before `time` that the expression started to hold, evaluating backward from
+    /// `time` only as far as needed
+    pub fn prev_start(
+        &self,
+        waves: &[&InMemWave],
+        time: Toffset,
+    ) -> Result<Option<Toffset>, Waverr> {
+        self.prev_change_where(waves, time, starts_to_hold)
+    }
+
+    /// First time after `time` that any of `waves` changes, for which `found` holds given the
+    /// values of the expression before and after the change. The value before the start of the
+    /// signal dump is `None`, so the dump starting counts as a change
+    pub fn next_change_where<F>(
+        &self,
+        waves: &[&InMemWave],
+        time: Toffset,
+        mut found: F,
+    ) -> Result<Option<Toffset>, Waverr>
+    where
+        F: FnMut(Option<&Option<BigUint>>, &Option<BigUint>) -> bool,
+    {
+        let widths = self.widths(waves)?;
+        let first = match first_time(waves) {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let from = time.saturating_add(1).max(first);
+        let mut values = waves
+            .iter()
+            .map(|wave| wave.value_before(from))
+            .collect::<Result<Vec<_>, Waverr>>()?;
+        let mut merged = Merged::new(waves, from, true)?;
+        let mut before = if time < first {
+            if merged.peek() == Some(first) {
+                merged.step(&mut values)?;
+            }
+            let value = self.expr.value(&values, &widths);
+            if found(None, &value) {
+                return Ok(Some(first));
+            }
+            value
+        } else {
+            self.expr.value(&values, &widths)
+        };
+        while let Some(change) = merged.step(&mut values)? {
+            let value = self.expr.value(&values, &widths);
+            if found(Some(&before), &value) {
+                return Ok(Some(change));
+            }
+            before = value;
+        }
+        Ok(None)
+    }
+
+    /// Last time before `time` that any of `waves` changes for which `found` holds, evaluating
+    /// backward from `time`
+    pub fn prev_change_where<F>(
+        &self,
+        waves: &[&InMemWave],
+        time: Toffset,
+        mut found: F,
+    ) -> Result<Option<Toffset>, Waverr>
+    where
+        F: FnMut(Option<&Option<BigUint>>, &Option<BigUint>) -> bool,
+    {
+        let widths = self.widths(waves)?;
+        let first = match first_time(waves) {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        let mut merged = Merged::new(waves, time, false)?;
+        let mut values: Vec<Option<Vec<u8>>> = merged
+            .heads
+            .iter()
+            .map(|head| head.as_ref().map(|(_, data)| data.clone()))
+            .collect();
+        let mut after = self.expr.value(&values, &widths);
+        while let Some(change) = merged.step(&mut values)? {
+            // nothing comes before the start of the signal dump
+            if change <= first {
+                return Ok(Some(change).filter(|_| found(None, &after)));
+            }
+            let before = self.expr.value(&values, &widths);
+            if found(Some(&before), &after) {
+                return Ok(Some(change));
+            }
+            after = before;
+        }
+        // no wave changes between the start of the signal dump and here
+        Ok(Some(first).filter(|first| *first < time && found(None, &after)))
+    }
+
+    /// Widths of `waves`, once they are known to fit the expression
+    fn widths(&self, waves: &[&InMemWave]) -> Result<Vec<usize>, Waverr> {
+        if waves.len() != self.signals.len() {
+            return Err(Waverr::ExprErr(format!(
+                "expression refers to {} signals, but {} waves were given",
+                self.signals.len(),
+                waves.len()
+            )));
+        }
+        let widths: Vec<usize> = waves.iter().map(|wave| wave.get_width()).collect();
+        self.expr.check_bits(&self.signals, &widths)?;
+        Ok(widths)
+    }
+}
+
+/// Whether a change makes the expression hold where it didn't before
+fn starts_to_hold(before: Option<&Option<BigUint>>, after: &Option<BigUint>) -> bool {
+    let holds = |value: &Option<BigUint>| value.as_ref().is_some_and(|value| value.bits() != 0);
+    holds(after) && !before.is_some_and(holds)
+}
+
+/// First timestamp of the signal dump `waves` come from
+fn first_time(waves: &[&InMemWave]) -> Option<Toffset> {
+    waves.iter().map(|wave| wave.time_range().0).min()
+}
+
+/// Changes of several waves merged into a single stream, walking forward or backward in time.
+/// Only the next change of each wave is held, and each wave's changes are fetched a slice at a
+/// time as the walk reaches them
+struct Merged<'w> {
+    changes: Vec<Changes<'w>>,
+    /// Next change of each wave in walking order
+    heads: Vec<Option<CarriedValue>>,
+    /// Waves by the time of their next change; walking forward, times are flipped so that the
+    /// earliest comes out of the heap first
+    heap: BinaryHeap<(Toffset, usize)>,
+    forward: bool,
+}
+
+impl<'w> Merged<'w> {
+    /// Walk forward from changes at `time`, or backward from changes before it
+    fn new(waves: &[&'w InMemWave], time: Toffset, forward: bool) -> Result<Merged<'w>, Waverr> {
+        let mut merged = Merged {
+            changes: waves
+                .iter()
+                .map(|wave| {
+                    if forward {
+                        wave.changes_from(time)
+                    } else {
+                        wave.changes_until(time)
+                    }
+                })
+                .collect(),
+            heads: vec![None; waves.len()],
+            heap: BinaryHeap::with_capacity(waves.len()),
+            forward,
+        };
+        for index in 0..waves.len() {
+            merged.advance(index)?;
+        }
+        Ok(merged)
+    }
+
+    /// Heap key of a time, and the time of a heap key
+    fn key(&self, time: Toffset) -> Toffset {
+        if self.forward {
+            Toffset::MAX - time
+        } else {
+            time
+        }
+    }
+
+    /// Replace the head of wave `index` by its next change, returning the old one
+    fn advance(&mut self, index: usize) -> Result<Option<CarriedValue>, Waverr> {
+        let next = self.changes[index].next().transpose()?;
+        if let Some((time, _)) = next.as_ref() {
+            self.heap.push((self.key(*time), index));
+        }
+        Ok(std::mem::replace(&mut self.heads[index], next))
+    }
+
+    /// Time of the next change of any of the waves
+    fn peek(&self) -> Option<Toffset> {
+        self.heap.peek().map(|(key, _)| self.key(*key))
+    }
+
+    /// Walk past the next time any of the waves changes, returning it. Each wave that changes
+    /// then takes the value it has past that time in `values`: the change itself walking
+    /// forward, and the change before it walking backward
+    fn step(&mut self, values: &mut [Option<Vec<u8>>]) -> Result<Option<Toffset>, Waverr> {
+        let time = match self.peek() {
+            Some(time) => time,
+            None => return Ok(None),
+        };
+        while let Some((_, index)) = self.heap.peek().filter(|(key, _)| self.key(*key) == time) {
+            let index = *index;
+            self.heap.pop();
+            let change = self.advance(index)?;
+            values[index] = if self.forward {
+                change.map(|(_, data)| data)
+            } else {
+                self.heads[index].as_ref().map(|(_, data)| data.clone())
+            };
+        }
+        Ok(Some(time))
+    }
+}
+
+impl Expr {
+    /// Make sure every bit select is within its signal
+    fn check_bits(&self, signals: &[String], widths: &[usize]) -> Result<(), Waverr> {
+        match self {
+            Expr::Signal {
+                index,
+                bits: Some((msb, _)),
+            } if *msb >= widths[*index] => Err(Waverr::ExprErr(format!(
+                "{} is only {} bits wide",
+                signals[*index], widths[*index]
+            ))),
+            Expr::Signal { .. } | Expr::Value(..) => Ok(()),
+            Expr::Not(expr) | Expr::Invert(expr) => expr.check_bits(signals, widths),
+            Expr::Concat(exprs) => exprs
+                .iter()
+                .try_for_each(|expr| expr.check_bits(signals, widths)),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.check_bits(signals, widths)?;
+                rhs.check_bits(signals, widths)
+            }
+        }
+    }
+
+    /// Number of bits needed to hold any value of the expression
+    fn width(&self, widths: &[usize]) -> usize {
+        let width = match self {
+            Expr::Signal {
+                bits: Some((msb, lsb)),
+                ..
+            } => msb - lsb + 1,
+            Expr::Signal { index, .. } => widths[*index],
+            Expr::Value(value, size) => size.unwrap_or(value.bits() as usize),
+            Expr::Not(_) => 1,
+            Expr::Invert(expr) => expr.width(widths),
+            Expr::Concat(exprs) => exprs.iter().map(|expr| expr.width(widths)).sum(),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.width(widths), rhs.width(widths));
+                match op {
+                    BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Sub => lhs.max(rhs),
+                    BinOp::Add => lhs.max(rhs) + 1,
+                    BinOp::Mul => lhs + rhs,
+                    _ => 1,
+                }
+            }
+        };
+        width.max(1)
+    }
+
+    /// Value of the expression given the current value of each signal; `None` if it depends on
+    /// an unknown value. Comparisons and logical operators evaluate to 0 or 1
+    fn value<V: AsRef<[u8]>>(&self, values: &[Option<V>], widths: &[usize]) -> Option<BigUint> {
+        let from_bool = |truth: bool| BigUint::from(truth as u8);
+        match self {
+            Expr::Signal { index, bits } => {
+                signal_value(values[*index].as_ref()?.as_ref(), widths[*index], *bits)
+            }
+            Expr::Value(value, _) => Some(value.clone()),
+            Expr::Not(expr) => expr.truth(values, widths).map(|truth| from_bool(!truth)),
+            Expr::Invert(expr) => Some(expr.value(values, widths)? ^ mask(expr.width(widths))),
+            Expr::Concat(exprs) => exprs.iter().try_fold(BigUint::default(), |acc, expr| {
+                Some(acc << expr.width(widths) | expr.value(values, widths)?)
+            }),
+            Expr::Binary(BinOp::And, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.truth(values, widths), rhs.truth(values, widths));
+                if lhs == Some(false) || rhs == Some(false) {
+                    Some(from_bool(false))
+                } else {
+                    lhs.and(rhs).map(from_bool)
+                }
+            }
+            Expr::Binary(BinOp::Or, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.truth(values, widths), rhs.truth(values, widths));
+                if lhs == Some(true) || rhs == Some(true) {
+                    Some(from_bool(true))
+                } else {
+                    lhs.and(rhs).map(from_bool)
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.value(values, widths)?, rhs.value(values, widths)?);
+                Some(match op {
+                    BinOp::Eq => from_bool(lhs == rhs),
+                    BinOp::Ne => from_bool(lhs != rhs),
+                    BinOp::Lt => from_bool(lhs < rhs),
+                    BinOp::Le => from_bool(lhs <= rhs),
+                    BinOp::Gt => from_bool(lhs > rhs),
+                    BinOp::Ge => from_bool(lhs >= rhs),
+                    BinOp::BitAnd => lhs & rhs,
+                    BinOp::BitOr => lhs | rhs,
+                    BinOp::BitXor => lhs ^ rhs,
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => {
+                        let width = self.width(widths);
+                        (lhs + (BigUint::from(1u8) << width) - rhs) & mask(width)
+                    }
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::And | BinOp::Or => unreachable!(),
+                })
+            }
+        }
+    }
+
+    fn truth<V: AsRef<[u8]>>(&self, values: &[Option<V>], widths: &[usize]) -> Option<bool> {
+        self.value(values, widths).map(|value| value.bits() != 0)
+    }
+}
+
+/// The lowest `width` bits set
+fn mask(width: usize) -> BigUint {
+    (BigUint::from(1u8) << width) - 1u8
+}
+
+/// Value of bits `msb` through `lsb` of a droplet's data, or of all of its bits
+fn signal_value(data: &[u8], width: usize, bits: Option<(usize, usize)>) -> Option<BigUint> {
+    let (value, zx) = data.split_at(width.div_ceil(8).min(data.len()));
+    let (msb, lsb) = bits.unwrap_or((width.max(1) - 1, 0));
+    if (lsb..=msb).any(|bit| get_bit(zx, bit)) {
+        return None;
+    }
+    Some((BigUint::from_bytes_le(value) >> lsb) & mask(msb - lsb + 1))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Waverr> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some(first) = rest.chars().next() {
+        if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            let is_value = first.is_ascii_digit() || first == '\'';
+            let len = rest
+                .find(|c: char| {
+                    let word = c.is_ascii_alphanumeric() || c == '_';
+                    !(word || (is_value && c == '\'') || (!is_value && (c == '.' || c == '$')))
+                })
+                .unwrap_or(rest.len());
+            if len == 0 {
+                return Err(Waverr::ExprErr(format!("unexpected {} in {}", first, text)));
+            }
+            let word = rest[..len].to_string();
+            tokens.push(if is_value {
+                Token::Value(word)
+            } else {
+                Token::Signal(word)
+            });
+            rest = &rest[len..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Parse a value, along with its width if it is a sized Verilog style value; `0x`, `0o` and
+/// `0b` prefixes, Verilog style bases and `_` separators are allowed
+fn parse_value(text: &str) -> Result<(BigUint, Option<usize>), Waverr> {
+    let bad_value = || Waverr::ExprErr(format!("{} is not a value", text));
+    let value = text.replace('_', "").to_ascii_lowercase();
+    let (size, radix, digits) = if let Some((size, based)) = value.split_once('\'') {
+        let size = match size {
+            "" => None,
+            size => Some(size.parse::<usize>().map_err(|_| bad_value())?),
+        };
+        let based = based.strip_prefix('s').unwrap_or(based);
+        match based.chars().next() {
+            Some('h') => (size, 16, &based[1..]),
+            Some('d') => (size, 10, &based[1..]),
+            Some('o') => (size, 8, &based[1..]),
+            Some('b') => (size, 2, &based[1..]),
+            _ => return Err(bad_value()),
+        }
+    } else if let Some(hex) = value.strip_prefix("0x") {
+        (None, 16, hex)
+    } else if let Some(octal) = value.strip_prefix("0o") {
+        (None, 8, octal)
+    } else if let Some(binary) = value.strip_prefix("0b") {
+        (None, 2, binary)
+    } else {
+        (None, 10, value.as_str())
+    };
+    let value = BigUint::parse_bytes(digits.as_bytes(), radix).ok_or_else(bad_value)?;
+    match size {
+        Some(0) => Err(bad_value()),
+        Some(size) => Ok((value & mask(size), Some(size))),
+        None => Ok((value, None)),
+    }
+}
+
+/// Recursive descent parser; each method parses one level of precedence, loosest first
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+    signals: Vec<String>,
+}
+
+impl<'t> Parser<'t> {
+    fn next(&mut self) -> Option<&'t Token> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    /// Consume `op` if it is the next token
+    fn eat(&mut self, op: &str) -> bool {
+        let found = matches!(self.tokens.get(self.pos), Some(Token::Op(next)) if *next == op);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), Waverr> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(Waverr::ExprErr(format!("expected {}", op)))
+        }
+    }
+
+    /// Parse `operand`s joined by any of `ops`, grouping to the left
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        operand: fn(&mut Self) -> Result<Expr, Waverr>,
+    ) -> Result<Expr, Waverr> {
+        let mut lhs = operand(self)?;
+        while let Some((_, op)) = ops.iter().find(|(op, _)| self.eat(op)) {
+            lhs = Expr::Binary(*op, Box::new(lhs), Box::new(operand(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn or(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("||", BinOp::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("&&", BinOp::And)], Self::comparison)
+    }
+
+    /// Comparisons don't chain, so there is at most one
+    fn comparison(&mut self) -> Result<Expr, Waverr> {
+        let lhs = self.bit_or()?;
+        match COMPARISONS.iter().find(|(op, _)| self.eat(op)) {
+            Some((_, op)) => Ok(Expr::Binary(*op, Box::new(lhs), Box::new(self.bit_or()?))),
+            None => Ok(lhs),
+        }
+    }
+
+    fn bit_or(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("|", BinOp::BitOr)], Self::bit_xor)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("^", BinOp::BitXor)], Self::bit_and)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("&", BinOp::BitAnd)], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("+", BinOp::Add), ("-", BinOp::Sub)], Self::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, Waverr> {
+        self.binary(&[("*", BinOp::Mul)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Waverr> {
+        if self.eat("!") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if self.eat("~") {
+            Ok(Expr::Invert(Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, Waverr> {
+        match self.next() {
+            Some(Token::Op("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Op("{")) => {
+                let mut exprs = vec![self.or()?];
+                while self.eat(",") {
+                    exprs.push(self.or()?);
+                }
+                self.expect("}")?;
+                Ok(Expr::Concat(exprs))
+            }
+            Some(Token::Value(value)) => {
+                parse_value(value).map(|(value, size)| Expr::Value(value, size))
+            }
+            Some(Token::Signal(name)) => {
+                let index = match self.signals.iter().position(|signal| signal == name) {
+                    Some(index) => index,
+                    None => {
+                        self.signals.push(name.clone());
+                        self.signals.len() - 1
+                    }
+                };
+                let bits = if self.eat("[") {
+                    let msb = self.bit()?;
+                    let lsb = if self.eat(":") { self.bit()? } else { msb };
+                    self.expect("]")?;
+                    if lsb > msb {
+                        return Err(Waverr::ExprErr(format!(
+                            "bits of {} must be selected msb first",
+                            name
+                        )));
+                    }
+                    Some((msb, lsb))
+                } else {
+                    None
+                };
+                Ok(Expr::Signal { index, bits })
+            }
+            Some(token) => Err(Waverr::ExprErr(format!(
+                "expected a signal or value, got {:?}",
+                token
+            ))),
+            None => Err(Waverr::ExprErr("expected a signal or value".to_string())),
+        }
+    }
+
+    fn bit(&mut self) -> Result<usize, Waverr> {
+        match self.next() {
+            Some(Token::Value(bit)) => bit
+                .parse()
+                .map_err(|_| Waverr::ExprErr(format!("{} is not a bit", bit))),
+            _ => Err(Waverr::ExprErr("expected a bit".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use crate::wavedb::WaveDb;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case("valid && ready && data[7:0] == 8'hff", &["valid", "ready", "data"]; "conjunction")]
+    #[test_case("!(a || b) && a[3] != 0x1", &["a", "b"]; "repeated signal")]
+    #[test_case("TOP.vga.x_addr >= 'd10", &["TOP.vga.x_addr"]; "dotted name")]
+    #[test_case("{hi, lo[3:0]} + ~hi", &["hi", "lo"]; "concatenation")]
+    fn parse_tests(text: &str, signals: &[&str]) {
+        let expression = Expression::parse(text).unwrap();
+        assert_eq!(expression.signals(), signals);
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("a &&"; "dangling operator")]
+    #[test_case("(a || b"; "unclosed paren")]
+    #[test_case("a[0:3]"; "reversed bits")]
+    #[test_case("a[b]"; "signal as bit")]
+    #[test_case("a == 8'q1"; "bad base")]
+    #[test_case("a b"; "missing operator")]
+    #[test_case("a == #"; "bad character")]
+    #[test_case("{a, }"; "dangling comma")]
+    #[test_case("a + 0'h1"; "zero width value")]
+    fn parse_fail_tests(text: &str) {
+        assert!(Expression::parse(text).is_err());
+    }
+
+    #[test_case("a[3:0] == 0xf", &[0xff, 0x00], 8, true; "bit select")]
+    #[test_case("a[7:4] < 3", &[0x2f, 0x00], 8, true; "upper bits")]
+    #[test_case("a[3]", &[0x00, 0x08], 8, false; "unknown bit")]
+    #[test_case("a[3] || a[2]", &[0x04, 0x08], 8, true; "unknown or true")]
+    #[test_case("a[3] && a[2]", &[0x00, 0x08], 8, false; "unknown and false")]
+    #[test_case("!a[7:4]", &[0x08, 0x08], 8, true; "unknown outside select")]
+    fn truth_tests(text: &str, data: &[u8], width: usize, truth: bool) {
+        let expression = Expression::parse(text).unwrap();
+        let truth_at = expression.expr.truth(&[Some(data)], &[width]);
+        assert_eq!(truth_at == Some(true), truth);
+    }
+
+    #[test_case("a[7:4] + a[3:0]", 15, 5; "sum")]
+    #[test_case("a[3:0] - a[7:4]", 9, 4; "difference")]
+    #[test_case("a[7:4] - a[3:0]", 7, 4; "wrapping difference")]
+    #[test_case("a[3:0] * a[7:4]", 36, 8; "product")]
+    #[test_case("~a[3:0]", 3, 4; "invert")]
+    #[test_case("{a[3:0], a[7:4], 2'b01}", 0x30d, 10; "concatenation")]
+    #[test_case("a & 0xf0 | 1", 0x31, 8; "bitwise")]
+    #[test_case("a ^ 8'hff == 0xc3", 1, 1; "xor binds tighter than comparison")]
+    #[test_case("4'h1f", 0xf, 4; "sized value")]
+    fn value_tests(text: &str, value: u64, width: usize) {
+        let expression = Expression::parse(text).unwrap();
+        let data: &[u8] = &[0x3c];
+        assert_eq!(
+            expression.expr.value(&[Some(data)], &[8]),
+            Some(BigUint::from(value))
+        );
+        assert_eq!(expression.expr.width(&[8]), width);
+    }
+
+    fn create_vga_wdb() -> (WaveDb, tempfile::TempDir) {
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+        (wdb, db)
+    }
+
+    /// Value of a wave's droplet data, if it has no unknown bits
+    fn known_value(data: &[u8], width: usize) -> Option<u64> {
+        let (value, zx) = data.split_at(width.div_ceil(8));
+        if zx.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        Some(
+            value
+                .iter()
+                .rev()
+                .fold(0, |acc, byte| acc << 8 | *byte as u64),
+        )
+    }
+
+    #[test_case("vga_hs && !reset", |v| v[0] == Some(1) && v[1] == Some(0); "and not")]
+    #[test_case("!(vga_hs || reset)", |v| v[0] == Some(0) && v[1] == Some(0); "nor")]
+    #[test_case(
+        "x_addr[3:0] == 4'hf || y_addr > 10",
+        |v| v[0].is_some_and(|x| x & 0xf == 0xf) || v[1].is_some_and(|y| y > 10);
+        "bit select"
+    )]
+    fn interval_tests(text: &str, oracle: fn(&[Option<u64>]) -> bool) {
+        let (wdb, _db) = create_vga_wdb();
+        let expression = Expression::parse(text).unwrap();
+        let waves: Vec<Arc<InMemWave>> = expression
+            .signals()
+            .iter()
+            .map(|signal| wdb.get_imw(format!("TOP.{}", signal)).unwrap())
+            .collect();
+        let wave_refs: Vec<&InMemWave> = waves.iter().map(|wave| wave.as_ref()).collect();
+
+        for (begin, end) in [(0, Toffset::MAX), (5000, 20_000)].iter() {
+            let windows: Vec<InMemWave> = waves
+                .iter()
+                .map(|wave| wave.window(0, Toffset::MAX).unwrap())
+                .collect();
+            let mut times: Vec<Toffset> = windows
+                .iter()
+                .flat_map(|wave| wave.all_data().map(|(time, _)| time))
+                .filter(|time| time > begin && time < end)
+                .collect();
+            times.push(*begin);
+            times.sort_unstable();
+            times.dedup();
+
+            let mut baseline = Vec::new();
+            let mut start = None;
+            for time in times {
+                let values: Vec<Option<u64>> = windows
+                    .iter()
+                    .map(|wave| {
+                        wave.get_prev_time(time + 1)
+                            .and_then(|(_, data)| known_value(data, wave.get_width()))
+                    })
+                    .collect();
+                match (oracle(&values), start) {
+                    (true, None) => start = Some(time),
+                    (false, Some(from)) => {
+                        baseline.push((from, time));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            baseline.extend(start.map(|from| (from, *end)));
+            assert!(!baseline.is_empty());
+
+            let intervals = expression.intervals(&wave_refs, *begin, *end).unwrap();
+            assert_eq!(intervals, baseline);
+        }
+    }
+
+    #[test_case("x_addr", Some("TOP.vga.x_addr"); "unique name")]
+    #[test_case("TOP.vga.valid", Some("TOP.vga.valid"); "path")]
+    #[test_case("valid", None; "ambiguous name")]
+    #[test_case("TOP.valid", None; "missing path")]
+    fn resolve_tests(signal: &str, path: Option<&str>) {
+        let waves: Vec<InMemWave> = ["TOP.vga.x_addr", "TOP.vga.valid", "TOP.bus.valid"]
+            .iter()
+            .enumerate()
+            .map(|(id, path)| {
+                let name = path.rsplit('.').next().unwrap().to_string();
+                InMemWave::new(name, id as u32, 1, Vec::new())
+                    .unwrap()
+                    .with_path(path.to_string())
+            })
+            .collect();
+        // the same signal shown twice isn't ambiguous
+        let wave_refs: Vec<&InMemWave> = waves.iter().chain(waves.first()).collect();
+        let expression = Expression::parse(signal).unwrap();
+        let resolved = expression.resolve(&wave_refs);
+        assert_eq!(
+            resolved.ok().map(|waves| waves[0].get_path().to_string()),
+            path.map(str::to_string)
+        );
+    }
+
+    #[test]
+    fn ambiguous_names_list_candidates() {
+        let waves: Vec<InMemWave> = ["TOP.vga.valid", "TOP.bus.valid"]
+            .iter()
+            .map(|path| {
+                InMemWave::new("valid".to_string(), 0, 1, Vec::new())
+                    .unwrap()
+                    .with_path(path.to_string())
+            })
+            .collect();
+        let wave_refs: Vec<&InMemWave> = waves.iter().collect();
+        let expression = Expression::parse("valid").unwrap();
+        match expression.resolve(&wave_refs) {
+            Err(Waverr::ExprErr(err)) => {
+                assert!(err.contains("TOP.vga.valid") && err.contains("TOP.bus.valid"))
+            }
+            other => panic!("expected an ambiguous name, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bits_past_width() {
+        let (wdb, _db) = create_vga_wdb();
+        let expression = Expression::parse("x_addr[10]").unwrap();
+        let wave = wdb.get_imw("TOP.x_addr".to_string()).unwrap();
+        assert!(expression.intervals(&[wave.as_ref()], 0, 100).is_err());
+    }
+
+    #[test_case("vga_hs && !reset"; "and not")]
+    #[test_case("x_addr[3:0] == 4'hf || y_addr > 10"; "bit select")]
+    #[test_case("!vga_hs"; "holds at start")]
+    fn step_through_starts(text: &str) {
+        let (wdb, _db) = create_vga_wdb();
+        let expression = Expression::parse(text).unwrap();
+        let waves: Vec<Arc<InMemWave>> = expression
+            .signals()
+            .iter()
+            .map(|signal| wdb.get_imw(format!("TOP.{}", signal)).unwrap())
+            .collect();
+        let wave_refs: Vec<&InMemWave> = waves.iter().map(|wave| wave.as_ref()).collect();
+        let (first, _) = wave_refs[0].time_range();
+        let starts: Vec<Toffset> = expression
+            .intervals(&wave_refs, first, Toffset::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|(start, _)| start)
+            .collect();
+        assert!(!starts.is_empty());
+
+        let mut forward = Vec::new();
+        let mut time = 0;
+        while let Some(start) = expression.next_start(&wave_refs, time).unwrap() {
+            forward.push(start);
+            time = start;
+        }
+        let mut backward = Vec::new();
+        let mut time = Toffset::MAX;
+        while let Some(start) = expression.prev_start(&wave_refs, time).unwrap() {
+            backward.push(start);
+            time = start;
+        }
+        backward.reverse();
+        assert_eq!(forward, starts);
+        assert_eq!(backward, starts);
+    }
+}
@@ -1,6 +1,10 @@
+use crate::errors::Waverr;
 use crate::puddle::utils;
 use crate::puddle::Droplet;
-#[derive(Clone, Copy, Debug)]
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[derive(Clone, Copy, Debug, PartialEq)]
 ///Represents ways to format ParsedVec into String
 pub enum WaveFormat {
     Decimal,
@@ -8,6 +12,110 @@ pub enum WaveFormat {
     Binary,
     Octal,
     SDecimal,
+    /// Unsigned fixed point with the given number of fractional bits
+    UFixed(u32),
+    /// Two's complement fixed point with the given number of fractional bits
+    SFixed(u32),
+    /// The vector reinterpreted as an IEEE-754 half precision float
+    F16,
+    /// The vector reinterpreted as an IEEE-754 single precision float
+    F32,
+    /// The vector reinterpreted as an IEEE-754 double precision float
+    F64,
+    /// Every byte of the vector as a character, most significant byte first
+    Ascii,
+    /// The name the value was given in an [`EnumMap`]
+    Enum,
+}
+
+/// User supplied names for the values of a signal, such as the states of an FSM
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EnumMap {
+    names: BTreeMap<u64, String>,
+}
+
+impl EnumMap {
+    pub fn new() -> EnumMap {
+        EnumMap::default()
+    }
+
+    /// Name `value`, returning the name it had before
+    pub fn insert(&mut self, value: u64, name: String) -> Option<String> {
+        self.names.insert(value, name)
+    }
+
+    pub fn remove(&mut self, value: u64) -> Option<String> {
+        self.names.remove(&value)
+    }
+
+    pub fn get(&self, value: u64) -> Option<&str> {
+        self.names.get(&value).map(|name| name.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &str)> + '_ {
+        self.names
+            .iter()
+            .map(|(value, name)| (*value, name.as_str()))
+    }
+
+    /// Parse a `value = name` entry, as typed into the UI
+    pub fn parse_entry(entry: &str) -> Result<(u64, String), Waverr> {
+        let (value, name) = entry
+            .split_once('=')
+            .ok_or_else(|| Waverr::EnumMapErr(format!("expected value = name, got {}", entry)))?;
+        let name = name.trim().trim_matches('"');
+        if name.is_empty() {
+            return Err(Waverr::EnumMapErr(format!("no name given in {}", entry)));
+        }
+        Ok((parse_enum_value(value)?, name.to_string()))
+    }
+}
+
+/// Parse a value of an enum map; `0x`, `0o` and `0b` prefixes and `_` separators are allowed
+fn parse_enum_value(text: &str) -> Result<u64, Waverr> {
+    let text = text.trim().replace('_', "");
+    let lowered = text.to_ascii_lowercase();
+    let parsed = if let Some(hex) = lowered.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(octal) = lowered.strip_prefix("0o") {
+        u64::from_str_radix(octal, 8)
+    } else if let Some(binary) = lowered.strip_prefix("0b") {
+        u64::from_str_radix(binary, 2)
+    } else {
+        lowered.parse()
+    };
+    parsed.map_err(|_| Waverr::EnumMapErr(format!("{} is not a value", text)))
+}
+
+/// Parse enum maps from TOML; every table is named by the full path of a signal, and maps its
+/// values to names:
+///
+/// ```toml
+/// ["top.cpu.fsm_state"]
+/// 0 = "IDLE"
+/// 0x1 = "FETCH"
+/// 0b10 = "DECODE"
+/// ```
+pub fn parse_enum_maps(text: &str) -> Result<Vec<(String, EnumMap)>, Waverr> {
+    let tables: BTreeMap<String, BTreeMap<String, String>> = toml::from_str(text)?;
+    tables
+        .into_iter()
+        .map(|(signal, entries)| {
+            let mut enum_map = EnumMap::new();
+            for (value, name) in entries {
+                enum_map.insert(parse_enum_value(&value)?, name);
+            }
+            Ok((signal, enum_map))
+        })
+        .collect()
 }
 
 fn split_zx_and_payload(drop: Droplet<'_>, bitwidth: usize) -> (&'_ [u8], &'_ [u8]) {
@@ -15,13 +123,255 @@ fn split_zx_and_payload(drop: Droplet<'_>, bitwidth: usize) -> (&'_ [u8], &'_ [u
     drop.take_data().split_at(bitwidth_idx)
 }
 
+/// Value and zx bits of `drop`, least significant byte first, with any bits past `bitwidth`
+/// cleared
+fn value_and_zx(drop: Droplet<'_>, bitwidth: usize) -> (Vec<u8>, Vec<u8>) {
+    let num_bytes = bitwidth.div_ceil(8);
+    let (payload, zx) = if drop.is_zx() {
+        split_zx_and_payload(drop, bitwidth)
+    } else {
+        (drop.take_data(), &[][..])
+    };
+    let mask = |bytes: &[u8]| {
+        let mut bytes = bytes.to_vec();
+        bytes.resize(num_bytes, 0);
+        if let (Some(top), 1..=7) = (bytes.last_mut(), bitwidth % 8) {
+            *top &= (1 << (bitwidth % 8)) - 1;
+        }
+        bytes
+    };
+    (mask(payload), mask(zx))
+}
+
+pub(crate) fn get_bit(bytes: &[u8], bit: usize) -> bool {
+    bytes
+        .get(bit / 8)
+        .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+/// Character shown for a group of bits with some of them undefined; x wins over z, like it
+/// does for hex
+fn zx_char(value: &[u8], zx: &[u8]) -> char {
+    if value
+        .iter()
+        .zip(zx.iter())
+        .any(|(value, zx)| value & zx != 0)
+    {
+        'x'
+    } else {
+        'z'
+    }
+}
+
+fn any_zx(zx: &[u8]) -> bool {
+    zx.iter().any(|byte| *byte != 0)
+}
+
+/// Undefined state of a whole value, used to decide how it is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZxState {
+    /// At least one bit is unknown
+    X,
+    /// Every bit is high impedance
+    Z,
+}
+
+/// Whether the value of `drop` is undefined. Values that only have some of their bits at z are
+/// not, so that their defined bits are still shown
+pub fn zx_state(drop: Droplet<'_>, bitwidth: usize) -> Option<ZxState> {
+    if !drop.is_zx() {
+        return None;
+    }
+    data_zx_state(drop.take_data(), bitwidth)
+}
+
+/// [`zx_state`] for data handed out without its droplet, such as the values
+/// [`InMemWave`] carries across time slices; zx bits follow the value bits when there are any
+///
+/// [`InMemWave`]: crate::storage::in_memory::InMemWave
+pub fn data_zx_state(data: &[u8], bitwidth: usize) -> Option<ZxState> {
+    let num_bytes = bitwidth.div_ceil(8);
+    if num_bytes == 0 || data.len() < 2 * num_bytes {
+        return None;
+    }
+    let (value, zx) = data.split_at(num_bytes);
+    if (0..bitwidth).any(|bit| get_bit(zx, bit) && get_bit(value, bit)) {
+        Some(ZxState::X)
+    } else if (0..bitwidth).all(|bit| get_bit(zx, bit)) {
+        Some(ZxState::Z)
+    } else {
+        None
+    }
+}
+
+/// Split `value` into its sign and magnitude; unsigned values are never negative
+fn sign_and_magnitude(value: &[u8], bitwidth: usize, signed: bool) -> (bool, BigUint) {
+    let unsigned = BigUint::from_bytes_le(value);
+    if signed && bitwidth > 0 && get_bit(value, bitwidth - 1) {
+        (true, (BigUint::from(1u8) << bitwidth) - unsigned)
+    } else {
+        (false, unsigned)
+    }
+}
+
+/// Format as a (possibly signed) decimal number. A single undefined bit makes the whole number
+/// undefined, so the value is shown as a lone x or z
+fn format_decimal(value: &[u8], zx: &[u8], bitwidth: usize, signed: bool) -> String {
+    if any_zx(zx) {
+        return zx_char(value, zx).to_string();
+    }
+    match sign_and_magnitude(value, bitwidth, signed) {
+        (true, magnitude) => format!("-{}", magnitude),
+        (false, magnitude) => magnitude.to_string(),
+    }
+}
+
+/// Format as a fixed point number with `frac_bits` fractional bits. Every binary fraction has
+/// a finite decimal expansion, so this is exact: value / 2^n is (value * 5^n) / 10^n
+fn format_fixed(value: &[u8], zx: &[u8], bitwidth: usize, frac_bits: u32, signed: bool) -> String {
+    if any_zx(zx) {
+        return zx_char(value, zx).to_string();
+    }
+    let (negative, magnitude) = sign_and_magnitude(value, bitwidth, signed);
+    let frac_digits = frac_bits as usize;
+    let digits = (magnitude * BigUint::from(5u8).pow(frac_bits)).to_string();
+    let digits = format!("{:0>width$}", digits, width = frac_digits + 1);
+    let (int, frac) = digits.split_at(digits.len() - frac_digits);
+    let frac = frac.trim_end_matches('0');
+    let sign = if negative { "-" } else { "" };
+    if frac.is_empty() {
+        format!("{}{}", sign, int)
+    } else {
+        format!("{}{}.{}", sign, int, frac)
+    }
+}
+
+/// Widen an IEEE-754 half to an f32, which can represent every half exactly
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    match exponent {
+        0 => {
+            // zero or subnormal; mantissa * 2^-24
+            let magnitude = mantissa as f32 * 2f32.powi(-24);
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 127 - 15) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Reinterpret the low `float_bits` bits of the vector as an IEEE-754 float
+fn format_float(value: &[u8], zx: &[u8], float_bits: usize) -> String {
+    if any_zx(zx) {
+        return zx_char(value, zx).to_string();
+    }
+    let mut bytes = [0u8; 8];
+    for (byte, value) in bytes.iter_mut().zip(value.iter()).take(float_bits / 8) {
+        *byte = *value;
+    }
+    let raw = u64::from_le_bytes(bytes);
+    match float_bits {
+        16 => f16_to_f32(raw as u16).to_string(),
+        32 => f32::from_bits(raw as u32).to_string(),
+        _ => f64::from_bits(raw).to_string(),
+    }
+}
+
+/// Format as octal, most significant digit first; each digit covers 3 bits
+fn format_octal(value: &[u8], zx: &[u8], bitwidth: usize) -> String {
+    let num_digits = bitwidth.div_ceil(3);
+    (0..num_digits)
+        .rev()
+        .map(|digit| {
+            let bits = digit * 3..(digit * 3 + 3).min(bitwidth);
+            let digit_of = |bytes: &[u8]| {
+                bits.clone()
+                    .rev()
+                    .fold(0u8, |acc, bit| (acc << 1) | get_bit(bytes, bit) as u8)
+            };
+            match (digit_of(value), digit_of(zx)) {
+                (value, 0) => char::from(b'0' + value),
+                (value, zx) => zx_char(&[value], &[zx]),
+            }
+        })
+        .collect()
+}
+
+/// Format every byte as a character, most significant byte first. Leading NULs are treated as
+/// padding and skipped; other non-printable bytes are escaped
+fn format_ascii(value: &[u8], zx: &[u8]) -> String {
+    let top = value
+        .iter()
+        .zip(zx.iter())
+        .rposition(|(value, zx)| *value != 0 || *zx != 0)
+        .unwrap_or(0);
+    value[..=top]
+        .iter()
+        .zip(zx.iter())
+        .rev()
+        .map(|(value, zx)| match (*value, *zx) {
+            (_, 0x01..=0xff) => zx_char(&[*value], &[*zx]).to_string(),
+            (b'\\', _) => String::from("\\\\"),
+            (b'\n', _) => String::from("\\n"),
+            (b'\r', _) => String::from("\\r"),
+            (b'\t', _) => String::from("\\t"),
+            (0, _) => String::from("\\0"),
+            (printable @ 0x20..=0x7e, _) => char::from(printable).to_string(),
+            (other, _) => format!("\\x{:02x}", other),
+        })
+        .collect()
+}
+
+/// Format the value of a signal by its name in `enum_map`; values without a name, or with
+/// undefined bits, fall back to hex
+pub fn format_enum(
+    drop: Droplet,
+    enum_map: &EnumMap,
+    bitwidth: usize,
+    visible_chars: usize,
+) -> String {
+    let data = drop.get_data();
+    let name = if drop.is_zx() || data.iter().skip(8).any(|byte| *byte != 0) {
+        None
+    } else {
+        let value = data
+            .iter()
+            .take(8)
+            .rev()
+            .fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+        enum_map.get(value)
+    };
+    match name {
+        Some(name) => name.chars().take(visible_chars).collect(),
+        None => format_payload(drop, WaveFormat::Hex, bitwidth, visible_chars),
+    }
+}
+
 pub fn format_payload(
     drop: Droplet,
     format: WaveFormat,
     bitwidth: usize,
     visible_chars: usize,
 ) -> String {
-    let visible_chars = visible_chars.min(bitwidth);
+    // a signed number can need one more character than it has bits, for its sign
+    let visible_chars = match format {
+        WaveFormat::SDecimal => visible_chars.min(bitwidth + 1),
+        // fractions and floats can take many more characters than they have bits
+        WaveFormat::UFixed(_)
+        | WaveFormat::SFixed(_)
+        | WaveFormat::F16
+        | WaveFormat::F32
+        | WaveFormat::F64
+        | WaveFormat::Ascii
+        | WaveFormat::Enum => visible_chars,
+        _ => visible_chars.min(bitwidth),
+    };
     let mut gen_str: String = match format {
         WaveFormat::Hex => {
             if drop.is_zx() {
@@ -106,18 +456,76 @@ pub fn format_payload(
                     .collect()
             }
         }
-        _ => {
-            unimplemented!()
+        WaveFormat::Decimal => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_decimal(&value, &zx, bitwidth, false)
+        }
+        WaveFormat::SDecimal => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_decimal(&value, &zx, bitwidth, true)
         }
+        WaveFormat::Octal => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_octal(&value, &zx, bitwidth)
+        }
+        WaveFormat::UFixed(frac_bits) => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_fixed(&value, &zx, bitwidth, frac_bits, false)
+        }
+        WaveFormat::SFixed(frac_bits) => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_fixed(&value, &zx, bitwidth, frac_bits, true)
+        }
+        WaveFormat::F16 | WaveFormat::F32 | WaveFormat::F64 => {
+            let float_bits = match format {
+                WaveFormat::F16 => 16,
+                WaveFormat::F32 => 32,
+                _ => 64,
+            };
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_float(&value, &zx, float_bits)
+        }
+        WaveFormat::Ascii => {
+            let (value, zx) = value_and_zx(drop, bitwidth);
+            format_ascii(&value, &zx)
+        }
+        // without an enum map there are no names to show
+        WaveFormat::Enum => return format_payload(drop, WaveFormat::Hex, bitwidth, visible_chars),
     };
     gen_str.truncate(visible_chars);
     gen_str
 }
 
+/// Format the value of an enumerated signal as the name of its literal; values without a
+/// literal fall back to hex
+pub fn format_literal(
+    drop: Droplet,
+    literals: &[String],
+    bitwidth: usize,
+    visible_chars: usize,
+) -> String {
+    if drop.is_zx() {
+        return String::from("x");
+    }
+    let value = drop
+        .get_data()
+        .iter()
+        .take(8)
+        .rev()
+        .fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+    match literals.get(value as usize) {
+        Some(literal) => literal.chars().take(visible_chars).collect(),
+        None => format_payload(drop, WaveFormat::Hex, bitwidth, visible_chars),
+    }
+}
+
 #[cfg(test)]
 #[allow(dead_code, unused_macros, unused_imports, unused_variables)]
 mod tests {
-    use super::{format_payload, WaveFormat};
+    use super::{
+        data_zx_state, format_enum, format_literal, format_payload, parse_enum_maps, zx_state,
+        EnumMap, WaveFormat, ZxState,
+    };
     use crate::puddle::testing_utils::test_droplet;
     use test_case::test_case;
 
@@ -152,4 +560,234 @@ mod tests {
         let output = format_payload(droplet, WaveFormat::Binary, bitwidth, num_chars);
         assert_eq!(output, String::from(baseline));
     }
+
+    #[test_case([0,0,0xef,0xbe,0xad,0xde], 32, 20, "3735928559"; "vanilla dec")]
+    #[test_case([0,0,0xef,0xbe,0xad,0xde], 32, 4, "3735"; "truncated dec")]
+    #[test_case([0,0,0xff,0xff], 9, 20, "511"; "masked dec")]
+    #[test_case([0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0x01], 136, 50,
+        "340282366920938463463374607431768211456"; "wide dec")]
+    #[test_case([0,0x80,0xef,0x0b,0xaa,0xfe], 16, 20, "x"; "zx dec")]
+    #[test_case([0,0x80,0x00,0x00,0xff,0xff], 16, 20, "z"; "z dec")]
+    fn dectests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, WaveFormat::Decimal, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0xff,0x7f], 16, 20, "32767"; "positive sdec")]
+    #[test_case([0,0,0xff,0xff], 16, 20, "-1"; "negative sdec")]
+    #[test_case([0,0,0x00,0x01], 9, 20, "-256"; "non pow 2 sdec")]
+    #[test_case([0,0,0x01], 1, 20, "-1"; "single bit sdec")]
+    #[test_case([0,0,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,
+        0xff,0xff], 136, 50, "-1"; "wide sdec")]
+    #[test_case([0,0,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
+        0x00,0x80], 136, 50, "-43556142965880123323311949751266331066368"; "wide min sdec")]
+    #[test_case([0,0x80,0x00,0x80,0x01,0x00], 16, 20, "z"; "zx sdec")]
+    fn sdectests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, WaveFormat::SDecimal, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0x2f,0x01], 9, 20, "457"; "vanilla oct")]
+    #[test_case([0,0,0x05,0x00], 10, 20, "0005"; "padded oct")]
+    #[test_case([0,0,0xef,0xbe,0xad,0xde], 32, 4, "3365"; "truncated oct")]
+    #[test_case([0,0x80,0x2f,0x01,0x07,0x00], 9, 20, "45x"; "x oct")]
+    #[test_case([0,0x80,0x00,0x01,0x38,0x00], 9, 20, "4z0"; "z oct")]
+    fn octtests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, WaveFormat::Octal, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0x28], WaveFormat::UFixed(4), 8, 20, "2.5"; "vanilla ufixed")]
+    #[test_case([0,0,0x28], WaveFormat::UFixed(0), 8, 20, "40"; "integer ufixed")]
+    #[test_case([0,0,0x01], WaveFormat::UFixed(8), 8, 20, "0.00390625"; "all fraction ufixed")]
+    #[test_case([0,0,0x03], WaveFormat::UFixed(12), 8, 20, "0.000732421875"; "wide fraction ufixed")]
+    #[test_case([0,0,0xd8], WaveFormat::SFixed(4), 8, 20, "-2.5"; "negative sfixed")]
+    #[test_case([0,0,0xd8], WaveFormat::SFixed(4), 8, 2, "-2"; "truncated sfixed")]
+    #[test_case([0,0,0x00,0x80], WaveFormat::SFixed(15), 16, 20, "-1"; "q15 min sfixed")]
+    #[test_case([0,0,0x00,0x40], WaveFormat::SFixed(15), 16, 20, "0.5"; "q15 half sfixed")]
+    #[test_case([0,0x80,0x28,0x01], WaveFormat::SFixed(4), 8, 20, "z"; "zx sfixed")]
+    fn fixedtests<T: Into<Vec<u8>>>(
+        content: T,
+        format: WaveFormat,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, format, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0x00,0x3c], WaveFormat::F16, 16, 20, "1"; "one f16")]
+    #[test_case([0,0,0x00,0xc0], WaveFormat::F16, 16, 20, "-2"; "negative f16")]
+    #[test_case([0,0,0x01,0x00], WaveFormat::F16, 16, 20, "0.000000059604645"; "subnormal f16")]
+    #[test_case([0,0,0x00,0x7c], WaveFormat::F16, 16, 20, "inf"; "inf f16")]
+    #[test_case([0,0,0x00,0x00,0xc0,0x3f], WaveFormat::F32, 32, 20, "1.5"; "vanilla f32")]
+    #[test_case([0,0,0x9a,0x99,0x99,0x99,0x99,0x99,0xb9,0x3f], WaveFormat::F64, 64, 20, "0.1";
+        "vanilla f64")]
+    #[test_case([0,0,0x00,0x00,0xc0,0x7f], WaveFormat::F32, 32, 20, "NaN"; "nan f32")]
+    #[test_case([0,0x80,0x00,0x00,0xc0,0x3f,0x01,0x00,0x00,0x00], WaveFormat::F32, 32, 20, "z";
+        "zx f32")]
+    fn floattests<T: Into<Vec<u8>>>(
+        content: T,
+        format: WaveFormat,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, format, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0x01], 1, None; "defined bit")]
+    #[test_case([0,0x80,0x01,0x01], 1, Some(ZxState::X); "x bit")]
+    #[test_case([0,0x80,0x00,0x01], 1, Some(ZxState::Z); "z bit")]
+    #[test_case([0,0x80,0x00,0x00,0xff,0x0f], 12, Some(ZxState::Z); "z bus")]
+    #[test_case([0,0x80,0x00,0x04,0xff,0x0f], 12, Some(ZxState::X); "z bus with an x bit")]
+    #[test_case([0,0x80,0x05,0x00,0xf0,0x0f], 12, None; "partially z bus")]
+    fn zxstatetests<T: Into<Vec<u8>>>(content: T, bitwidth: usize, baseline: Option<ZxState>) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        assert_eq!(zx_state(droplet, bitwidth), baseline);
+        if droplet.is_zx() {
+            assert_eq!(data_zx_state(droplet.get_data(), bitwidth), baseline);
+        } else {
+            // defined data is never long enough to hold zx bits
+            assert_eq!(data_zx_state(droplet.get_data(), bitwidth), None);
+        }
+    }
+
+    #[test_case([0,0,0x4b,0x4f], 16, 20, "OK"; "vanilla ascii")]
+    #[test_case([0,0,0x4b,0x4f,0,0], 32, 20, "OK"; "padded ascii")]
+    #[test_case([0,0,0x00,0x0a,0x49], 24, 20, "I\\n\\0"; "escaped ascii")]
+    #[test_case([0,0,0x7f,0x5c], 16, 20, "\\\\\\x7f"; "hex escaped ascii")]
+    #[test_case([0,0,0x00,0x00], 16, 20, "\\0"; "empty ascii")]
+    #[test_case([0,0,0x44,0x43,0x42,0x41], 32, 2, "AB"; "truncated ascii")]
+    #[test_case([0,0x80,0x4b,0x4f,0x01,0x00], 16, 20, "Ox"; "zx ascii")]
+    fn asciitests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_payload(droplet, WaveFormat::Ascii, bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case([0,0,0x01], 2, 20, "busy"; "vanilla literal")]
+    #[test_case([0,0,0x02], 2, 2, "do"; "truncated literal")]
+    #[test_case([0,0,0x03], 2, 20, "3"; "out of range literal")]
+    #[test_case([0,0x80,0x01,0x02], 2, 20, "x"; "zx literal")]
+    fn literaltests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let literals: Vec<String> = ["idle", "busy", "done"].iter().map(|s| s.to_string()).collect();
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_literal(droplet, literals.as_slice(), bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    fn fsm_states() -> EnumMap {
+        let mut enum_map = EnumMap::new();
+        enum_map.insert(0, "IDLE".into());
+        enum_map.insert(5, "FETCH".into());
+        enum_map.insert(0x1_0000_0000, "WIDE".into());
+        enum_map
+    }
+
+    #[test_case([0,0,0x05,0x00], 12, 20, "FETCH"; "vanilla enum")]
+    #[test_case([0,0,0x05,0x00], 12, 2, "FE"; "truncated enum")]
+    #[test_case([0,0,0xab,0x01], 12, 20, "1ab"; "unmapped enum")]
+    #[test_case([0,0,0,0,0,0,0x01], 40, 20, "WIDE"; "wide enum")]
+    #[test_case([0,0x80,0x0f,0xf0], 8, 20, "fz"; "zx enum")]
+    fn enumtests<T: Into<Vec<u8>>>(
+        content: T,
+        bitwidth: usize,
+        num_chars: usize,
+        baseline: &'static str,
+    ) {
+        let content: Vec<u8> = content.into();
+        let droplet = test_droplet(content.as_slice());
+        let output = format_enum(droplet, &fsm_states(), bitwidth, num_chars);
+        assert_eq!(output, String::from(baseline));
+    }
+
+    #[test_case("5 = FETCH", Some((5, "FETCH")); "vanilla entry")]
+    #[test_case(" 0x1f = \"LOAD\" ", Some((0x1f, "LOAD")); "hex entry")]
+    #[test_case("0b1_0 = DECODE", Some((2, "DECODE")); "binary entry")]
+    #[test_case("FETCH", None; "missing value")]
+    #[test_case("0x1f =", None; "missing name")]
+    #[test_case("0xg = NOPE", None; "bad value")]
+    fn enum_entries(entry: &str, expected: Option<(u64, &str)>) {
+        let parsed = EnumMap::parse_entry(entry).ok();
+        assert_eq!(
+            parsed.as_ref().map(|(value, name)| (*value, name.as_str())),
+            expected
+        );
+    }
+
+    #[test]
+    fn enum_maps_from_toml() {
+        let maps = parse_enum_maps(
+            r#"
+            ["top.cpu.state"]
+            0 = "IDLE"
+            0x1 = "FETCH"
+            0b10 = "DECODE"
+
+            ["top.dma.state"]
+            3 = "DONE"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(maps.len(), 2);
+        let (signal, cpu) = &maps[0];
+        assert_eq!(signal, "top.cpu.state");
+        assert_eq!(
+            cpu.iter().collect::<Vec<_>>(),
+            vec![(0, "IDLE"), (1, "FETCH"), (2, "DECODE")]
+        );
+        assert_eq!(maps[1].1.get(3), Some("DONE"));
+        assert!(parse_enum_maps("[\"top.state\"]\nidle = \"IDLE\"").is_err());
+    }
+
+    #[test]
+    fn enum_format_without_map() {
+        let droplet = test_droplet(&[0, 0, 0xef, 0xbe]);
+        assert_eq!(format_payload(droplet, WaveFormat::Enum, 16, 20), "beef");
+
+        // only the low 64 bits can be named; wider values are never looked up
+        let droplet = test_droplet(&[0, 0, 0x05, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+        assert_ne!(format_enum(droplet, &fsm_states(), 72, 20), "FETCH");
+    }
 }
@@ -0,0 +1,736 @@
+/*! Native reader for FST dumps, as written by GTKWave's `fstapi` (and so by Verilator, Icarus and GHDL).
+
+[`FstParser`] plays the same role as [`WaveParser`](crate::vcd_parser::WaveParser): it builds a
+[`HierMap`] out of the hierarchy block, then yields the value changes of every block as
+[`vcd::Command`]s so that puddles are built exactly the same way regardless of the dump format.
+
+The FST handle of a signal (minus one) is used as its signal id.
+!*/
+use crate::errors::Waverr;
+use crate::hier_map::{HierMap, SignalItem};
+use crate::signals::SigType;
+use crate::timescale::Timescale;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use vcd::{Command, IdCode, Value};
+
+const BLOCK_HEADER: u8 = 0;
+const BLOCK_VCDATA: u8 = 1;
+const BLOCK_BLACKOUT: u8 = 2;
+const BLOCK_GEOMETRY: u8 = 3;
+const BLOCK_HIERARCHY: u8 = 4;
+const BLOCK_VCDATA_DYN_ALIAS: u8 = 5;
+const BLOCK_HIERARCHY_LZ4: u8 = 6;
+const BLOCK_HIERARCHY_LZ4DUO: u8 = 7;
+const BLOCK_VCDATA_DYN_ALIAS2: u8 = 8;
+const BLOCK_ZWRAPPER: u8 = 254;
+const BLOCK_SKIP: u8 = 255;
+
+const HIER_SCOPE: u8 = 254;
+const HIER_UPSCOPE: u8 = 255;
+const HIER_ATTRBEGIN: u8 = 252;
+const HIER_ATTREND: u8 = 253;
+const VAR_TYPE_REAL: u8 = 3;
+const VAR_TYPE_REAL_PARAMETER: u8 = 4;
+const VAR_TYPE_PORT: u8 = 18;
+const VAR_TYPE_REALTIME: u8 = 20;
+const VAR_TYPE_GEN_STRING: u8 = 21;
+const VAR_TYPE_SV_SHORTREAL: u8 = 29;
+const VAR_TYPE_MAX: u8 = 29;
+const ATTR_MISC: u8 = 0;
+const MISC_SOURCESTEM: u8 = 4;
+const MISC_SOURCEISTEM: u8 = 5;
+
+/// Characters used by FST to encode the non 0/1 states of single bit signals
+const ONE_BIT_STATES: &[u8; 8] = b"xzhuwl-?";
+
+fn truncated() -> Waverr {
+    Waverr::FstErr("FST file ends in the middle of a block")
+}
+
+/// Cursor over a byte slice that knows how to read the primitives FST is made out of
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        ByteReader { data, pos }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Waverr> {
+        let end = self.pos.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Waverr> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, Waverr> {
+        let mut raw = [0; 8];
+        raw.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_be_bytes(raw))
+    }
+
+    fn varint(&mut self) -> Result<u64, Waverr> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Waverr::FstErr("Malformed varint"))
+    }
+
+    fn signed_varint(&mut self) -> Result<i64, Waverr> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                if shift + 7 < 64 && byte & 0x40 != 0 {
+                    value |= u64::MAX << (shift + 7);
+                }
+                return Ok(value as i64);
+            }
+        }
+        Err(Waverr::FstErr("Malformed signed varint"))
+    }
+
+    fn c_str(&mut self) -> Result<String, Waverr> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest.iter().position(|b| *b == 0).ok_or_else(truncated)?;
+        let string = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.pos += len + 1;
+        Ok(string)
+    }
+}
+
+fn decompress_zlib(data: &[u8], len: usize) -> Result<Vec<u8>, Waverr> {
+    let mut out = Vec::with_capacity(len);
+    ZlibDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|_| Waverr::FstErr("Corrupt zlib data"))?;
+    Ok(out)
+}
+
+/// Blocks that are stored uncompressed have equal compressed and uncompressed lengths
+fn maybe_zlib(data: &[u8], len: usize) -> Result<Vec<u8>, Waverr> {
+    if data.len() == len {
+        Ok(data.to_vec())
+    } else {
+        decompress_zlib(data, len)
+    }
+}
+
+fn decompress_gzip(data: &[u8], len: usize) -> Result<Vec<u8>, Waverr> {
+    let mut out = Vec::with_capacity(len);
+    GzDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|_| Waverr::FstErr("Corrupt gzip data"))?;
+    Ok(out)
+}
+
+fn decompress_lz4(data: &[u8], len: usize) -> Result<Vec<u8>, Waverr> {
+    lz4_flex::block::decompress(data, len).map_err(|_| Waverr::FstErr("Corrupt lz4 data"))
+}
+
+/// FastLZ decompression (levels 1 and 2), used by `fstapi` for value change data
+fn decompress_fastlz(data: &[u8], len: usize) -> Result<Vec<u8>, Waverr> {
+    let corrupt = || Waverr::FstErr("Corrupt fastlz data");
+    let mut out: Vec<u8> = Vec::with_capacity(len);
+    let mut input = ByteReader::new(data, 0);
+    let level = data.first().map(|header| (header >> 5) + 1).unwrap_or(1);
+    let mut opcode = input.u8()? & 0x1f;
+    loop {
+        if opcode < 32 {
+            out.extend_from_slice(input.bytes(opcode as usize + 1)?);
+        } else {
+            let mut length = (opcode >> 5) as usize + 2;
+            let mut distance = ((opcode & 0x1f) as usize) << 8;
+            if length == 9 {
+                loop {
+                    let extra = input.u8()?;
+                    length += extra as usize;
+                    if level == 1 || extra != 255 {
+                        break;
+                    }
+                }
+            }
+            let code = input.u8()?;
+            distance += code as usize;
+            if level == 2 && code == 255 && opcode & 0x1f == 31 {
+                distance = (((input.u8()? as usize) << 8) | input.u8()? as usize) + 8191;
+            }
+            let start = out.len().checked_sub(distance + 1).ok_or_else(corrupt)?;
+            for idx in start..start + length {
+                out.push(out[idx]);
+            }
+        }
+        if input.is_empty() {
+            break;
+        }
+        opcode = input.u8()?;
+    }
+    Ok(out)
+}
+
+/// Width of a signal's values in the value change data
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Geometry {
+    Bits(usize),
+    Real,
+    VarLen,
+}
+
+impl Geometry {
+    fn from_raw(raw: u64) -> Geometry {
+        match raw {
+            0 => Geometry::Real,
+            0xFFFF_FFFF => Geometry::VarLen,
+            len => Geometry::Bits(len as usize),
+        }
+    }
+}
+
+/// Where a signal's changes for one block live, relative to the start of the value change data
+#[derive(Debug, Clone, Copy)]
+enum SignalLoc {
+    None,
+    Alias(usize),
+    Offset(usize, usize),
+}
+
+fn fst_value(state: u8) -> Value {
+    match state {
+        b'0' | b'l' | b'L' => Value::V0,
+        b'1' | b'h' | b'H' => Value::V1,
+        b'z' | b'Z' => Value::Z,
+        _ => Value::X,
+    }
+}
+
+/// Strip the ` [msb:lsb]` suffix that `fstapi` appends to vector names
+fn strip_range(name: String) -> String {
+    match name.rfind(" [") {
+        Some(idx) if name.ends_with(']') => name[..idx].to_string(),
+        _ => name,
+    }
+}
+
+pub struct FstParser {
+    data: Vec<u8>,
+    timescale_exponent: i8,
+    little_endian_reals: bool,
+    geometry: Vec<Geometry>,
+    hierarchy: Option<Vec<u8>>,
+    /// (file offset of the section, block type) of every value change block
+    vc_blocks: Vec<(usize, u8)>,
+    next_block: usize,
+    pending: std::vec::IntoIter<Command>,
+}
+
+impl FstParser {
+    pub fn new(file_path: PathBuf) -> Result<FstParser, Waverr> {
+        let data = fs::read(&file_path).map_err(|_| Waverr::FstErr("Could not open FST!"))?;
+        FstParser::from_bytes(data)
+    }
+
+    fn from_bytes(data: Vec<u8>) -> Result<FstParser, Waverr> {
+        let data = if data.first() == Some(&BLOCK_ZWRAPPER) {
+            let mut reader = ByteReader::new(&data, 1);
+            let section_length = reader.u64()? as usize;
+            let uncompressed_length = reader.u64()? as usize;
+            let end = (section_length + 1).min(data.len());
+            decompress_gzip(&data[reader.pos..end], uncompressed_length)?
+        } else {
+            data
+        };
+
+        let mut parser = FstParser {
+            data: Vec::new(),
+            timescale_exponent: -9,
+            little_endian_reals: true,
+            geometry: Vec::new(),
+            hierarchy: None,
+            vc_blocks: Vec::new(),
+            next_block: 0,
+            pending: Vec::new().into_iter(),
+        };
+        let mut found_header = false;
+        let mut reader = ByteReader::new(&data, 0);
+        while !reader.is_empty() {
+            let block_type = reader.u8()?;
+            let section_start = reader.pos;
+            let section_length = reader.u64()? as usize;
+            if block_type == BLOCK_SKIP && section_length == 0 {
+                break;
+            }
+            if section_length < 8 {
+                return Err(Waverr::FstErr("Malformed FST section length"));
+            }
+            let section = reader.bytes(section_length - 8)?;
+            match block_type {
+                BLOCK_HEADER => {
+                    parser.parse_header(section)?;
+                    found_header = true;
+                }
+                BLOCK_GEOMETRY => parser.parse_geometry(section)?,
+                BLOCK_HIERARCHY | BLOCK_HIERARCHY_LZ4 | BLOCK_HIERARCHY_LZ4DUO => {
+                    parser.hierarchy = Some(FstParser::hierarchy_bytes(block_type, section)?);
+                }
+                BLOCK_VCDATA | BLOCK_VCDATA_DYN_ALIAS | BLOCK_VCDATA_DYN_ALIAS2 => {
+                    parser.vc_blocks.push((section_start, block_type))
+                }
+                BLOCK_BLACKOUT | BLOCK_SKIP => {}
+                _ => return Err(Waverr::FstErr("Unknown FST block type")),
+            }
+        }
+        if !found_header {
+            return Err(Waverr::FstErr("FST header not found"));
+        }
+        parser.data = data;
+        Ok(parser)
+    }
+
+    fn parse_header(&mut self, section: &[u8]) -> Result<(), Waverr> {
+        let mut reader = ByteReader::new(section, 0);
+        let _start_time = reader.u64()?;
+        let _end_time = reader.u64()?;
+        let endian_test = reader.bytes(8)?;
+        self.little_endian_reals = endian_test == std::f64::consts::E.to_le_bytes();
+        // memory used by the writer, scope count, var count, max handle, block count
+        reader.bytes(5 * 8)?;
+        self.timescale_exponent = reader.u8()? as i8;
+        Ok(())
+    }
+
+    fn parse_geometry(&mut self, section: &[u8]) -> Result<(), Waverr> {
+        let mut reader = ByteReader::new(section, 0);
+        let uncompressed_length = reader.u64()? as usize;
+        let max_handle = reader.u64()?;
+        let bytes = maybe_zlib(&section[reader.pos..], uncompressed_length)?;
+        let mut reader = ByteReader::new(&bytes, 0);
+        self.geometry = (0..max_handle)
+            .map(|_| reader.varint().map(Geometry::from_raw))
+            .collect::<Result<Vec<Geometry>, Waverr>>()?;
+        Ok(())
+    }
+
+    fn hierarchy_bytes(block_type: u8, section: &[u8]) -> Result<Vec<u8>, Waverr> {
+        let mut reader = ByteReader::new(section, 0);
+        let uncompressed_length = reader.u64()? as usize;
+        match block_type {
+            BLOCK_HIERARCHY => decompress_gzip(&section[reader.pos..], uncompressed_length),
+            BLOCK_HIERARCHY_LZ4 => decompress_lz4(&section[reader.pos..], uncompressed_length),
+            _ => {
+                let first_pass_length = reader.varint()? as usize;
+                let first_pass = decompress_lz4(&section[reader.pos..], first_pass_length)?;
+                decompress_lz4(&first_pass, uncompressed_length)
+            }
+        }
+    }
+
+    /// Timescale declared by the FST header, which stores it as a power of ten
+    pub fn timescale(&self) -> Timescale {
+        Timescale::from_exponent(self.timescale_exponent)
+    }
+
+    pub fn create_hiermap(&mut self) -> Result<HierMap, Waverr> {
+        let bytes = self
+            .hierarchy
+            .take()
+            .ok_or(Waverr::FstErr("Hierarchy is not found in FST!"))?;
+        let mut hier_map = HierMap::default();
+        let mut scopes: Vec<usize> = Vec::new();
+        let mut handle_count = 0;
+        let mut reader = ByteReader::new(&bytes, 0);
+        while !reader.is_empty() {
+            match reader.u8()? {
+                HIER_SCOPE => {
+                    let _scope_type = reader.u8()?;
+                    let name = reader.c_str()?;
+                    let _component = reader.c_str()?;
+                    scopes.push(hier_map.add_module(name, scopes.last().cloned()));
+                }
+                HIER_UPSCOPE => {
+                    scopes.pop();
+                }
+                HIER_ATTRBEGIN => {
+                    let attr_type = reader.u8()?;
+                    let subtype = reader.u8()?;
+                    if attr_type == ATTR_MISC
+                        && (subtype == MISC_SOURCESTEM || subtype == MISC_SOURCEISTEM)
+                    {
+                        reader.varint()?;
+                        reader.u8()?;
+                    } else {
+                        reader.c_str()?;
+                    }
+                    reader.varint()?;
+                }
+                HIER_ATTREND => {}
+                var_type if var_type <= VAR_TYPE_MAX => {
+                    let _direction = reader.u8()?;
+                    let name = strip_range(reader.c_str()?);
+                    let raw_length = reader.varint()? as u32;
+                    let width = if var_type == VAR_TYPE_PORT {
+                        raw_length.saturating_sub(2) / 3
+                    } else {
+                        raw_length
+                    };
+                    let alias = reader.varint()? as u32;
+                    let handle = if alias == 0 {
+                        handle_count += 1;
+                        handle_count
+                    } else {
+                        alias
+                    };
+                    let module = *scopes
+                        .last()
+                        .ok_or(Waverr::FstErr("Scopeless variables are forbidden"))?;
+                    let item = SignalItem::new(name, handle - 1, width);
+                    let item = match var_type {
+                        VAR_TYPE_REAL
+                        | VAR_TYPE_REAL_PARAMETER
+                        | VAR_TYPE_REALTIME
+                        | VAR_TYPE_SV_SHORTREAL => item.with_sig_type(SigType::Float),
+                        VAR_TYPE_GEN_STRING => item.with_sig_type(SigType::Str(width as usize)),
+                        _ => item,
+                    };
+                    hier_map.add_signal(module, item);
+                }
+                _ => return Err(Waverr::FstErr("Unknown FST hierarchy entry")),
+            }
+        }
+        Ok(hier_map)
+    }
+
+    fn signal_locs(
+        chain: &[u8],
+        block_type: u8,
+        max_handle: usize,
+        chain_end: usize,
+    ) -> Result<Vec<SignalLoc>, Waverr> {
+        let mut locs = vec![SignalLoc::None; max_handle];
+        let mut reader = ByteReader::new(chain, 0);
+        let mut idx = 0;
+        let mut offset = 0;
+        let mut prev_offset_idx: Option<usize> = None;
+        let mut prev_alias = 0;
+        let mut new_offset = |locs: &mut Vec<SignalLoc>, idx: usize, delta: usize| {
+            if let Some(prev) = prev_offset_idx {
+                locs[prev] = SignalLoc::Offset(offset, delta);
+            }
+            offset += delta;
+            prev_offset_idx = Some(idx);
+        };
+
+        while !reader.is_empty() {
+            if idx >= max_handle {
+                return Err(Waverr::FstErr(
+                    "Value change chain is longer than handle count",
+                ));
+            }
+            if block_type == BLOCK_VCDATA_DYN_ALIAS2 {
+                if chain[reader.pos] & 1 == 1 {
+                    let shifted = reader.signed_varint()? >> 1;
+                    if shifted > 0 {
+                        new_offset(&mut locs, idx, shifted as usize);
+                    } else {
+                        if shifted < 0 {
+                            prev_alias = (-shifted - 1) as usize;
+                        }
+                        locs[idx] = SignalLoc::Alias(prev_alias);
+                    }
+                    idx += 1;
+                } else {
+                    idx += (reader.varint()? >> 1) as usize;
+                }
+            } else {
+                let raw = reader.varint()?;
+                if raw == 0 {
+                    let alias = reader.varint()? as usize;
+                    locs[idx] = SignalLoc::Alias(alias.saturating_sub(1));
+                    idx += 1;
+                } else if raw & 1 == 1 {
+                    new_offset(&mut locs, idx, (raw >> 1) as usize);
+                    idx += 1;
+                } else {
+                    idx += (raw >> 1) as usize;
+                }
+            }
+        }
+        // the last signal with data runs up until the chain itself
+        if let Some(prev) = prev_offset_idx {
+            let len = chain_end
+                .checked_sub(offset)
+                .ok_or(Waverr::FstErr("Malformed value change chain"))?;
+            locs[prev] = SignalLoc::Offset(offset, len);
+        }
+        Ok(locs)
+    }
+
+    /// Build a command from the ascii encoding of a bit vector
+    fn bits_command(id: IdCode, states: &[u8]) -> Command {
+        if states.len() == 1 {
+            Command::ChangeScalar(id, fst_value(states[0]))
+        } else {
+            Command::ChangeVector(id, states.iter().cloned().map(fst_value).collect())
+        }
+    }
+
+    fn real(&self, bytes: &[u8]) -> f64 {
+        let mut raw = [0; 8];
+        raw.copy_from_slice(bytes);
+        if self.little_endian_reals {
+            f64::from_le_bytes(raw)
+        } else {
+            f64::from_be_bytes(raw)
+        }
+    }
+
+    /// Decode every change of a single signal in a block, tagging each with its time index
+    fn decode_signal(
+        &self,
+        handle: usize,
+        data: &[u8],
+        events: &mut Vec<(usize, Command)>,
+    ) -> Result<(), Waverr> {
+        let id = IdCode::from(handle as u64);
+        let geometry = self.geometry[handle];
+        let mut reader = ByteReader::new(data, 0);
+        let mut time_idx = 0;
+        while !reader.is_empty() {
+            let vli = reader.varint()?;
+            let command = match geometry {
+                Geometry::Bits(1) => {
+                    let state = if vli & 1 == 0 {
+                        time_idx += (vli >> 2) as usize;
+                        b'0' | ((vli >> 1) & 1) as u8
+                    } else {
+                        time_idx += (vli >> 4) as usize;
+                        ONE_BIT_STATES[((vli >> 1) & 7) as usize]
+                    };
+                    Command::ChangeScalar(id, fst_value(state))
+                }
+                Geometry::Bits(len) => {
+                    time_idx += (vli >> 1) as usize;
+                    if vli & 1 == 0 {
+                        let packed = reader.bytes((len + 7) / 8)?;
+                        let states: Vec<u8> = (0..len)
+                            .map(|bit| b'0' | ((packed[bit / 8] >> (7 - bit % 8)) & 1))
+                            .collect();
+                        FstParser::bits_command(id, &states)
+                    } else {
+                        FstParser::bits_command(id, reader.bytes(len)?)
+                    }
+                }
+                Geometry::Real => {
+                    time_idx += (vli >> 1) as usize;
+                    if vli & 1 == 0 {
+                        return Err(Waverr::FstErr("Packed real values are not supported"));
+                    }
+                    Command::ChangeReal(id, self.real(reader.bytes(8)?))
+                }
+                Geometry::VarLen => {
+                    time_idx += (vli >> 1) as usize;
+                    let len = reader.varint()? as usize;
+                    let string = String::from_utf8_lossy(reader.bytes(len)?).into_owned();
+                    Command::ChangeString(id, string)
+                }
+            };
+            events.push((time_idx, command));
+        }
+        Ok(())
+    }
+
+    /// Decode one value change block into a time ordered list of commands
+    fn read_block(
+        &self,
+        section_start: usize,
+        block_type: u8,
+        first: bool,
+    ) -> Result<Vec<Command>, Waverr> {
+        let mut reader = ByteReader::new(&self.data, section_start);
+        let section_length = reader.u64()? as usize;
+        let section_end = section_start + section_length;
+        let start_time = reader.u64()?;
+        let _end_time = reader.u64()?;
+        let _mem_required = reader.u64()?;
+
+        // time table lives at the very end of the block
+        let mut time_reader = ByteReader::new(&self.data, section_end - 24);
+        let time_uncompressed = time_reader.u64()? as usize;
+        let time_compressed = time_reader.u64()? as usize;
+        let time_count = time_reader.u64()? as usize;
+        let time_start = (section_end - 24)
+            .checked_sub(time_compressed)
+            .ok_or_else(truncated)?;
+        let time_bytes = maybe_zlib(&self.data[time_start..section_end - 24], time_uncompressed)?;
+        let mut time_reader = ByteReader::new(&time_bytes, 0);
+        let mut time = 0;
+        let mut time_table = Vec::with_capacity(time_count);
+        for _ in 0..time_count {
+            time += time_reader.varint()?;
+            time_table.push(time);
+        }
+
+        let mut commands = Vec::new();
+        let frame_uncompressed = reader.varint()? as usize;
+        let frame_compressed = reader.varint()? as usize;
+        let frame_max_handle = reader.varint()? as usize;
+        let frame = reader.bytes(frame_compressed)?;
+        // the frame holds the value of every signal at the start of the dump; later blocks
+        // repeat it, but those values are already known
+        if first && !matches!(time_table.first(), Some(first) if *first <= start_time) {
+            let frame = maybe_zlib(frame, frame_uncompressed)?;
+            let mut frame_reader = ByteReader::new(&frame, 0);
+            commands.push(Command::Timestamp(start_time));
+            for (handle, geometry) in self.geometry.iter().enumerate().take(frame_max_handle) {
+                let id = IdCode::from(handle as u64);
+                match geometry {
+                    Geometry::Bits(len) => {
+                        commands.push(FstParser::bits_command(id, frame_reader.bytes(*len)?))
+                    }
+                    Geometry::Real => {
+                        commands.push(Command::ChangeReal(id, self.real(frame_reader.bytes(8)?)))
+                    }
+                    Geometry::VarLen => {}
+                }
+            }
+        }
+
+        let max_handle = (reader.varint()? as usize).min(self.geometry.len());
+        let vc_start = reader.pos;
+        let pack_type = reader.u8()?;
+        let chain_len_offset = time_start.checked_sub(8).ok_or_else(truncated)?;
+        let chain_len = ByteReader::new(&self.data, chain_len_offset).u64()? as usize;
+        let chain_start = chain_len_offset
+            .checked_sub(chain_len)
+            .ok_or_else(truncated)?;
+        let locs = FstParser::signal_locs(
+            &self.data[chain_start..chain_len_offset],
+            block_type,
+            max_handle,
+            chain_start - vc_start,
+        )?;
+
+        let mut events: Vec<(usize, Command)> = Vec::new();
+        for handle in 0..max_handle {
+            let (offset, len) = match locs[handle] {
+                SignalLoc::None => continue,
+                SignalLoc::Offset(offset, len) => (offset, len),
+                SignalLoc::Alias(alias) => match locs.get(alias) {
+                    Some(SignalLoc::Offset(offset, len)) => (*offset, *len),
+                    _ => return Err(Waverr::FstErr("Alias does not point at signal data")),
+                },
+            };
+            let mut signal_reader = ByteReader::new(&self.data, vc_start + offset);
+            let uncompressed_length = signal_reader.varint()? as usize;
+            let packed = signal_reader.bytes(len - (signal_reader.pos - vc_start - offset))?;
+            if uncompressed_length == 0 {
+                self.decode_signal(handle, packed, &mut events)?;
+            } else {
+                let unpacked = match pack_type {
+                    b'4' => decompress_lz4(packed, uncompressed_length)?,
+                    b'F' => decompress_fastlz(packed, uncompressed_length)?,
+                    _ => decompress_zlib(packed, uncompressed_length)?,
+                };
+                self.decode_signal(handle, &unpacked, &mut events)?;
+            }
+        }
+
+        events.sort_by_key(|(time_idx, _)| *time_idx);
+        let mut current_idx = None;
+        for (time_idx, command) in events.into_iter() {
+            if current_idx != Some(time_idx) {
+                let time = time_table.get(time_idx).ok_or(Waverr::FstErr(
+                    "Value change past the end of the time table",
+                ))?;
+                commands.push(Command::Timestamp(*time));
+                current_idx = Some(time_idx);
+            }
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+}
+
+impl Iterator for FstParser {
+    type Item = Result<Command, Waverr>;
+    fn next(&mut self) -> Option<Result<Command, Waverr>> {
+        loop {
+            if let Some(command) = self.pending.next() {
+                return Some(Ok(command));
+            }
+            let (section_start, block_type) = *self.vc_blocks.get(self.next_block)?;
+            let first = self.next_block == 0;
+            self.next_block += 1;
+            match self.read_block(section_start, block_type, first) {
+                Ok(commands) => self.pending = commands.into_iter(),
+                Err(err) => {
+                    self.next_block = self.vc_blocks.len();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use crate::timescale::TimeUnit;
+
+    fn fst_test_path(path: &str) -> PathBuf {
+        let mut path_to_fst = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_fst.push(path);
+        path_to_fst
+    }
+
+    #[test]
+    fn fastlz_level1() {
+        // three literals, then a three byte match pointing back at the start
+        let compressed = [0x02, b'a', b'b', b'c', 0x20, 0x02];
+        assert_eq!(
+            decompress_fastlz(&compressed, 6).unwrap(),
+            b"abcabc".to_vec()
+        );
+    }
+
+    #[test]
+    fn vga_fst_hier_map() {
+        let mut parser = FstParser::new(fst_test_path("test_vcds/vga.fst")).unwrap();
+        assert_eq!(
+            parser.timescale(),
+            Timescale {
+                magnitude: 1,
+                unit: TimeUnit::Ns
+            }
+        );
+        let hm = parser.create_hiermap().unwrap();
+        let live_module = hm.set_path_abs("TOP.vga").unwrap();
+        let signals = hm.get_module_signals_vec(live_module);
+        assert_eq!(signals.len(), 30);
+        let x_cnt = hm.path_to_signalref("TOP.vga.x_cnt").unwrap();
+        assert_eq!(x_cnt.width(), 10);
+        // aliased handles share a signal id
+        let top_clock = hm.path_to_signalref("TOP.clock").unwrap();
+        let vga_clock = hm.path_to_signalref("TOP.vga.clock").unwrap();
+        assert_eq!(top_clock.id(), vga_clock.id());
+    }
+}
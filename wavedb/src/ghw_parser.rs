@@ -0,0 +1,1085 @@
+/*! Reader for GHW dumps, the native waveform format of GHDL (`--wave=file.ghw`).
+
+Unlike VCD, GHW keeps the VHDL type of every signal around. [`GhwParser`] uses that to turn
+records and arrays into [`HierMap`] modules, and to remember the literal names of enumerated types
+so that the wave window can show `idle`/`busy` rather than the position of the literal.
+
+GHDL gives every scalar element of a signal its own id; each declared signal (or record field, or
+array element) becomes a single wavedb signal. `std_logic_vector`s and friends are reassembled
+into vectors, and the result is yielded as [`vcd::Command`]s just like the other parsers.
+!*/
+use crate::errors::Waverr;
+use crate::hier_map::{HierMap, SignalItem};
+use crate::signals::SigType;
+use crate::timescale::{TimeUnit, Timescale};
+use flate2::read::GzDecoder;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use vcd::{Command, IdCode, Value};
+
+const GHW_MAGIC: &[u8; 9] = b"GHDLwave\n";
+const GHW_HEADER_LEN: usize = 16;
+const GZIP_MAGIC: &[u8; 2] = b"\x1f\x8b";
+const BZIP2_MAGIC: &[u8; 2] = b"BZ";
+
+const SECTION_STRINGS: &[u8; 4] = b"STR\0";
+const SECTION_HIERARCHY: &[u8; 4] = b"HIE\0";
+const SECTION_TYPES: &[u8; 4] = b"TYP\0";
+const SECTION_WELL_KNOWN_TYPES: &[u8; 4] = b"WKT\0";
+const SECTION_END_OF_HEADER: &[u8; 4] = b"EOH\0";
+const SECTION_SNAPSHOT: &[u8; 4] = b"SNP\0";
+const SECTION_CYCLE: &[u8; 4] = b"CYC\0";
+const SECTION_DIRECTORY: &[u8; 4] = b"DIR\0";
+const SECTION_TAILER: &[u8; 4] = b"TAI\0";
+const END_SNAPSHOT: &[u8; 4] = b"ESN\0";
+const END_CYCLE: &[u8; 4] = b"ECY\0";
+const END_DIRECTORY: &[u8; 4] = b"EOD\0";
+
+// type kinds, as numbered by GHDL's runtime type information
+const RTIK_B2: u8 = 22;
+const RTIK_E8: u8 = 23;
+const RTIK_I32: u8 = 25;
+const RTIK_I64: u8 = 26;
+const RTIK_F64: u8 = 27;
+const RTIK_P32: u8 = 28;
+const RTIK_P64: u8 = 29;
+const RTIK_ARRAY: u8 = 31;
+const RTIK_RECORD: u8 = 32;
+const RTIK_SUBTYPE_SCALAR: u8 = 34;
+const RTIK_SUBTYPE_ARRAY: u8 = 35;
+const RTIK_SUBTYPE_UNBOUNDED_ARRAY: u8 = 37;
+const RTIK_SUBTYPE_RECORD: u8 = 38;
+
+const HIE_END: u8 = 0;
+const HIE_BLOCK: u8 = 3;
+const HIE_GENERATE_IF: u8 = 4;
+const HIE_GENERATE_FOR: u8 = 5;
+const HIE_INSTANCE: u8 = 6;
+const HIE_PACKAGE: u8 = 7;
+const HIE_PROCESS: u8 = 13;
+const HIE_GENERIC: u8 = 14;
+const HIE_END_OF_SCOPE: u8 = 15;
+const HIE_SIGNAL: u8 = 16;
+const HIE_PORT_LINKAGE: u8 = 21;
+
+/// Literals of `std_ulogic`, in declaration order
+const STD_LOGIC_LITERALS: &[u8; 9] = b"UX01ZWLH-";
+const BIT_LITERALS: &[u8; 2] = b"01";
+
+fn truncated() -> Waverr {
+    Waverr::GhwErr("GHW file ends in the middle of a section")
+}
+
+/// Cursor over a byte slice that knows how to read the primitives GHW is made out of
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8], pos: usize, big_endian: bool) -> Self {
+        ByteReader {
+            data,
+            pos,
+            big_endian,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Waverr> {
+        let end = self.pos.checked_add(len).ok_or_else(truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Waverr> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, Waverr> {
+        let mut raw = [0; 4];
+        raw.copy_from_slice(self.bytes(4)?);
+        Ok(if self.big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        })
+    }
+
+    fn i64(&mut self) -> Result<i64, Waverr> {
+        let mut raw = [0; 8];
+        raw.copy_from_slice(self.bytes(8)?);
+        Ok(if self.big_endian {
+            i64::from_be_bytes(raw)
+        } else {
+            i64::from_le_bytes(raw)
+        })
+    }
+
+    fn f64(&mut self) -> Result<f64, Waverr> {
+        Ok(f64::from_bits(self.i64()? as u64))
+    }
+
+    fn uleb(&mut self) -> Result<u64, Waverr> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Waverr::GhwErr("Malformed unsigned leb128"))
+    }
+
+    fn sleb(&mut self) -> Result<i64, Waverr> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                if shift + 7 < 64 && byte & 0x40 != 0 {
+                    value |= u64::MAX << (shift + 7);
+                }
+                return Ok(value as i64);
+            }
+        }
+        Err(Waverr::GhwErr("Malformed signed leb128"))
+    }
+
+    /// Most section headers are padded with four zero bytes
+    fn zeros(&mut self) -> Result<(), Waverr> {
+        if self.bytes(4)? != [0; 4] {
+            return Err(Waverr::GhwErr("Expected zero padding in section header"));
+        }
+        Ok(())
+    }
+
+    fn mark(&mut self, expected: &[u8; 4]) -> Result<(), Waverr> {
+        if self.bytes(4)? != expected {
+            return Err(Waverr::GhwErr("Section is missing its end marker"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Bounds of an array, in the order they were declared
+struct IndexRange {
+    left: i64,
+    right: i64,
+    downto: bool,
+}
+
+impl IndexRange {
+    fn len(&self) -> usize {
+        let span = if self.downto {
+            self.left - self.right
+        } else {
+            self.right - self.left
+        };
+        (span + 1).max(0) as usize
+    }
+
+    /// Index values from left to right
+    fn indices(&self) -> impl Iterator<Item = i64> {
+        let (left, downto) = (self.left, self.downto);
+        (0..self.len() as i64).map(move |offset| if downto { left - offset } else { left + offset })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The parts of a VHDL type that matter for laying out and decoding signals
+enum GhwType {
+    /// std_logic or std_ulogic
+    Logic,
+    Bit,
+    Enum(Vec<String>),
+    Int32,
+    Int64,
+    Real,
+    /// Arrays without a range are unbounded, and must be constrained before a signal can use them
+    Array {
+        element: Box<GhwType>,
+        range: Option<IndexRange>,
+    },
+    Record(Vec<(String, GhwType)>),
+}
+
+impl GhwType {
+    fn from_literals(literals: Vec<String>) -> GhwType {
+        fn matches(literals: &[String], expected: &[u8]) -> bool {
+            literals.len() == expected.len()
+                && literals.iter().zip(expected).all(|(literal, expected)| {
+                    // character literals are stored with their ticks, i.e. '0'
+                    let literal = literal.as_bytes();
+                    match literal.len() {
+                        1 => literal[0].eq_ignore_ascii_case(expected),
+                        3 => literal[1].eq_ignore_ascii_case(expected),
+                        _ => false,
+                    }
+                })
+        }
+
+        if matches(&literals, STD_LOGIC_LITERALS) {
+            GhwType::Logic
+        } else if matches(&literals, BIT_LITERALS) {
+            GhwType::Bit
+        } else {
+            GhwType::Enum(literals)
+        }
+    }
+
+    fn is_bounded(&self) -> bool {
+        match self {
+            GhwType::Array { element, range } => range.is_some() && element.is_bounded(),
+            GhwType::Record(fields) => fields.iter().all(|(_, field)| field.is_bounded()),
+            _ => true,
+        }
+    }
+
+    fn scalar(&self) -> Option<Scalar> {
+        match self {
+            GhwType::Logic => Some(Scalar::Logic),
+            GhwType::Bit => Some(Scalar::Bit),
+            GhwType::Enum(_) => Some(Scalar::Enum),
+            GhwType::Int32 | GhwType::Int64 => Some(Scalar::Int),
+            GhwType::Real => Some(Scalar::Real),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// How the value of a single GHW signal is encoded in the body of the dump
+enum Scalar {
+    Logic,
+    Bit,
+    Enum,
+    Int,
+    Real,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// How a wavedb signal is assembled out of its GHW signals
+enum Encoding {
+    /// Single std_logic or bit
+    Bit,
+    /// Array of std_logic or bits, leftmost element first
+    Vector,
+    /// Enums and integers; the value is stored as an unsigned vector of the given width
+    Integer(u32),
+    Real,
+}
+
+impl Encoding {
+    fn width(&self, num_signals: usize) -> u32 {
+        match self {
+            Encoding::Bit => 1,
+            Encoding::Vector => num_signals as u32,
+            Encoding::Integer(width) => *width,
+            Encoding::Real => 64,
+        }
+    }
+}
+
+/// Number of bits needed to store the position of any of `num_literals` literals
+fn enum_width(num_literals: usize) -> u32 {
+    usize::BITS - (num_literals.max(2) - 1).leading_zeros()
+}
+
+struct GhwVar {
+    encoding: Encoding,
+    signals: Vec<usize>,
+}
+
+#[derive(Default)]
+/// Current value of every GHW signal, and the wavedb signals that they make up
+struct SignalTable {
+    kinds: Vec<Option<Scalar>>,
+    values: Vec<u64>,
+    /// wavedb ids of the signals that each GHW signal is a part of
+    owners: Vec<Vec<u32>>,
+    vars: Vec<GhwVar>,
+    /// Ports are bound to the same GHW signals as the signal driving them; those share a wavedb id
+    var_ids: HashMap<Vec<usize>, u32>,
+    dirty: Vec<bool>,
+    dirty_list: Vec<u32>,
+}
+
+impl SignalTable {
+    fn new(max_signal_id: usize) -> SignalTable {
+        SignalTable {
+            kinds: vec![None; max_signal_id],
+            values: vec![0; max_signal_id],
+            owners: vec![Vec::new(); max_signal_id],
+            ..SignalTable::default()
+        }
+    }
+
+    fn read_signal_id(&mut self, reader: &mut ByteReader, kind: Scalar) -> Result<usize, Waverr> {
+        let idx = (reader.uleb()? as usize)
+            .checked_sub(1)
+            .filter(|idx| *idx < self.kinds.len())
+            .ok_or(Waverr::GhwErr("Signal id is out of range"))?;
+        match self.kinds[idx] {
+            Some(prev) if prev != kind => Err(Waverr::GhwErr(
+                "Signal id is shared by signals of different types",
+            )),
+            _ => {
+                self.kinds[idx] = Some(kind);
+                Ok(idx)
+            }
+        }
+    }
+
+    /// Read the signal ids of a declaration of type `ty`, adding a signal (or module, for
+    /// composite types) named `name` underneath `module`
+    fn add_var(
+        &mut self,
+        reader: &mut ByteReader,
+        hier_map: &mut HierMap,
+        module: usize,
+        name: String,
+        ty: &GhwType,
+    ) -> Result<(), Waverr> {
+        match ty {
+            GhwType::Array {
+                element,
+                range: Some(range),
+            } => match element.scalar() {
+                Some(kind @ Scalar::Logic) | Some(kind @ Scalar::Bit) => {
+                    let signals = (0..range.len())
+                        .map(|_| self.read_signal_id(reader, kind))
+                        .collect::<Result<Vec<usize>, Waverr>>()?;
+                    if !signals.is_empty() {
+                        self.add_signal(hier_map, module, name, signals, Encoding::Vector, None);
+                    }
+                    Ok(())
+                }
+                _ => {
+                    let array = hier_map.add_module(name, Some(module));
+                    for index in range.indices() {
+                        self.add_var(reader, hier_map, array, format!("[{}]", index), element)?;
+                    }
+                    Ok(())
+                }
+            },
+            GhwType::Array { range: None, .. } => {
+                Err(Waverr::GhwErr("Signal has an unbounded array type"))
+            }
+            GhwType::Record(fields) => {
+                let record = hier_map.add_module(name, Some(module));
+                for (field_name, field) in fields.iter() {
+                    self.add_var(reader, hier_map, record, field_name.clone(), field)?;
+                }
+                Ok(())
+            }
+            scalar => {
+                let (encoding, literals) = match scalar {
+                    GhwType::Enum(literals) => (
+                        Encoding::Integer(enum_width(literals.len())),
+                        Some(literals.clone()),
+                    ),
+                    GhwType::Int32 => (Encoding::Integer(32), None),
+                    GhwType::Int64 => (Encoding::Integer(64), None),
+                    GhwType::Real => (Encoding::Real, None),
+                    _ => (Encoding::Bit, None),
+                };
+                let kind = scalar.scalar().expect("composite types are handled above");
+                let signal = self.read_signal_id(reader, kind)?;
+                self.add_signal(hier_map, module, name, vec![signal], encoding, literals);
+                Ok(())
+            }
+        }
+    }
+
+    fn add_signal(
+        &mut self,
+        hier_map: &mut HierMap,
+        module: usize,
+        name: String,
+        signals: Vec<usize>,
+        encoding: Encoding,
+        literals: Option<Vec<String>>,
+    ) {
+        let width = encoding.width(signals.len());
+        let sig_type = match encoding {
+            Encoding::Real => SigType::Float,
+            _ => SigType::from_width(width as usize),
+        };
+        let id = match self.var_ids.get(&signals) {
+            Some(id) => *id,
+            None => {
+                let id = self.vars.len() as u32;
+                for signal in signals.iter() {
+                    self.owners[*signal].push(id);
+                }
+                self.var_ids.insert(signals.clone(), id);
+                self.vars.push(GhwVar { encoding, signals });
+                self.dirty.push(false);
+                id
+            }
+        };
+        let item = SignalItem::new(name, id, width).with_sig_type(sig_type);
+        let item = match literals {
+            Some(literals) => item.with_literals(literals),
+            None => item,
+        };
+        hier_map.add_signal(module, item);
+    }
+
+    fn read_value(&mut self, reader: &mut ByteReader, idx: usize) -> Result<(), Waverr> {
+        let kind = self
+            .kinds
+            .get(idx)
+            .cloned()
+            .flatten()
+            .ok_or(Waverr::GhwErr("Value change for an undeclared signal"))?;
+        self.values[idx] = match kind {
+            Scalar::Logic | Scalar::Bit | Scalar::Enum => reader.u8()? as u64,
+            Scalar::Int => reader.sleb()? as u64,
+            Scalar::Real => reader.f64()?.to_bits(),
+        };
+        for owner in self.owners[idx].iter().cloned() {
+            if !self.dirty[owner as usize] {
+                self.dirty[owner as usize] = true;
+                self.dirty_list.push(owner);
+            }
+        }
+        Ok(())
+    }
+
+    fn bit_value(&self, idx: usize) -> Value {
+        match (self.kinds[idx], self.values[idx]) {
+            // std_logic literals, in order: U X 0 1 Z W L H -
+            (Some(Scalar::Logic), 2) | (Some(Scalar::Logic), 6) => Value::V0,
+            (Some(Scalar::Logic), 3) | (Some(Scalar::Logic), 7) => Value::V1,
+            (Some(Scalar::Logic), 4) => Value::Z,
+            (Some(Scalar::Logic), _) => Value::X,
+            (_, 0) => Value::V0,
+            _ => Value::V1,
+        }
+    }
+
+    fn command(&self, id: u32) -> Command {
+        let var = &self.vars[id as usize];
+        let code = IdCode::from(id);
+        match var.encoding {
+            Encoding::Bit => Command::ChangeScalar(code, self.bit_value(var.signals[0])),
+            Encoding::Vector => Command::ChangeVector(
+                code,
+                var.signals.iter().map(|idx| self.bit_value(*idx)).collect(),
+            ),
+            Encoding::Integer(width) => {
+                let value = self.values[var.signals[0]];
+                Command::ChangeVector(
+                    code,
+                    (0..width)
+                        .rev()
+                        .map(|bit| {
+                            if (value >> bit) & 1 == 1 {
+                                Value::V1
+                            } else {
+                                Value::V0
+                            }
+                        })
+                        .collect(),
+                )
+            }
+            Encoding::Real => {
+                Command::ChangeReal(code, f64::from_bits(self.values[var.signals[0]]))
+            }
+        }
+    }
+
+    /// Queue up the signals that changed during the time step at `time`
+    fn finish_step(&mut self, time: u64, pending: &mut VecDeque<Command>) {
+        if self.dirty_list.is_empty() {
+            return;
+        }
+        pending.push_back(Command::Timestamp(time));
+        let mut changed = std::mem::take(&mut self.dirty_list);
+        changed.sort_unstable();
+        for id in changed.drain(..) {
+            self.dirty[id as usize] = false;
+            pending.push_back(self.command(id));
+        }
+        self.dirty_list = changed;
+    }
+}
+
+fn string(strings: &[String], id: u64) -> Result<String, Waverr> {
+    strings
+        .get(id as usize)
+        .cloned()
+        .ok_or(Waverr::GhwErr("Reference to a string that doesn't exist"))
+}
+
+fn type_ref(types: &[GhwType], id: u64) -> Result<&GhwType, Waverr> {
+    (id as usize)
+        .checked_sub(1)
+        .and_then(|idx| types.get(idx))
+        .ok_or(Waverr::GhwErr("Reference to a type that doesn't exist"))
+}
+
+fn read_strings(reader: &mut ByteReader) -> Result<Vec<String>, Waverr> {
+    reader.zeros()?;
+    // the count excludes one of the strings; index 0 is reserved for anonymous names
+    let count = reader.u32()? as usize + 1;
+    let _total_size = reader.u32()?;
+    let mut strings = Vec::with_capacity(count + 1);
+    strings.push(String::from("<anon>"));
+    let mut buf = Vec::new();
+    for _ in 0..count {
+        let mut c = reader.u8()?;
+        while c > 31 && !(128..=159).contains(&c) {
+            buf.push(c);
+            c = reader.u8()?;
+        }
+        strings.push(String::from_utf8_lossy(&buf).into_owned());
+        // the terminator encodes how many leading bytes the next string shares with this one
+        let mut prefix_len = (c & 0x1f) as usize;
+        let mut shift = 5;
+        while c >= 128 {
+            c = reader.u8()?;
+            prefix_len |= ((c & 0x1f) as usize) << shift;
+            shift += 5;
+        }
+        buf.truncate(prefix_len);
+    }
+    Ok(strings)
+}
+
+fn read_range(reader: &mut ByteReader) -> Result<Option<IndexRange>, Waverr> {
+    let kind = reader.u8()?;
+    let downto = kind & 0x80 != 0;
+    let (left, right) = match kind & 0x7f {
+        RTIK_B2 | RTIK_E8 => (reader.u8()? as i64, reader.u8()? as i64),
+        RTIK_I32 | RTIK_P32 | RTIK_I64 | RTIK_P64 => (reader.sleb()?, reader.sleb()?),
+        RTIK_F64 => {
+            reader.f64()?;
+            reader.f64()?;
+            return Ok(None);
+        }
+        _ => return Err(Waverr::GhwErr("Unsupported range type")),
+    };
+    Ok(Some(IndexRange {
+        left,
+        right,
+        downto,
+    }))
+}
+
+/// Constrain the unbounded parts of an array or record type
+fn read_bounds(reader: &mut ByteReader, base: &GhwType) -> Result<GhwType, Waverr> {
+    match base {
+        GhwType::Array { element, .. } => {
+            let range =
+                read_range(reader)?.ok_or(Waverr::GhwErr("Array is indexed by a real range"))?;
+            let element = if element.is_bounded() {
+                element.clone()
+            } else {
+                Box::new(read_bounds(reader, element)?)
+            };
+            Ok(GhwType::Array {
+                element,
+                range: Some(range),
+            })
+        }
+        GhwType::Record(fields) => fields
+            .iter()
+            .map(|(name, field)| {
+                if field.is_bounded() {
+                    Ok((name.clone(), field.clone()))
+                } else {
+                    Ok((name.clone(), read_bounds(reader, field)?))
+                }
+            })
+            .collect::<Result<Vec<_>, Waverr>>()
+            .map(GhwType::Record),
+        _ => Err(Waverr::GhwErr("Bounds given for a scalar type")),
+    }
+}
+
+fn read_types(
+    reader: &mut ByteReader,
+    strings: &[String],
+    version: u8,
+) -> Result<Vec<GhwType>, Waverr> {
+    reader.zeros()?;
+    let count = reader.u32()? as usize;
+    let mut types = Vec::with_capacity(count);
+    for _ in 0..count {
+        let kind = reader.u8()?;
+        let _name = reader.uleb()?;
+        let ty = match kind {
+            RTIK_B2 | RTIK_E8 => {
+                let num_literals = reader.uleb()?;
+                let literals = (0..num_literals)
+                    .map(|_| string(strings, reader.uleb()?))
+                    .collect::<Result<Vec<String>, Waverr>>()?;
+                GhwType::from_literals(literals)
+            }
+            RTIK_I32 => GhwType::Int32,
+            RTIK_I64 => GhwType::Int64,
+            RTIK_F64 => GhwType::Real,
+            RTIK_P64 => {
+                // physical types are dumped as their value in the base unit
+                if version > 0 {
+                    for _ in 0..reader.uleb()? {
+                        reader.uleb()?;
+                        reader.sleb()?;
+                    }
+                }
+                GhwType::Int64
+            }
+            RTIK_SUBTYPE_SCALAR => {
+                // subtypes keep the encoding of their base type
+                let base = type_ref(&types, reader.uleb()?)?.clone();
+                read_range(reader)?;
+                base
+            }
+            RTIK_ARRAY => {
+                let element = type_ref(&types, reader.uleb()?)?.clone();
+                if reader.uleb()? != 1 {
+                    return Err(Waverr::GhwErr("Multi-dimensional arrays are not supported"));
+                }
+                reader.uleb()?;
+                GhwType::Array {
+                    element: Box::new(element),
+                    range: None,
+                }
+            }
+            RTIK_SUBTYPE_ARRAY | RTIK_SUBTYPE_RECORD => {
+                let base = type_ref(&types, reader.uleb()?)?.clone();
+                read_bounds(reader, &base)?
+            }
+            RTIK_SUBTYPE_UNBOUNDED_ARRAY => match type_ref(&types, reader.uleb()?)? {
+                GhwType::Array { element, .. } => GhwType::Array {
+                    element: element.clone(),
+                    range: None,
+                },
+                _ => return Err(Waverr::GhwErr("Unbounded subtype of a non-array type")),
+            },
+            RTIK_RECORD => {
+                let num_fields = reader.uleb()?;
+                let fields = (0..num_fields)
+                    .map(|_| {
+                        let name = string(strings, reader.uleb()?)?;
+                        Ok((name, type_ref(&types, reader.uleb()?)?.clone()))
+                    })
+                    .collect::<Result<Vec<_>, Waverr>>()?;
+                GhwType::Record(fields)
+            }
+            _ => return Err(Waverr::GhwErr("Unsupported VHDL type")),
+        };
+        types.push(ty);
+    }
+    if reader.u8()? != 0 {
+        return Err(Waverr::GhwErr("Type section is not terminated"));
+    }
+    Ok(types)
+}
+
+/// Well known types (boolean, bit, std_ulogic) are recognized from their literals instead
+fn skip_well_known_types(reader: &mut ByteReader) -> Result<(), Waverr> {
+    reader.zeros()?;
+    while reader.u8()? != 0 {
+        reader.uleb()?;
+    }
+    Ok(())
+}
+
+fn read_hierarchy(
+    reader: &mut ByteReader,
+    strings: &[String],
+    types: &[GhwType],
+) -> Result<(HierMap, SignalTable), Waverr> {
+    reader.zeros()?;
+    let _num_scopes = reader.u32()?;
+    let _num_declarations = reader.u32()?;
+    let max_signal_id = reader.u32()? as usize;
+
+    let mut hier_map = HierMap::default();
+    let mut signals = SignalTable::new(max_signal_id);
+    let mut scopes: Vec<usize> = Vec::new();
+    loop {
+        let kind = reader.u8()?;
+        match kind {
+            HIE_END => break,
+            HIE_END_OF_SCOPE => {
+                scopes
+                    .pop()
+                    .ok_or(Waverr::GhwErr("Unbalanced end of scope"))?;
+            }
+            HIE_PROCESS => {
+                reader.uleb()?;
+            }
+            HIE_BLOCK | HIE_GENERATE_IF | HIE_GENERATE_FOR | HIE_INSTANCE | HIE_PACKAGE
+            | HIE_GENERIC => {
+                let mut name = string(strings, reader.uleb()?)?;
+                if kind == HIE_GENERATE_FOR {
+                    let value = match type_ref(types, reader.uleb()?)?.scalar() {
+                        Some(Scalar::Logic) | Some(Scalar::Bit) | Some(Scalar::Enum) => {
+                            reader.u8()?.to_string()
+                        }
+                        Some(Scalar::Int) => reader.sleb()?.to_string(),
+                        Some(Scalar::Real) => reader.f64()?.to_string(),
+                        None => return Err(Waverr::GhwErr("Generate iterator isn't a scalar")),
+                    };
+                    name = format!("{}({})", name, value);
+                }
+                scopes.push(hier_map.add_module(name, scopes.last().cloned()));
+            }
+            HIE_SIGNAL..=HIE_PORT_LINKAGE => {
+                let name = string(strings, reader.uleb()?)?;
+                let ty = type_ref(types, reader.uleb()?)?;
+                let module = *scopes
+                    .last()
+                    .ok_or(Waverr::GhwErr("Signal declared outside of any scope"))?;
+                signals.add_var(reader, &mut hier_map, module, name, ty)?;
+            }
+            _ => return Err(Waverr::GhwErr("Unknown hierarchy entry")),
+        }
+    }
+    Ok((hier_map, signals))
+}
+
+pub struct GhwParser {
+    data: Vec<u8>,
+    big_endian: bool,
+    /// Offset of the next unread section of the body
+    pos: usize,
+    hier_map: HierMap,
+    signals: SignalTable,
+    /// Time of the next step of the cycle section being read, if we're in the middle of one
+    cycle_time: Option<u64>,
+    done: bool,
+    pending: VecDeque<Command>,
+}
+
+impl GhwParser {
+    pub fn new(file_path: PathBuf) -> Result<GhwParser, Waverr> {
+        let data = fs::read(&file_path).map_err(|_| Waverr::GhwErr("Could not open GHW!"))?;
+        GhwParser::from_bytes(data)
+    }
+
+    fn from_bytes(data: Vec<u8>) -> Result<GhwParser, Waverr> {
+        let data = match data.get(..2) {
+            Some(magic) if magic == GZIP_MAGIC => {
+                let mut out = Vec::new();
+                GzDecoder::new(data.as_slice())
+                    .read_to_end(&mut out)
+                    .map_err(|_| Waverr::GhwErr("Corrupt gzip data"))?;
+                out
+            }
+            Some(magic) if magic == BZIP2_MAGIC => {
+                return Err(Waverr::GhwErr(
+                    "bzip2 compressed GHW files are not supported",
+                ))
+            }
+            _ => data,
+        };
+        if data.len() < GHW_HEADER_LEN || &data[..GHW_MAGIC.len()] != GHW_MAGIC {
+            return Err(Waverr::GhwErr("Not a GHW file"));
+        }
+        let header = &data[GHW_MAGIC.len()..GHW_HEADER_LEN];
+        let (version, endianness) = (header[2], header[3]);
+        if header[0] != 16 || header[1] != 0 || version > 1 || !(1..=2).contains(&endianness) {
+            return Err(Waverr::GhwErr("Unsupported GHW header"));
+        }
+        let big_endian = endianness == 2;
+
+        let mut reader = ByteReader::new(&data, GHW_HEADER_LEN, big_endian);
+        let mut strings = Vec::new();
+        let mut types = Vec::new();
+        let mut hierarchy = None;
+        loop {
+            match reader.bytes(4)? {
+                mark if mark == SECTION_STRINGS => strings = read_strings(&mut reader)?,
+                mark if mark == SECTION_TYPES => {
+                    types = read_types(&mut reader, &strings, version)?
+                }
+                mark if mark == SECTION_WELL_KNOWN_TYPES => skip_well_known_types(&mut reader)?,
+                mark if mark == SECTION_HIERARCHY => {
+                    hierarchy = Some(read_hierarchy(&mut reader, &strings, &types)?)
+                }
+                mark if mark == SECTION_END_OF_HEADER => break,
+                _ => return Err(Waverr::GhwErr("Unknown section in GHW header")),
+            }
+        }
+        let (hier_map, signals) =
+            hierarchy.ok_or(Waverr::GhwErr("GHW file has no hierarchy section"))?;
+        let pos = reader.pos;
+        Ok(GhwParser {
+            data,
+            big_endian,
+            pos,
+            hier_map,
+            signals,
+            cycle_time: None,
+            done: false,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// GHDL always dumps time in femtoseconds
+    pub fn timescale(&self) -> Timescale {
+        Timescale {
+            magnitude: 1,
+            unit: TimeUnit::Fs,
+        }
+    }
+
+    pub fn create_hiermap(&mut self) -> Result<HierMap, Waverr> {
+        Ok(std::mem::take(&mut self.hier_map))
+    }
+
+    /// Read the next time step of the body, queueing up its commands
+    fn read_step(&mut self) -> Result<(), Waverr> {
+        let mut reader = ByteReader::new(&self.data, self.pos, self.big_endian);
+        if let Some(time) = self.cycle_time {
+            let mut signal_id = 0;
+            loop {
+                let delta = reader.uleb()? as usize;
+                if delta == 0 {
+                    break;
+                }
+                signal_id += delta;
+                self.signals.read_value(&mut reader, signal_id - 1)?;
+            }
+            self.signals.finish_step(time, &mut self.pending);
+            let time_delta = reader.sleb()?;
+            if time_delta < 0 {
+                reader.mark(END_CYCLE)?;
+                self.cycle_time = None;
+            } else {
+                self.cycle_time = Some(time + time_delta as u64);
+            }
+        } else if reader.is_empty() {
+            // dumps of simulations that didn't finish cleanly have no tailer
+            self.done = true;
+        } else {
+            match reader.bytes(4)? {
+                mark if mark == SECTION_SNAPSHOT => {
+                    reader.zeros()?;
+                    let time = reader.i64()? as u64;
+                    for idx in 0..self.signals.kinds.len() {
+                        if self.signals.kinds[idx].is_some() {
+                            self.signals.read_value(&mut reader, idx)?;
+                        }
+                    }
+                    reader.mark(END_SNAPSHOT)?;
+                    self.signals.finish_step(time, &mut self.pending);
+                }
+                mark if mark == SECTION_CYCLE => {
+                    self.cycle_time = Some(reader.i64()? as u64);
+                }
+                mark if mark == SECTION_DIRECTORY => {
+                    reader.zeros()?;
+                    let num_entries = reader.u32()? as usize;
+                    reader.bytes(num_entries * 8)?;
+                    reader.mark(END_DIRECTORY)?;
+                }
+                mark if mark == SECTION_TAILER => {
+                    reader.bytes(8)?;
+                    self.done = true;
+                }
+                _ => return Err(Waverr::GhwErr("Unknown section in GHW body")),
+            }
+        }
+        self.pos = reader.pos;
+        Ok(())
+    }
+}
+
+impl Iterator for GhwParser {
+    type Item = Result<Command, Waverr>;
+    fn next(&mut self) -> Option<Result<Command, Waverr>> {
+        loop {
+            if let Some(command) = self.pending.pop_front() {
+                return Some(Ok(command));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.read_step() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn ghw_test_path(path: &str) -> PathBuf {
+        let mut path_to_ghw = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_ghw.push(path);
+        path_to_ghw
+    }
+
+    /// Collect the value changes of signal `id` as (time, command) pairs
+    fn changes_of(parser: GhwParser, id: u32) -> Vec<(u64, Command)> {
+        let mut time = 0;
+        let mut changes = Vec::new();
+        for command in parser {
+            match command.unwrap() {
+                Command::Timestamp(new_time) => time = new_time,
+                command => {
+                    if crate::puddle::utils::get_id(&command).unwrap() == id {
+                        changes.push((time, command));
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn fsm_ghw_hier_map() {
+        let mut parser = GhwParser::new(ghw_test_path("test_vcds/fsm.ghw")).unwrap();
+        assert_eq!(
+            parser.timescale(),
+            Timescale {
+                magnitude: 1,
+                unit: TimeUnit::Fs
+            }
+        );
+        let hm = parser.create_hiermap().unwrap();
+        let top = hm.set_path_abs("top").unwrap();
+        let names: Vec<String> = hm
+            .get_module_children(top)
+            .iter()
+            .map(|module| module.name.clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["bus", "history", "pkt", "u_sub", "g(0)", "g(1)"]
+        );
+
+        assert_eq!(hm.path_to_signalref("top.data").unwrap().width(), 8);
+        assert_eq!(hm.path_to_signalref("top.count").unwrap().width(), 32);
+        assert_eq!(hm.path_to_signalref("top.bus.addr").unwrap().width(), 4);
+        assert_eq!(hm.path_to_signalref("top.pkt.payload").unwrap().width(), 2);
+
+        let state = hm.path_to_signalref("top.state").unwrap();
+        assert_eq!(state.width(), 2);
+        assert_eq!(
+            state.literals().unwrap(),
+            &["idle".to_string(), "busy".to_string(), "done".to_string()]
+        );
+        let history = hm.path_to_signalref("top.history.[1]").unwrap();
+        assert_eq!(history.literals(), state.literals());
+        let flag = hm.path_to_signalref("top.g(0).tmp").unwrap();
+        assert_eq!(flag.width(), 1);
+        assert_eq!(flag.literals().unwrap()[1], "true");
+        assert!(hm
+            .path_to_signalref("top.clk")
+            .unwrap()
+            .literals()
+            .is_none());
+
+        // ports share an id with the signal they're bound to
+        let clk = hm.path_to_signalref("top.clk").unwrap();
+        let clk_in = hm.path_to_signalref("top.u_sub.clk_in").unwrap();
+        assert_eq!(clk.id(), clk_in.id());
+        let nibble = hm.path_to_signalref("top.u_sub.nibble").unwrap();
+        assert_eq!(nibble.width(), 4);
+        assert_ne!(nibble.id(), hm.path_to_signalref("top.data").unwrap().id());
+    }
+
+    #[test]
+    fn fsm_ghw_values() {
+        let mut parser = GhwParser::new(ghw_test_path("test_vcds/fsm.ghw")).unwrap();
+        let hm = parser.create_hiermap().unwrap();
+        let state = hm.path_to_signalref("top.state").unwrap().id();
+        let data = hm.path_to_signalref("top.data").unwrap().id();
+        let level = hm.path_to_signalref("top.level").unwrap().id();
+
+        let changes = changes_of(parser, state);
+        assert_eq!(changes.len(), 20);
+        assert_eq!(
+            changes[..3],
+            [
+                (
+                    0,
+                    Command::ChangeVector(state.into(), vec![Value::V0, Value::V0])
+                ),
+                (
+                    3000,
+                    Command::ChangeVector(state.into(), vec![Value::V0, Value::V1])
+                ),
+                (
+                    5000,
+                    Command::ChangeVector(state.into(), vec![Value::V1, Value::V0])
+                ),
+            ]
+        );
+
+        let parser = GhwParser::new(ghw_test_path("test_vcds/fsm.ghw")).unwrap();
+        let changes = changes_of(parser, data);
+        // 'U' is shown as x, and bit vectors are reassembled msb first
+        assert_eq!(
+            changes[0],
+            (0, Command::ChangeVector(data.into(), vec![Value::X; 8]))
+        );
+        assert_eq!(
+            changes[1],
+            (
+                3000,
+                Command::ChangeVector(
+                    data.into(),
+                    vec![
+                        Value::V0,
+                        Value::V0,
+                        Value::V0,
+                        Value::V1,
+                        Value::V0,
+                        Value::V0,
+                        Value::V0,
+                        Value::V1
+                    ]
+                )
+            )
+        );
+
+        let parser = GhwParser::new(ghw_test_path("test_vcds/fsm.ghw")).unwrap();
+        let changes = changes_of(parser, level);
+        assert_eq!(changes[2], (5000, Command::ChangeReal(level.into(), 1.0)));
+        assert_eq!(changes.last().unwrap().0, 39000);
+    }
+
+    #[test]
+    fn gzipped_ghw() {
+        let raw = fs::read(ghw_test_path("test_vcds/fsm.ghw")).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let plain: Vec<Command> = GhwParser::from_bytes(raw)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let unzipped: Vec<Command> = GhwParser::from_bytes(gzipped)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert!(!plain.is_empty());
+        assert_eq!(plain, unzipped);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::errors::Waverr;
+use crate::signals::SigType;
 use serde::{Deserialize, Serialize};
 use vcd::ScopeItem;
 
@@ -97,7 +98,15 @@ impl HierMap {
     /// Get the signals of the "live" module. This is exposed to wave2 app
     /// for filling in the signal navigator
     pub fn get_module_signals_vec(&self, live_module: usize) -> Vec<SignalItem> {
-        self.module_list[live_module].signals.clone()
+        let module_path = self.idx_to_path(live_module);
+        self.module_list[live_module]
+            .signals
+            .iter()
+            .map(|signal| SignalItem {
+                path: Some(format!("{}.{}", module_path, signal.name)),
+                ..signal.clone()
+            })
+            .collect()
     }
 
     /// Map absolute path -> signal id
@@ -122,6 +131,22 @@ impl HierMap {
         }
     }
 
+    /// Append a module to the map, returning its index. Used by parsers whose hierarchy isn't
+    /// expressed as a [`vcd::Header`]
+    pub(crate) fn add_module(&mut self, name: String, parent: Option<usize>) -> usize {
+        let idx = self.module_list.len();
+        self.module_list.push(ModuleItem::new(name, parent, idx));
+        match parent {
+            Some(parent) => self.module_list[parent].add_child(idx),
+            None => self.top_indices.push(idx),
+        }
+        idx
+    }
+
+    pub(crate) fn add_signal(&mut self, module: usize, signal: SignalItem) {
+        self.module_list[module].add_sig(signal);
+    }
+
     pub fn idx_to_path(&self, in_idx: usize) -> String {
         let mut idx = in_idx;
         let mut path = self.module_list[idx].name.clone();
@@ -206,7 +231,12 @@ pub struct ModuleItem {
 
 impl From<vcd::Var> for SignalItem {
     fn from(var: vcd::Var) -> SignalItem {
-        SignalItem::new(var.reference, var.code.0 as u32, var.size)
+        let item = SignalItem::new(var.reference, var.code.0 as u32, var.size);
+        match var.var_type {
+            vcd::VarType::Real => item.with_sig_type(SigType::Float),
+            vcd::VarType::String => item.with_sig_type(SigType::Str(var.size as usize)),
+            _ => item,
+        }
     }
 }
 
@@ -235,6 +265,13 @@ pub struct SignalItem {
     pub(crate) name: String,
     pub(crate) id: u32,
     pub(crate) width: u32,
+    /// Names of the literals of an enumerated type, indexed by value
+    pub(crate) literals: Option<Vec<String>>,
+    pub(crate) sig_type: SigType,
+    /// Dotted path of the signal, filled in when signals are handed out by module; aliases of
+    /// a signal in other modules share everything else
+    #[serde(skip)]
+    pub(crate) path: Option<String>,
 }
 
 impl SignalItem {
@@ -243,8 +280,22 @@ impl SignalItem {
             name,
             id,
             width,
+            literals: None,
+            sig_type: SigType::from_width(width as usize),
+            path: None,
         }
     }
+
+    pub fn with_literals(mut self, literals: Vec<String>) -> Self {
+        self.literals = Some(literals);
+        self
+    }
+
+    pub fn with_sig_type(mut self, sig_type: SigType) -> Self {
+        self.sig_type = sig_type;
+        self
+    }
+
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
@@ -256,6 +307,18 @@ impl SignalItem {
         self.width
     }
 
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn literals(&self) -> Option<&[String]> {
+        self.literals.as_deref()
+    }
+
+    pub fn sig_type(&self) -> SigType {
+        self.sig_type
+    }
+
     pub fn destructure(item: SignalItem) -> (String, u32) {
         (item.name, item.id)
     }
@@ -344,7 +407,13 @@ mod tests {
         let new_live_module = hm.set_path_relative("vga", live_module);
         assert!(new_live_module.is_ok(), "Path exists!");
 
-        let num_children = hm.get_module_signals(new_live_module.unwrap()).len();
+        let vga = new_live_module.unwrap();
+        let num_children = hm.get_module_signals(vga).len();
         assert_eq!(num_children, 30);
+
+        // x_addr is aliased in TOP and TOP.vga
+        let signals = hm.get_module_signals_vec(vga);
+        let x_addr = signals.iter().find(|signal| signal.name() == "x_addr").unwrap();
+        assert_eq!(x_addr.path(), Some("TOP.vga.x_addr"));
     }
 }
@@ -0,0 +1,331 @@
+/*! Pipelined conversion of a signal dump into puddles.
+
+Ingestion is split across threads so that parsing, puddle building and disk writes overlap:
+
+* the calling thread tokenizes the dump, batching value changes by the worker that owns their
+  signal id block
+* worker threads accumulate changes into [`PuddleBuilder`]s, and encode (and compress) each
+  puddle once its time slice is complete
+* a writer thread batches the encoded puddles into sled
+
+Every signal id block is owned by exactly one worker, and each worker sees its changes in dump
+order, so the puddles produced are the same no matter how many workers are used. Workers link
+each puddle to the earlier puddles its signals last changed in as they go. Links to later puddles
+aren't known until those are built, so instead of holding on to puddles, the writer records every
+puddle each signal changes in under [`CHANGES_TREE`], and the next puddle a signal changes in is
+looked up there when a puddle is read back.
+!*/
+use crate::compression::Compression;
+use crate::errors::Waverr;
+use crate::puddle::builder::PuddleBuilder;
+use crate::puddle::utils::get_id;
+use crate::puddle::{Puddle, SignalId, Toffset};
+use crate::MAX_PUDDLE_WIDTH;
+use log::info;
+use sled::{Batch, Db};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use vcd::Command;
+
+/// Number of value changes handed to a worker at a time
+const CHANGE_BATCH_SIZE: usize = 4096;
+/// Number of batches that can be queued up for a single worker
+const CHANGE_QUEUE_DEPTH: usize = 16;
+/// Number of encoded puddles that can be queued up for the writer
+const PUDDLE_QUEUE_DEPTH: usize = 1024;
+/// Number of puddles written to sled per batch
+const WRITE_BATCH_SIZE: u64 = 256;
+/// Number of value changes between progress reports
+const PROGRESS_INTERVAL: u64 = 1 << 18;
+/// Tree holding every puddle, keyed by [`puddle_key`]
+pub(crate) const PUDDLES_TREE: &[u8] = b"puddles";
+/// Tree with an empty entry for every puddle each signal changes in, keyed by [`change_key`]
+pub(crate) const CHANGES_TREE: &[u8] = b"changes";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Snapshot of how far along ingestion is
+pub struct IngestProgress {
+    /// Value changes read from the dump so far
+    pub changes: u64,
+    /// Latest timestamp read from the dump
+    pub time: Toffset,
+    /// Puddles persisted to sled so far
+    pub puddles_written: u64,
+}
+
+/// A puddle encoded and ready to be written to sled
+struct Encoded {
+    btree_idx: Toffset,
+    base_sigid: SignalId,
+    /// Signals that change in the puddle, recorded in [`CHANGES_TREE`]
+    signals: Vec<SignalId>,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Knobs for converting a signal dump into a wavedb
+pub struct IngestOptions {
+    /// Number of puddle building threads
+    pub workers: usize,
+    /// Codec applied to puddles before they are written
+    pub compression: Compression,
+}
+
+impl Default for IngestOptions {
+    /// Two threads are left for tokenizing and writing; puddles are left uncompressed
+    fn default() -> IngestOptions {
+        IngestOptions {
+            workers: num_cpus::get().saturating_sub(2).max(1),
+            compression: Compression::default(),
+        }
+    }
+}
+
+/// Convert `commands` into puddles stored in `db`, returning the first and last timestamps of
+/// the dump
+pub(crate) fn ingest<I>(
+    commands: I,
+    db: &Db,
+    options: IngestOptions,
+    progress: &mut dyn FnMut(IngestProgress),
+) -> Result<(Toffset, Toffset), Waverr>
+where
+    I: Iterator<Item = Result<Command, Waverr>>,
+{
+    let workers = options.workers.max(1);
+    let written = Arc::new(AtomicU64::new(0));
+    let (puddle_tx, puddle_rx) = sync_channel(PUDDLE_QUEUE_DEPTH);
+    let writer = {
+        let db = db.clone();
+        let written = written.clone();
+        thread::spawn(move || write_puddles(db, puddle_rx, written))
+    };
+
+    let (change_txs, builders): (Vec<_>, Vec<_>) = (0..workers)
+        .map(|_| {
+            let (change_tx, change_rx) = sync_channel(CHANGE_QUEUE_DEPTH);
+            let puddle_tx = puddle_tx.clone();
+            let compression = options.compression;
+            let builder = thread::spawn(move || build_puddles(change_rx, puddle_tx, compression));
+            (change_tx, builder)
+        })
+        .collect();
+    drop(puddle_tx);
+
+    let tokenized = tokenize(commands, &change_txs, &written, progress);
+    drop(change_txs);
+
+    // a worker or the writer failing hangs up its channel, which stops the threads feeding it;
+    // check downstream first so the root cause is reported rather than the disconnect
+    let built: Vec<Result<(), Waverr>> = builders.into_iter().map(join).collect();
+    join(writer)?;
+    built.into_iter().try_for_each(|result| result)?;
+    let tokenized = tokenized?;
+
+    let done = IngestProgress {
+        changes: tokenized.changes,
+        time: tokenized.last,
+        puddles_written: written.load(Ordering::Relaxed),
+    };
+    info!("Ingest finished: {:?}", done);
+    progress(done);
+    match tokenized.first {
+        Some(first) => Ok((first, tokenized.last)),
+        None => Err(Waverr::IngestErr("No timestamp present in signal dump")),
+    }
+}
+
+fn join(handle: thread::JoinHandle<Result<(), Waverr>>) -> Result<(), Waverr> {
+    handle
+        .join()
+        .unwrap_or(Err(Waverr::IngestErr("Ingest thread panicked")))
+}
+
+struct Tokenized {
+    first: Option<Toffset>,
+    last: Toffset,
+    changes: u64,
+}
+
+/// Walk the dump, handing each value change (tagged with its timestamp) to the worker that owns
+/// its signal id block
+fn tokenize<I>(
+    commands: I,
+    change_txs: &[SyncSender<Vec<(Toffset, Command)>>],
+    written: &AtomicU64,
+    progress: &mut dyn FnMut(IngestProgress),
+) -> Result<Tokenized, Waverr>
+where
+    I: Iterator<Item = Result<Command, Waverr>>,
+{
+    let mut tokenized = Tokenized {
+        first: None,
+        last: 0,
+        changes: 0,
+    };
+    let mut batches: Vec<Vec<(Toffset, Command)>> = change_txs
+        .iter()
+        .map(|_| Vec::with_capacity(CHANGE_BATCH_SIZE))
+        .collect();
+    let hung_up = || Waverr::IngestErr("Puddle builder stopped early");
+    let mut next_report = PROGRESS_INTERVAL;
+
+    for item in commands {
+        match item? {
+            Command::Timestamp(time) => {
+                if tokenized.first.is_none() {
+                    tokenized.first = Some(time);
+                }
+                tokenized.last = time;
+            }
+            Command::Begin(_) | Command::End(_) => {}
+            command => {
+                let block = get_id(&command)? / Puddle::signals_per_puddle();
+                let worker = block as usize % change_txs.len();
+                batches[worker].push((tokenized.last, command));
+                if batches[worker].len() == CHANGE_BATCH_SIZE {
+                    let batch = std::mem::replace(
+                        &mut batches[worker],
+                        Vec::with_capacity(CHANGE_BATCH_SIZE),
+                    );
+                    change_txs[worker].send(batch).map_err(|_| hung_up())?;
+                }
+                tokenized.changes += 1;
+                if tokenized.changes == next_report {
+                    next_report += PROGRESS_INTERVAL;
+                    let report = IngestProgress {
+                        changes: tokenized.changes,
+                        time: tokenized.last,
+                        puddles_written: written.load(Ordering::Relaxed),
+                    };
+                    info!("Ingest progress: {:?}", report);
+                    progress(report);
+                }
+            }
+        }
+    }
+    for (batch, change_tx) in batches.into_iter().zip(change_txs.iter()) {
+        if !batch.is_empty() {
+            change_tx.send(batch).map_err(|_| hung_up())?;
+        }
+    }
+    Ok(tokenized)
+}
+
+/// Worker loop; accumulates changes into puddles, sending them off to the writer once the dump
+/// moves past their time slice
+fn build_puddles(
+    change_rx: Receiver<Vec<(Toffset, Command)>>,
+    puddle_tx: SyncSender<Encoded>,
+    compression: Compression,
+) -> Result<(), Waverr> {
+    let mut slice_base: Option<Toffset> = None;
+    let mut inflight: HashMap<SignalId, PuddleBuilder> = HashMap::new();
+    // per block, the base of the last puddle each signal changed in
+    let mut last_changes: HashMap<SignalId, HashMap<SignalId, Toffset>> = HashMap::new();
+    let mut flush = |inflight: &mut HashMap<SignalId, PuddleBuilder>| -> Result<(), Waverr> {
+        for (base_sigid, builder) in inflight.drain() {
+            let mut puddle: Puddle = builder.into();
+            puddle.link_prev(last_changes.entry(base_sigid).or_default());
+            let encoded = Encoded {
+                btree_idx: puddle.get_btree_idx(),
+                base_sigid: puddle.get_base_sigid(),
+                signals: puddle.signals().collect(),
+                bytes: compression.compress(puddle.to_bytes()?)?,
+            };
+            puddle_tx
+                .send(encoded)
+                .map_err(|_| Waverr::IngestErr("Puddle writer stopped early"))?;
+        }
+        Ok(())
+    };
+
+    for batch in change_rx.iter() {
+        for (time, command) in batch {
+            let base = time - time % MAX_PUDDLE_WIDTH;
+            match slice_base {
+                Some(current) if current == base => {}
+                Some(current) if current > base => {
+                    return Err(Waverr::PuddleErr {
+                        time: base,
+                        base_sigid: get_id(&command)?,
+                        context: "Timestamps went backwards; this puddle was already written",
+                    });
+                }
+                _ => {
+                    flush(&mut inflight)?;
+                    slice_base = Some(base);
+                }
+            }
+            let id = get_id(&command)?;
+            inflight
+                .entry(id - id % Puddle::signals_per_puddle())
+                .or_insert_with(|| PuddleBuilder::new(base))
+                .add_signal(command, time)?;
+        }
+    }
+    flush(&mut inflight)
+}
+
+/// Writer loop; batches puddles, and the signals that change in them, into sled
+fn write_puddles(
+    db: Db,
+    puddle_rx: Receiver<Encoded>,
+    written: Arc<AtomicU64>,
+) -> Result<(), Waverr> {
+    let puddles_tree = db.open_tree(PUDDLES_TREE)?;
+    let changes_tree = db.open_tree(CHANGES_TREE)?;
+    let (mut puddles, mut changes) = (Batch::default(), Batch::default());
+    let mut pending = 0;
+    let apply = |puddles: Batch, changes: Batch, count: u64| {
+        puddles_tree.apply_batch(puddles)?;
+        changes_tree.apply_batch(changes)?;
+        written.fetch_add(count, Ordering::Relaxed);
+        Ok::<(), Waverr>(())
+    };
+
+    for encoded in puddle_rx.iter() {
+        puddles.insert(
+            &puddle_key(encoded.base_sigid, encoded.btree_idx)[..],
+            encoded.bytes,
+        );
+        for signal in encoded.signals {
+            changes.insert(&change_key(signal, encoded.btree_idx)[..], Vec::new());
+        }
+        pending += 1;
+        if pending == WRITE_BATCH_SIZE {
+            apply(
+                std::mem::take(&mut puddles),
+                std::mem::take(&mut changes),
+                pending,
+            )?;
+            pending = 0;
+        }
+    }
+    apply(puddles, changes, pending)
+}
+
+/// Key of the puddle of block `base_sigid` in the time slice at `btree_idx`. Keys are big
+/// endian, so the puddles of a block are ordered by time
+pub(crate) fn puddle_key(base_sigid: SignalId, btree_idx: Toffset) -> [u8; 12] {
+    let mut key = [0; 12];
+    key[..4].copy_from_slice(&base_sigid.to_be_bytes());
+    key[4..].copy_from_slice(&btree_idx.to_be_bytes());
+    key
+}
+
+/// Key recording that `signal_id` changes in the time slice at `btree_idx`; laid out like
+/// [`puddle_key`]
+pub(crate) fn change_key(signal_id: SignalId, btree_idx: Toffset) -> [u8; 12] {
+    puddle_key(signal_id, btree_idx)
+}
+
+/// Time slice of a key laid out like [`puddle_key`]
+pub(crate) fn key_slice(key: &[u8]) -> Toffset {
+    let mut btree_idx = [0; 8];
+    btree_idx.copy_from_slice(&key[4..12]);
+    Toffset::from_be_bytes(btree_idx)
+}
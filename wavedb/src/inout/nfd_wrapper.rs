@@ -4,8 +4,13 @@ use std::path::{Path, PathBuf};
 
 //Taken almost verbatim from Tolstack. Thank you :-) 
 pub async fn open() -> Result<PathBuf, io::Error> {
+    open_filtered("vcd,fst,ghw").await
+}
+
+/// Ask the user for a file with one of the comma separated `extensions`
+pub async fn open_filtered(extensions: &str) -> Result<PathBuf, io::Error> {
     let result: nfd::Response =
-        match async { nfd::open_file_dialog(Some("vcd"), None) }.await {
+        match async { nfd::open_file_dialog(Some(extensions), None) }.await {
             Ok(result) => result,
             Err(_) => {
                 return Err(io::Error::new(
@@ -1,6 +1,8 @@
 use super::nfd_wrapper;
 use crate::api::WdbApi;
 use crate::errors::Waverr;
+use crate::formatting::EnumMap;
+use crate::puddle::SignalId;
 use crate::wavedb::WaveDb;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
@@ -24,15 +26,26 @@ pub async fn load_vcd() -> Result<Arc<WdbApi>, Waverr> {
 
     let output_path = format!("/tmp/wave2/{}", quick_hash(&path));
     // i am going to be fucking sick
-    let wdb = async { WaveDb::from_vcd(path, Path::new(&output_path)) }.await?;
+    let wdb = async { WaveDb::from_file(path, Path::new(&output_path)) }.await?;
 
     Ok(Arc::new(WdbApi::from(wdb)))
 }
 
+/// Ask the user for a TOML file of enum maps, and attach them to their signals
+pub async fn load_enum_maps(
+    api: Arc<WdbApi>,
+) -> Result<Arc<Vec<(SignalId, EnumMap)>>, Arc<Waverr>> {
+    let path = match nfd_wrapper::open_filtered("toml").await {
+        Ok(path) => path,
+        Err(error) => return Err(Arc::new(Waverr::IoErr(error))),
+    };
+    WdbApi::load_enum_maps(api, path).await
+}
+
 pub async fn load_vcd_from_path(path: PathBuf) -> Option<Arc<WdbApi>> {
     let output_path = format!("/tmp/wave2/{}", quick_hash(&path));
     // i am going to be fucking sick
-    let wdb = async { WaveDb::from_vcd(path, Path::new(&output_path)) }.await.ok();
+    let wdb = async { WaveDb::from_file(path, Path::new(&output_path)) }.await.ok();
     if wdb.is_some() {
         Some(Arc::new(WdbApi::from(wdb.unwrap())))
     } else {
@@ -17,9 +17,16 @@ pub mod hier_map;
 pub mod inout;
 pub mod puddle;
 mod vcd_parser;
+mod fst_parser;
+mod ghw_parser;
 pub mod wavedb;
 pub mod formatting;
+pub mod compression;
+pub mod ingest;
+pub mod timescale;
+pub mod search;
+pub mod expr;
 
 //TODO: maybe replace this eventually
-const MAX_PUDDLE_WIDTH: u32 = puddle::Puddle::max_puddle_length();
+const MAX_PUDDLE_WIDTH: puddle::Toffset = puddle::Puddle::max_puddle_length();
 
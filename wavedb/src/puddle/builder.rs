@@ -1,19 +1,24 @@
 use super::utils::get_id;
-use super::{PMeta, Poffset, Puddle, SignalId, Toffset};
+use super::{PMeta, Poffset, Puddle, SignalId, SummaryMark, Toffset};
 use crate::errors::Waverr;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use vcd::{Command, Value};
 
 /// Transient payload; this is the temporary container that is accumulated into as
-/// a vcd is parsed; TODO: make this sync? in the future it would be nice to build these out in
-/// parallel
+/// a vcd is parsed
 #[derive(Default)]
 struct RunningPayload {
     pub data: Vec<u8>,
     num_items: u16,
     width: usize,
     contains_zx: bool,
+    /// set for signals with variable length values, like strings
+    var_len: bool,
+    /// Summary marks of the buckets that have ended so far
+    marks: Vec<SummaryMark>,
+    /// Offset of the last droplet in `data`
+    last: u32,
 }
 
 #[derive(Default)]
@@ -67,6 +72,27 @@ impl Extend<Value> for RunningPayload {
     }
 }
 
+impl RunningPayload {
+    /// Close every bucket before `bucket`
+    fn mark_until(&mut self, bucket: usize) {
+        while self.marks.len() < bucket {
+            self.marks.push(SummaryMark {
+                changes: self.num_items,
+                last: self.last,
+            });
+        }
+    }
+
+    /// Summary marks of the whole puddle, if the signal changes often enough to need them
+    fn take_summary(&mut self) -> Vec<SummaryMark> {
+        if self.num_items <= Puddle::SUMMARY_MIN_CHANGES {
+            return Vec::new();
+        }
+        self.mark_until(Puddle::summary_buckets());
+        std::mem::take(&mut self.marks)
+    }
+}
+
 impl PuddleBuilder {
     pub fn new(base: Toffset) -> Self {
         PuddleBuilder {
@@ -84,6 +110,8 @@ impl PuddleBuilder {
             .payloads
             .entry(id as u32)
             .or_insert_with(RunningPayload::default);
+        running_pload.mark_until((time_delta >> Puddle::SUMMARY_BUCKET_BITS) as usize);
+        let droplet_offset = running_pload.data.len() as u32;
         running_pload.extend(time_delta.to_le_bytes().iter().cloned());
         match command {
             Command::ChangeScalar(.., val) => {
@@ -96,14 +124,26 @@ impl PuddleBuilder {
                 running_pload.extend(val.into_iter().rev());
             }
             Command::ChangeReal(.., val) => {
+                running_pload.width = 64;
                 running_pload.extend(val.to_le_bytes().iter().cloned());
             }
             Command::ChangeString(.., string) => {
-                running_pload.extend(string.as_bytes().iter().cloned());
+                let len = u16::try_from(string.len()).map_err(|_| Waverr::PuddleErr {
+                    time: timestamp,
+                    base_sigid: id - id % Puddle::signals_per_puddle(),
+                    context: "String values longer than 64KiB are not supported",
+                })?;
+                // flag the droplet as variable length, and follow the header with its length
+                let header_base = running_pload.data.len() - 1;
+                running_pload.data[header_base] |= 0x40;
+                running_pload.var_len = true;
+                running_pload.extend(len.to_le_bytes().iter().cloned());
+                running_pload.extend(string.into_bytes());
             }
             _ => return Err(Waverr::VcdCommandErr(command)),
         }
         running_pload.num_items += 1;
+        running_pload.last = droplet_offset;
         Ok(())
     }
 }
@@ -126,13 +166,15 @@ impl From<PuddleBuilder> for Puddle {
         let payload = puddle_builder
             .payloads
             .into_iter()
-            .flat_map(|(key, payload)| {
+            .flat_map(|(key, mut payload)| {
                 //info!("num items is {}",payload.num_items);
+                let summary = payload.take_summary();
                 let droplet_descriptor = PMeta {
                     offset,
                     len: payload.num_items,
                     width: payload.width,
-                    var_len: payload.contains_zx,
+                    var_len: payload.contains_zx || payload.var_len,
+                    summary,
                 };
                 offset_map.insert(key, droplet_descriptor);
                 offset += payload.data.len();
@@ -427,4 +469,160 @@ pub mod tests {
             assert_eq!(time, droplet.get_timestamp() as usize);
         }
     }
+
+    #[test]
+    fn puddle_builder_strings() {
+        init_test_logger();
+
+        let states = ["IDLE", "", "WAIT_FOR_ACK", "X"];
+        let mut pb = PuddleBuilder::new(0);
+        for i in 0..200 {
+            pb.add_signal(
+                Command::ChangeString(0_u32.into(), states[i % 4].into()),
+                i as Toffset,
+            )
+            .unwrap();
+            // a fixed width neighbour, to check var len droplets don't spill into it
+            pb.add_signal(
+                Command::ChangeVector(1_u32.into(), num_to_vec(5, 4)),
+                i as Toffset,
+            )
+            .unwrap();
+        }
+        let puddle: Puddle = pb.into();
+        let cursor = puddle.get_cursor(0).expect("This cursor should exist");
+        let forward: Vec<Droplet> = cursor.into_iter().collect();
+        assert_eq!(forward.len(), 200);
+        for (time, droplet) in forward.iter().enumerate() {
+            assert!(droplet.is_var_len());
+            assert_eq!(droplet.get_data(), states[time % 4].as_bytes());
+            assert_eq!(time, droplet.get_timestamp() as usize);
+        }
+
+        let backward: Vec<Droplet> = puddle.get_cursor(0).unwrap().rev().collect();
+        assert_eq!(backward.len(), 200);
+        for (droplet, time) in backward.iter().zip((0..200).rev()) {
+            assert_eq!(droplet.get_data(), states[time % 4].as_bytes());
+            assert_eq!(time, droplet.get_timestamp() as usize);
+        }
+
+        let mut cursor = puddle.get_cursor(0).unwrap();
+        assert_eq!(
+            cursor.set_back_index(6).unwrap().get_data(),
+            b"WAIT_FOR_ACK"
+        );
+        assert_eq!(cursor.set_front_index(3).unwrap().get_data(), b"X");
+        assert_eq!(cursor.next().unwrap().get_data(), b"X");
+        assert_eq!(cursor.next().unwrap().get_data(), b"IDLE");
+
+        for droplet in puddle.get_cursor(1).unwrap() {
+            assert_eq!(droplet.get_data(), &[5]);
+        }
+    }
+
+    #[test]
+    fn puddle_builder_too_long_string() {
+        let mut pb = PuddleBuilder::new(0);
+        let long = "a".repeat(u16::MAX as usize + 1);
+        assert!(pb
+            .add_signal(Command::ChangeString(3_u32.into(), long), 0)
+            .is_err());
+    }
+
+    #[test]
+    fn puddle_builder_real() {
+        init_test_logger();
+
+        let mut pb = PuddleBuilder::new(0);
+        for i in 0..100 {
+            pb.add_signal(Command::ChangeReal(0_u32.into(), i as f64 * 0.25), i)
+                .unwrap();
+        }
+        let puddle: Puddle = pb.into();
+        assert_eq!(puddle.get_signal_width(0), Some(64));
+        let droplet_vec: Vec<Droplet> = puddle.get_cursor(0).unwrap().rev().collect();
+        assert_eq!(droplet_vec.len(), 100);
+        for (droplet, time) in droplet_vec.iter().zip((0..100).rev()) {
+            let data = f64::from_le_bytes(droplet.get_data().try_into().unwrap());
+            assert_eq!(data, time as f64 * 0.25);
+            assert_eq!(time, droplet.get_timestamp() as usize);
+        }
+    }
+
+    #[test]
+    fn puddle_builder_summary() {
+        init_test_logger();
+
+        // a busy signal with an x now and then, next to one that rarely changes
+        let mut pb = PuddleBuilder::new(0);
+        for time in (0..Puddle::max_puddle_length()).step_by(3) {
+            let value = if time % 100 == 0 {
+                vec![Value::X]
+            } else {
+                vec![if time % 2 == 0 { Value::V0 } else { Value::V1 }]
+            };
+            pb.add_signal(Command::ChangeVector(0_u32.into(), value), time)
+                .unwrap();
+        }
+        for time in [10, 2000].iter() {
+            pb.add_signal(
+                Command::ChangeVector(1_u32.into(), num_to_vec(*time, 12)),
+                *time,
+            )
+            .unwrap();
+        }
+        let puddle: Puddle = pb.into();
+        assert_eq!(
+            puddle.offset_map[&0].summary.len(),
+            Puddle::summary_buckets()
+        );
+        assert!(puddle.offset_map[&1].summary.is_empty());
+
+        for offset in [0, 1, 3, 63, 64, 65, 100, 101, 1000, 4095, 4096, 5000].iter() {
+            let expected: Vec<Droplet> = puddle
+                .get_cursor(0)
+                .unwrap()
+                .take_while(|droplet| (droplet.get_timestamp() as Toffset) < *offset)
+                .collect();
+            let (changes, last) = puddle.changes_before(0, *offset);
+            assert_eq!(
+                changes as usize,
+                expected.len(),
+                "changes before {}",
+                offset
+            );
+            assert_eq!(
+                last.map(|droplet| droplet.get_timestamp()),
+                expected.last().map(|droplet| droplet.get_timestamp())
+            );
+        }
+        let (changes, last) = puddle.changes_before(1, 2001);
+        assert_eq!(changes, 2);
+        assert_eq!(last.unwrap().get_timestamp(), 2000);
+        assert_eq!(puddle.changes_before(1, 10).0, 0);
+        assert_eq!(puddle.changes_before(2, 10).0, 0);
+    }
+
+    #[test]
+    fn puddle_builder_zx_backwards() {
+        init_test_logger();
+
+        let mut pb = PuddleBuilder::new(0);
+        for i in 0..100 {
+            let value = if i % 3 == 0 {
+                zx_vec_builder(0, 0x1, 0x2, 12)
+            } else {
+                num_to_vec(i, 12)
+            };
+            pb.add_signal(Command::ChangeVector(0_u32.into(), value), i)
+                .unwrap();
+        }
+        let puddle: Puddle = pb.into();
+        let droplet_vec: Vec<Droplet> = puddle.get_cursor(0).unwrap().rev().collect();
+        assert_eq!(droplet_vec.len(), 100);
+        for (droplet, time) in droplet_vec.iter().zip((0..100).rev()) {
+            assert_eq!(droplet.is_zx(), time % 3 == 0);
+            assert_eq!(time, droplet.get_timestamp() as usize);
+        }
+    }
 }
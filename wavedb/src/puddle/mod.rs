@@ -11,7 +11,16 @@ pub type SignalId = u32;
 /// offset into a puddle
 pub type Poffset = usize;
 /// Time offset; describes what puddle to look at
-pub type Toffset = u32;
+pub type Toffset = u64;
+
+/// Change count of a signal at the end of a bucket of a puddle; see [`PMeta::summary`]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+pub struct SummaryMark {
+    /// Number of changes up to the end of the bucket
+    changes: u16,
+    /// Offset, from the start of the signal's data, of the last of these changes
+    last: u32,
+}
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct PMeta {
@@ -24,17 +33,22 @@ pub struct PMeta {
     /// if this slice of the puddle has variable length data
     /// variable length data happens zx bits are present, etc
     var_len: bool,
+    /// Changes up to the end of every bucket of `1 << SUMMARY_BUCKET_BITS` ticks of the puddle.
+    /// Changes in any range of buckets, and so at any coarser resolution, are the difference of
+    /// two marks. Empty for signals that change too rarely for a summary to be worth it
+    summary: Vec<SummaryMark>,
 }
 
 impl PMeta {
     fn width(&self) -> usize {
         self.width
     }
+    /// Length of every droplet of this signal, if they all have the same length
     fn drop_len(&self) -> Option<usize> {
         if self.var_len {
-            Some(self.width() as usize + Droplet::header_width())
-        } else {
             None
+        } else {
+            Some(Droplet::data_len(self.width()) + Droplet::header_width())
         }
     }
 }
@@ -43,7 +57,12 @@ impl PMeta {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Puddle {
     offset_map: HashMap<SignalId, PMeta>,
+    /// Base of the closest later puddle of this block that each signal changes in. These
+    /// aren't known when the puddle is built, so a wavedb looks them up in its index of changes
+    /// when the puddle is read back
+    #[serde(skip)]
     next_sig_map: HashMap<SignalId, Toffset>,
+    /// Base of the closest earlier puddle of this block that each signal changes in
     prev_sig_map: HashMap<SignalId, Toffset>,
     ///Base time offset of this puddle;
     base: Toffset,
@@ -55,6 +74,16 @@ impl Puddle {
     /// The time width of a puddle; currently statically set, maybe worth setting as part of some
     /// configuration for wavedb
     const TIMESTAMP_BITS: u32 = 12;
+    /// Width, in ticks, of the finest buckets summaries are kept at
+    const SUMMARY_BUCKET_BITS: u32 = 6;
+    /// Signals that change at most this often in a puddle are not summarized; walking their
+    /// changes is about as cheap as reading a summary
+    const SUMMARY_MIN_CHANGES: u16 = 64;
+
+    /// Number of buckets in the summary of a signal
+    pub const fn summary_buckets() -> usize {
+        1 << (Puddle::TIMESTAMP_BITS - Puddle::SUMMARY_BUCKET_BITS)
+    }
     pub const fn max_puddle_length() -> Toffset {
         1 << Puddle::TIMESTAMP_BITS
     }
@@ -81,28 +110,114 @@ impl Puddle {
         self.base_sigid
     }
 
-    pub fn get_droplet(&self, signal_id: SignalId, poffset: Poffset) -> Result<Droplet, Toffset> {
+    /// Rough number of bytes this puddle occupies once decoded; used to budget the puddle cache
+    pub fn mem_size(&self) -> usize {
+        std::mem::size_of::<Puddle>()
+            + self.payload.capacity()
+            + self.offset_map.len() * std::mem::size_of::<(SignalId, PMeta)>()
+            + self
+                .offset_map
+                .values()
+                .map(|pmeta| pmeta.summary.capacity() * std::mem::size_of::<SummaryMark>())
+                .sum::<usize>()
+            + (self.next_sig_map.len() + self.prev_sig_map.len())
+                * std::mem::size_of::<(SignalId, Toffset)>()
+    }
+
+    /// Encode this puddle into its on-disk form
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Waverr> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    /// Decode a puddle from its on-disk form
+    pub fn from_bytes(bytes: &[u8]) -> Result<Puddle, Waverr> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+
+    /// Base of the closest earlier puddle that `signal_id` changes in, if it changed before
+    pub fn prev_change_base(&self, signal_id: SignalId) -> Option<Toffset> {
+        self.prev_sig_map.get(&signal_id).cloned()
+    }
+
+    /// Base of the closest later puddle that `signal_id` changes in, if it changes again
+    pub fn next_change_base(&self, signal_id: SignalId) -> Option<Toffset> {
+        self.next_sig_map.get(&signal_id).cloned()
+    }
+
+    /// Fill in `prev_sig_map` from the last puddle each signal of the block changed in, then
+    /// record the changes of this puddle in `last_changes`. Puddles must be linked in time order
+    pub(crate) fn link_prev(&mut self, last_changes: &mut HashMap<SignalId, Toffset>) {
+        self.prev_sig_map = last_changes.clone();
+        for sig_id in self.offset_map.keys() {
+            last_changes.insert(*sig_id, self.base);
+        }
+    }
+
+    /// Signals that change in this puddle
+    pub(crate) fn signals(&self) -> impl Iterator<Item = SignalId> + '_ {
+        self.offset_map.keys().cloned()
+    }
+
+    /// Fill in `next_sig_map` with links looked up apart from this puddle
+    pub(crate) fn set_next_links(&mut self, next_sig_map: HashMap<SignalId, Toffset>) {
+        self.next_sig_map = next_sig_map;
+    }
+
+    /// Get the droplet of `signal_id` at `poffset`; if the signal doesn't change in this
+    /// puddle, the base of the next puddle it changes in is returned instead
+    pub fn get_droplet(
+        &self,
+        signal_id: SignalId,
+        poffset: Poffset,
+    ) -> Result<Droplet, Option<Toffset>> {
         let offset_data = self.offset_map.get(&signal_id);
 
         if offset_data.is_none() {
-            let toffset = *self.next_sig_map.get(&signal_id).expect(
-                "next_sig_map is missing a signal id. TODO: maybe downgrade to recoverable error",
-            );
-            return Err(toffset);
+            return Err(self.next_change_base(signal_id));
         }
 
         let pmeta = offset_data.unwrap();
+        Ok(Droplet::from_bytes(
+            &self.payload,
+            pmeta.offset + poffset,
+            pmeta.width(),
+        ))
+    }
 
-        if pmeta.var_len {
-            unimplemented!("i dont wanna deal with this yet")
-        } else {
-            let lbound = poffset + pmeta.offset;
-            let rbound = lbound + pmeta.drop_len().expect("Must be statically sized");
-
-            Ok(Droplet {
-                content: &self.payload[lbound..rbound],
-            })
+    /// Changes of `sig_id` in the first `offset` ticks of this puddle: how many there are, and
+    /// the last of them. The summary of the signal is used to skip most of its changes, so this
+    /// is cheap no matter how busy the signal is
+    pub fn changes_before(&self, sig_id: SignalId, offset: Toffset) -> (u16, Option<Droplet<'_>>) {
+        let pmeta = match self.offset_map.get(&sig_id) {
+            Some(pmeta) => pmeta,
+            None => return (0, None),
+        };
+        let (mut changes, mut last, mut poffset) = (0, None, pmeta.offset);
+        let bucket =
+            (offset.min(Puddle::max_puddle_length()) >> Puddle::SUMMARY_BUCKET_BITS) as usize;
+        let mark = bucket
+            .checked_sub(1)
+            .and_then(|bucket| pmeta.summary.get(bucket));
+        if let Some(mark) = mark.filter(|mark| mark.changes > 0) {
+            let droplet = Droplet::from_bytes(
+                &self.payload,
+                pmeta.offset + mark.last as Poffset,
+                pmeta.width(),
+            );
+            poffset = pmeta.offset + mark.last as Poffset + droplet.content.len();
+            changes = mark.changes;
+            last = Some(droplet);
+        }
+        while changes < pmeta.len {
+            let droplet = Droplet::from_bytes(&self.payload, poffset, pmeta.width());
+            if droplet.get_timestamp() as Toffset >= offset {
+                break;
+            }
+            poffset += droplet.content.len();
+            changes += 1;
+            last = Some(droplet);
         }
+        (changes, last)
     }
 
     pub fn get_signal_width(&self, sig_id: SignalId) -> Option<usize> {
@@ -121,13 +236,16 @@ impl Puddle {
 #[derive(Debug)]
 pub struct PCursor<'a> {
     sig_id: SignalId,
-    /// Offset into the payload
+    /// Offset into the payload of the droplet at pidx
     poffset: Poffset,
     /// index into the current puddle; keeps track if we need to go to the next puddle
     pidx: u16,
     pidx_back: u16,
     /// length of the puddle; if pidx equals this number, we have to go to the next puddle
     plen: u16,
+    /// Offsets of every droplet of a variable length signal; only needed to walk backwards, so
+    /// this is built on the first call to next_back
+    offsets: Option<Vec<Poffset>>,
     /// this slice should contain the ENTIRE puddle payload
     payload_handle: &'a [u8],
     meta_handle: &'a PMeta,
@@ -140,10 +258,10 @@ impl<'a> Iterator for PCursor<'a> {
         if self.pidx >= self.pidx_back {
             None
         } else {
-            let drop = self.set_front_index(self.pidx);
-            self.poffset += self.get_sigwidth();
+            let drop = self.droplet_at(self.poffset);
+            self.poffset += drop.content.len();
             self.pidx += 1;
-            drop
+            Some(drop)
         }
     }
 }
@@ -154,7 +272,8 @@ impl<'a> DoubleEndedIterator for PCursor<'a> {
             None
         } else {
             self.pidx_back -= 1;
-            self.set_back_index(self.pidx_back)
+            let poffset = self.offset_of(self.pidx_back);
+            Some(self.droplet_at(poffset))
         }
     }
 }
@@ -212,7 +331,7 @@ Droplet structure.
 
 * Timestamp(12 bits): offset from start of the drop.
 * Optional (2 bits): Unallocated
-* Variable length signal (1 bit): this bit is set if the signal has variable length; if this is the case
+* Variable length signal (1 bit): this bit is set if the signal has variable length, such as strings; if this is the case, the header is followed by a 2 byte (little endian) length, and then that many bytes of payload
 * ZX Bit (1bit) : This bit is set if there are any undefined (X) or undriven (HiZ) bits of this signal. If this is high, the payload portion of the Drop will be twice as long.
 
 if the zx bit is set, we have two "parallel" bit vectors that encode the state of the payload.
@@ -237,6 +356,7 @@ so in the case alluded above we have 00, 01, 00 and 10, which maps to 010z
 
 
 */
+#[derive(Clone, Copy, Debug)]
 pub struct Droplet<'a> {
     content: &'a [u8],
 }
@@ -246,9 +366,32 @@ impl<'a> Droplet<'a> {
         2
     }
 
-    fn new(payload: &'a [u8], poffset: Poffset, len: Poffset) -> Self {
+    /// Width of the length that follows the header of variable length droplets
+    const fn len_width() -> usize {
+        2
+    }
+
+    /// Number of bytes needed to hold `width` bits
+    fn data_len(width: usize) -> usize {
+        width / 8 + if width % 8 != 0 { 1 } else { 0 }
+    }
+
+    /// Length of the droplet at the start of `payload`, for a signal `width` bits wide
+    fn len_from_bytes(payload: &[u8], width: usize) -> usize {
+        if Droplet::is_var_from_bytes(payload) {
+            let len = u16::from_le_bytes([payload[2], payload[3]]) as usize;
+            Droplet::header_width() + Droplet::len_width() + len
+        } else if Droplet::is_zx_from_bytes(payload) {
+            Droplet::header_width() + 2 * Droplet::data_len(width)
+        } else {
+            Droplet::header_width() + Droplet::data_len(width)
+        }
+    }
+
+    fn from_bytes(payload: &'a [u8], poffset: Poffset, width: usize) -> Self {
+        let len = Droplet::len_from_bytes(&payload[poffset..], width);
         Droplet {
-            content: &payload[poffset..poffset + len + Droplet::header_width()],
+            content: &payload[poffset..poffset + len],
         }
     }
 
@@ -256,11 +399,11 @@ impl<'a> Droplet<'a> {
         (((self.content[1] & 0x0f) as u16) << 8) | self.content[0] as u16
     }
 
-    fn is_zx_from_bytes(payload: &'a [u8]) -> bool {
+    fn is_zx_from_bytes(payload: &[u8]) -> bool {
         (payload[1] & 0x80) != 0
     }
 
-    fn is_var_from_bytes(payload: &'a [u8]) -> bool {
+    fn is_var_from_bytes(payload: &[u8]) -> bool {
         (payload[1] & 0x40) != 0
     }
 
@@ -268,11 +411,23 @@ impl<'a> Droplet<'a> {
         (self.content[1] & 0x80) != 0
     }
 
+    pub fn is_var_len(&self) -> bool {
+        Droplet::is_var_from_bytes(self.content)
+    }
+
+    fn data_start(&self) -> usize {
+        if self.is_var_len() {
+            Droplet::header_width() + Droplet::len_width()
+        } else {
+            Droplet::header_width()
+        }
+    }
+
     pub fn take_data(self) -> &'a [u8] {
-        &self.content[2..]
+        &self.content[self.data_start()..]
     }
     pub fn get_data(&self) -> &[u8] {
-        &self.content[2..]
+        &self.content[self.data_start()..]
     }
 }
 
@@ -284,36 +439,34 @@ impl<'a> PCursor<'a> {
             pidx_back: meta_handle.len,
             poffset: meta_handle.offset,
             plen: meta_handle.len,
+            offsets: None,
             meta_handle,
             payload_handle: &puddle_handle.payload[..],
             puddle_handle,
         }
     }
 
-    fn get_droplet(&self, sig_width: usize) -> Option<Droplet<'a>> {
-        Some(Droplet {
-            content: &self.payload_handle[self.poffset..self.poffset + sig_width],
-        })
+    fn droplet_at(&self, poffset: Poffset) -> Droplet<'a> {
+        Droplet::from_bytes(self.payload_handle, poffset, self.meta_handle.width())
     }
 
-    fn set_index(&mut self, pidx: u16, starting_pidx: u16) -> Option<Droplet<'a>> {
-        let mut starting_pidx = starting_pidx;
-        if self.meta_handle.var_len {
-            if pidx < self.pidx {
-                starting_pidx = 0;
-                self.poffset = self.meta_handle.offset;
-            }
-            while pidx != starting_pidx {
-                self.poffset += self.get_sigwidth();
-                starting_pidx += 1;
-            }
-            let sig_width = self.get_sigwidth();
-            self.get_droplet(sig_width)
-        } else {
-            let sig_width = self.get_sigwidth();
-            self.poffset = self.meta_handle.offset + (pidx as usize * sig_width);
-            self.get_droplet(sig_width)
+    /// Offset into the payload of the droplet at `pidx`
+    fn offset_of(&mut self, pidx: u16) -> Poffset {
+        if let Some(drop_len) = self.meta_handle.drop_len() {
+            return self.meta_handle.offset + pidx as usize * drop_len;
         }
+        let (payload, meta) = (self.payload_handle, self.meta_handle);
+        let offsets = self.offsets.get_or_insert_with(|| {
+            let mut poffset = meta.offset;
+            (0..meta.len)
+                .map(|_| {
+                    let offset = poffset;
+                    poffset += Droplet::len_from_bytes(&payload[offset..], meta.width());
+                    offset
+                })
+                .collect()
+        });
+        offsets[pidx as usize]
     }
 
     /// Set the back index of cursor, get droplet at that index
@@ -323,63 +476,31 @@ impl<'a> PCursor<'a> {
             self.pidx_back = pidx;
             return None;
         }
-        let starting_index = self.pidx_back;
-        let drop = self.set_index(pidx, starting_index);
         self.pidx_back = pidx;
-        drop
+        let poffset = self.offset_of(pidx);
+        Some(self.droplet_at(poffset))
     }
 
-    /// Set the back index of cursor, get droplet at that index
-    /// used by next_back of double ended iterator
+    /// Set the front index of cursor, get droplet at that index
     pub fn set_front_index(&mut self, pidx: u16) -> Option<Droplet<'a>> {
         if pidx >= self.plen || pidx > self.pidx_back {
             self.pidx = pidx;
             return None;
         }
-        let starting_index = self.pidx;
-        let drop = self.set_index(pidx, starting_index);
         self.pidx = pidx;
-        drop
+        self.poffset = self.offset_of(pidx);
+        Some(self.droplet_at(self.poffset))
     }
 
-    /// Move the cursor to point to the next droplet
-    pub fn next_change(&mut self) -> Result<Droplet, Toffset> {
-        if self.meta_handle.var_len {
-            unimplemented!()
-        }
+    /// Move the cursor to point to the next droplet; past the end of the puddle, the base of
+    /// the next puddle this signal changes in is returned instead
+    pub fn next_change(&mut self) -> Result<Droplet<'a>, Option<Toffset>> {
         self.pidx += 1;
         if self.pidx < self.plen {
-            self.poffset += self.meta_handle.width();
-            Ok(Droplet::new(
-                self.payload_handle,
-                self.poffset,
-                self.meta_handle.width() as Poffset,
-            ))
-        } else {
-            Err(*self.puddle_handle.next_sig_map.get(&self.sig_id).unwrap())
-        }
-    }
-    fn get_sigwidth(&mut self) -> usize {
-        if Droplet::is_var_from_bytes(self.payload_handle) {
-            unimplemented!()
-        } else if Droplet::is_zx_from_bytes(
-            &self.payload_handle[self.poffset..self.poffset + Droplet::header_width()],
-        ) {
-            2 * (self.meta_handle.width() / 8
-                + if self.meta_handle.width() % 8 != 0 {
-                    1
-                } else {
-                    0
-                })
-                + Droplet::header_width()
+            self.poffset += self.droplet_at(self.poffset).content.len();
+            Ok(self.droplet_at(self.poffset))
         } else {
-            self.meta_handle.width() / 8
-                + Droplet::header_width()
-                + if self.meta_handle.width() % 8 != 0 {
-                    1
-                } else {
-                    0
-                }
+            Err(self.puddle_handle.next_change_base(self.sig_id))
         }
     }
 }
@@ -0,0 +1,259 @@
+/*! Searching a wave for the times it takes on a value.
+
+Queries are typed into the search bar of the wave window as a comparison followed by a value,
+such as `== 0x4000_1000`, `!= 0`, `< 12` or `> 0o17`. Digits of `0x`, `0o` and `0b` values can
+be `x` or `?`, so that `== 0b10xx` matches 8 through 11. Searches that don't start with a
+comparison are expressions; see [`crate::expr`].
+!*/
+use crate::errors::Waverr;
+use crate::puddle::Toffset;
+use crate::storage::in_memory::InMemWave;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+}
+
+impl Comparison {
+    /// Operators in the order they are tried against a query, so that `=` doesn't shadow `==`
+    const OPERATORS: [(&'static str, Comparison); 5] = [
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        ("<", Comparison::Lt),
+        (">", Comparison::Gt),
+        ("=", Comparison::Eq),
+    ];
+}
+
+/// A comparison against a value, along with the bits of the value that matter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueQuery {
+    comparison: Comparison,
+    /// Little endian bytes of the value compared against
+    value: Vec<u8>,
+    /// Bits of `value` that are compared; bits past the end of the mask always are
+    care: Vec<u8>,
+}
+
+impl ValueQuery {
+    /// Whether a search is meant as a value query rather than an expression; value queries are
+    /// the ones starting with a comparison
+    pub fn is_value_query(query: &str) -> bool {
+        let query = query.trim_start();
+        Comparison::OPERATORS
+            .iter()
+            .any(|(operator, _)| query.starts_with(operator))
+    }
+
+    /// Parse a query, as typed into the search bar
+    pub fn parse(query: &str) -> Result<ValueQuery, Waverr> {
+        let query = query.trim();
+        let (comparison, value) = Comparison::OPERATORS
+            .iter()
+            .find_map(|(operator, comparison)| {
+                query
+                    .strip_prefix(operator)
+                    .map(|value| (*comparison, value))
+            })
+            .ok_or_else(|| {
+                Waverr::SearchErr(format!(
+                    "expected ==, !=, < or > before a value, got {}",
+                    query
+                ))
+            })?;
+        let (value, care) = parse_pattern(value)?;
+        let ordered = comparison == Comparison::Lt || comparison == Comparison::Gt;
+        if ordered && care.iter().any(|byte| *byte != 0xff) {
+            return Err(Waverr::SearchErr(format!(
+                "don't care digits can only be used with == and !=, got {}",
+                query
+            )));
+        }
+        Ok(ValueQuery {
+            comparison,
+            value,
+            care,
+        })
+    }
+
+    pub fn comparison(&self) -> Comparison {
+        self.comparison
+    }
+
+    /// Whether `data`, a value of a wave `bitwidth` bits wide, satisfies the query; values with
+    /// unknown or high impedance bits never do
+    pub fn matches(&self, data: &[u8], bitwidth: usize) -> bool {
+        let num_bytes = bitwidth.div_ceil(8);
+        let (data, zx) = data.split_at(num_bytes.min(data.len()));
+        if zx.iter().any(|byte| *byte != 0) {
+            return false;
+        }
+        let byte = |bytes: &[u8], idx: usize| bytes.get(idx).cloned().unwrap_or(0);
+        let len = data.len().max(self.value.len());
+        let equal = (0..len).all(|idx| {
+            let care = self.care.get(idx).cloned().unwrap_or(0xff);
+            (byte(data, idx) ^ byte(&self.value, idx)) & care == 0
+        });
+        let order = (0..len)
+            .rev()
+            .map(|idx| byte(data, idx).cmp(&byte(&self.value, idx)))
+            .find(|order| *order != Ordering::Equal)
+            .unwrap_or(Ordering::Equal);
+        match self.comparison {
+            Comparison::Eq => equal,
+            Comparison::Ne => !equal,
+            Comparison::Lt => order == Ordering::Less,
+            Comparison::Gt => order == Ordering::Greater,
+        }
+    }
+
+    /// First time after `time` that `wave` changes to a value satisfying the query
+    pub fn next_match(&self, wave: &InMemWave, time: Toffset) -> Result<Option<Toffset>, Waverr> {
+        let bitwidth = wave.get_width();
+        wave.next_change_where(time, |before, after| self.is_hit(before, after, bitwidth))
+    }
+
+    /// Last time before `time` that `wave` changed to a value satisfying the query
+    pub fn prev_match(&self, wave: &InMemWave, time: Toffset) -> Result<Option<Toffset>, Waverr> {
+        let bitwidth = wave.get_width();
+        wave.prev_change_where(time, |before, after| self.is_hit(before, after, bitwidth))
+    }
+
+    /// Dumping the value a wave already holds again doesn't count as a hit
+    fn is_hit(&self, before: Option<&[u8]>, after: &[u8], bitwidth: usize) -> bool {
+        before != Some(after) && self.matches(after, bitwidth)
+    }
+}
+
+/// Parse a value along with a mask of the bits that matter in it. `0x`, `0o` and `0b` prefixes
+/// and `_` separators are allowed, and digits of prefixed values can be `x` or `?`
+fn parse_pattern(text: &str) -> Result<(Vec<u8>, Vec<u8>), Waverr> {
+    let text = text.trim().replace('_', "").to_ascii_lowercase();
+    let bad_value = || Waverr::SearchErr(format!("{} is not a value", text));
+    let (digits, digit_bits) = if let Some(hex) = text.strip_prefix("0x") {
+        (hex, 4)
+    } else if let Some(octal) = text.strip_prefix("0o") {
+        (octal, 3)
+    } else if let Some(binary) = text.strip_prefix("0b") {
+        (binary, 1)
+    } else {
+        let value: u128 = text.parse().map_err(|_| bad_value())?;
+        return Ok((value.to_le_bytes().to_vec(), vec![0xff; 16]));
+    };
+    if digits.is_empty() {
+        return Err(bad_value());
+    }
+
+    let num_bytes = (digits.len() * digit_bits).div_ceil(8);
+    let (mut value, mut care) = (vec![0u8; num_bytes], vec![0xffu8; num_bytes]);
+    for (idx, digit) in digits.chars().rev().enumerate() {
+        let (digit_value, digit_care) = match digit {
+            'x' | '?' => (0, false),
+            _ => (digit.to_digit(1 << digit_bits).ok_or_else(bad_value)?, true),
+        };
+        for bit in 0..digit_bits {
+            let (byte, shift) = ((idx * digit_bits + bit) / 8, (idx * digit_bits + bit) % 8);
+            if digit_value >> bit & 1 == 1 {
+                value[byte] |= 1 << shift;
+            }
+            if !digit_care {
+                care[byte] &= !(1 << shift);
+            }
+        }
+    }
+    Ok((value, care))
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use crate::wavedb::WaveDb;
+    use std::path::PathBuf;
+    use test_case::test_case;
+
+    #[test_case("== 0x4000_1000", Comparison::Eq; "equal")]
+    #[test_case("!=0", Comparison::Ne; "not equal")]
+    #[test_case("< 12", Comparison::Lt; "less")]
+    #[test_case("> 0o17", Comparison::Gt; "greater")]
+    #[test_case("= 3", Comparison::Eq; "single equals")]
+    fn parse_tests(query: &str, comparison: Comparison) {
+        assert_eq!(ValueQuery::parse(query).unwrap().comparison(), comparison);
+    }
+
+    #[test_case("== 0xg"; "bad digit")]
+    #[test_case("== 0x"; "no digits")]
+    #[test_case("> 0b1x"; "ordered don't care")]
+    #[test_case("== 12x"; "decimal don't care")]
+    #[test_case(""; "empty")]
+    #[test_case("0b10xx"; "bare value")]
+    fn parse_fail_tests(query: &str) {
+        assert!(ValueQuery::parse(query).is_err());
+    }
+
+    #[test_case("== 0xzz", true; "bad value")]
+    #[test_case("  < 3", true; "leading space")]
+    #[test_case("=3", true; "single equals")]
+    #[test_case("valid && data == 0xff", false; "expression")]
+    #[test_case("0x10", false; "bare value")]
+    fn grammar_tests(query: &str, baseline: bool) {
+        assert_eq!(ValueQuery::is_value_query(query), baseline);
+    }
+
+    #[test_case("== 0x4000_1000", &[0x00, 0x10, 0x00, 0x40], 32, true; "equal")]
+    #[test_case("== 0x4000_1000", &[0x01, 0x10, 0x00, 0x40], 32, false; "off by one")]
+    #[test_case("== 0x10", &[0x10, 0x01], 16, false; "upper bits are compared")]
+    #[test_case("!= 0", &[0x00, 0x00], 16, false; "not equal")]
+    #[test_case("< 0x100", &[0xff, 0x00], 16, true; "less")]
+    #[test_case("< 0x100", &[0x00, 0x01], 16, false; "not less")]
+    #[test_case("> 255", &[0x00, 0x01], 16, true; "greater")]
+    #[test_case("== 0b10xx", &[0b1001], 4, true; "don't care")]
+    #[test_case("== 0b10xx", &[0b1101], 4, false; "cared bit")]
+    #[test_case("== 0x4?", &[0x4f], 8, true; "question mark")]
+    #[test_case("!= 0", &[0x00, 0x01], 4, false; "unknown values never match")]
+    fn match_tests(query: &str, data: &[u8], bitwidth: usize, baseline: bool) {
+        let query = ValueQuery::parse(query).unwrap();
+        assert_eq!(query.matches(data, bitwidth), baseline);
+    }
+
+    #[test_case("== 100"; "equal")]
+    #[test_case("> 600"; "greater")]
+    #[test_case("< 3"; "less")]
+    #[test_case("== 0b1xxxxxxx00"; "don't care")]
+    fn search_tests(query: &str) {
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+
+        let query = ValueQuery::parse(query).unwrap();
+        let wave = wdb.get_imw("TOP.x_addr".to_string()).unwrap();
+        let all: Vec<(Toffset, Vec<u8>)> = wave
+            .window(0, Toffset::MAX)
+            .unwrap()
+            .all_data()
+            .map(|(time, data)| (time, data.to_vec()))
+            .collect();
+        let hits: Vec<Toffset> = all
+            .iter()
+            .enumerate()
+            .filter(|(idx, (_, data))| {
+                let before = idx.checked_sub(1).map(|prev| all[prev].1.as_slice());
+                query.is_hit(before, data, wave.get_width())
+            })
+            .map(|(_, (time, _))| *time)
+            .collect();
+        assert!(hits.len() > 2);
+
+        for time in [0, 5000, hits[0], hits[1], 70_000].iter() {
+            let next = hits.iter().find(|hit| *hit > time).cloned();
+            let prev = hits.iter().rev().find(|hit| *hit < time).cloned();
+            assert_eq!(query.next_match(&wave, *time).unwrap(), next);
+            assert_eq!(query.prev_match(&wave, *time).unwrap(), prev);
+        }
+    }
+}
@@ -1,12 +1,25 @@
 use serde::{Deserialize, Serialize};
 
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
 ///Signal type enum; describes bitwidth for vectored signals
 pub enum SigType {
+    #[default]
     Bit,
+    /// Real valued signal, stored as an f64
     Float,
     Vector(usize),
+    /// String valued signal; stored as variable length droplets
     Str(usize),
 }
 
+impl SigType {
+    /// Type of a logic signal that is `width` bits wide
+    pub fn from_width(width: usize) -> SigType {
+        if width == 1 {
+            SigType::Bit
+        } else {
+            SigType::Vector(width)
+        }
+    }
+}
@@ -0,0 +1,372 @@
+/*! Waves computed from other waves, such as `bus[15:8]` or `{hi, lo} + 1`.
+
+A derived wave is evaluated a slice at a time, as windows of it ask for puddles. Its puddles are
+built by a [`PuddleSource`] of its own and kept in the wavedb's puddle cache alongside those of
+dumped signals, so they count against the same budget and are built again if they are evicted.
+Derived waves are [`InMemWave`]s like the dumped signals they come from, and are windowed,
+searched, drawn and formatted the same way.
+
+[`PuddleSource`]: crate::storage::in_memory::PuddleSource
+!*/
+use crate::errors::Waverr;
+use crate::expr::Expression;
+use crate::puddle::builder::PuddleBuilder;
+use crate::puddle::{Puddle, SignalId, Toffset};
+use crate::storage::in_memory::{InMemWave, PuddleSource};
+use crate::storage::puddle_cache::PuddleCache;
+use num_bigint::BigUint;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use vcd::{Command, IdCode, Value};
+
+/// Ids of derived waves count down from here, clear of the ids of dumped signals
+pub const DERIVED_ID_BASE: SignalId = SignalId::MAX;
+
+/// Split a definition such as `hi = bus[15:8]` into the name of the derived wave and its
+/// expression. Definitions without a name are named after their expression
+pub fn parse_definition(definition: &str) -> (&str, &str) {
+    let definition = definition.trim();
+    let bytes = definition.as_bytes();
+    // a lone `=`, rather than one that is part of a comparison
+    let assignment = (0..bytes.len()).find(|idx| {
+        let prev = idx.checked_sub(1).map(|prev| bytes[prev]);
+        bytes[*idx] == b'='
+            && bytes.get(idx + 1) != Some(&b'=')
+            && !matches!(prev, Some(b'=') | Some(b'!') | Some(b'<') | Some(b'>'))
+    });
+    match assignment {
+        Some(idx) if !definition[..idx].trim().is_empty() => {
+            (definition[..idx].trim(), definition[idx + 1..].trim())
+        }
+        Some(idx) => (definition[idx + 1..].trim(), definition[idx + 1..].trim()),
+        None => (definition, definition),
+    }
+}
+
+/// Create a wave named `name` holding the value of `expression`; `waves` are the waves of
+/// [`Expression::signals`]. Times at which any bit the value depends on is unknown are unknown
+/// as a whole. Nothing is evaluated until windows of the wave are taken; their puddles are kept
+/// in `cache`
+pub fn derive_wave(
+    name: String,
+    signal_id: SignalId,
+    expression: Expression,
+    waves: Vec<Arc<InMemWave>>,
+    cache: Arc<Mutex<PuddleCache>>,
+) -> Result<InMemWave, Waverr> {
+    let wave_refs: Vec<&InMemWave> = waves.iter().map(|wave| wave.as_ref()).collect();
+    let width = expression.width(&wave_refs)?;
+    let time_range = wave_refs
+        .iter()
+        .map(|wave| wave.time_range())
+        .reduce(|(begin, end), (first, last)| (begin.min(first), end.max(last)))
+        .ok_or_else(|| Waverr::ExprErr(format!("{} doesn't refer to any signal", name)))?;
+    let source = DerivedSource {
+        signal_id,
+        width,
+        expression,
+        waves,
+        time_range,
+        cache,
+    };
+    Ok(InMemWave::from_source(
+        name,
+        signal_id,
+        width as u32,
+        Arc::new(source),
+    ))
+}
+
+/// Puddles of a derived wave, built from the waves it is computed from as they are asked for.
+/// Each puddle holds the changes of the derived value in its slice; slices the value doesn't
+/// change in have none
+#[derive(Debug)]
+struct DerivedSource {
+    signal_id: SignalId,
+    width: usize,
+    expression: Expression,
+    waves: Vec<Arc<InMemWave>>,
+    time_range: (Toffset, Toffset),
+    cache: Arc<Mutex<PuddleCache>>,
+}
+
+impl DerivedSource {
+    fn waves(&self) -> Vec<&InMemWave> {
+        self.waves.iter().map(|wave| wave.as_ref()).collect()
+    }
+
+    /// First time after `time` that the derived value changes
+    fn next_change(&self, time: Toffset) -> Result<Option<Toffset>, Waverr> {
+        self.expression
+            .next_change_where(&self.waves(), time, |before, after| before != Some(after))
+    }
+
+    /// Last time before `time` that the derived value changed
+    fn prev_change(&self, time: Toffset) -> Result<Option<Toffset>, Waverr> {
+        self.expression
+            .prev_change_where(&self.waves(), time, |before, after| before != Some(after))
+    }
+
+    /// Evaluate the slice starting at `slice_base` into a puddle, linked to the slices of the
+    /// changes around it
+    fn build_puddle(&self, slice_base: Toffset) -> Result<Option<Puddle>, Waverr> {
+        let waves = self.waves();
+        let (first, last) = self.time_range;
+        let slice_end = slice_base + Puddle::max_puddle_length();
+        let begin = slice_base.max(first);
+        // the value carried into the slice; nothing is carried into the start of the dump
+        let mut carried: Option<Option<BigUint>> = None;
+        if begin > first {
+            self.expression
+                .for_each_value(&waves, begin - 1, begin, |_, value| {
+                    carried = Some(value);
+                    Ok(())
+                })?;
+        }
+        let mut builder = PuddleBuilder::new(slice_base);
+        let mut changed = false;
+        self.expression.for_each_value(
+            &waves,
+            begin,
+            slice_end.min(last.saturating_add(1)),
+            |time, value| {
+                if carried.as_ref() == Some(&value) {
+                    return Ok(());
+                }
+                let change = Command::ChangeVector(
+                    IdCode::from(self.signal_id),
+                    bits(value.as_ref(), self.width),
+                );
+                builder.add_signal(change, time)?;
+                changed = true;
+                carried = Some(value);
+                Ok(())
+            },
+        )?;
+        if !changed {
+            return Ok(None);
+        }
+
+        let slice_of = |time: Toffset| (self.signal_id, time & !(Puddle::max_puddle_length() - 1));
+        let mut puddle = Puddle::from(builder);
+        let mut prev_links: HashMap<SignalId, Toffset> = self
+            .prev_change(slice_base)?
+            .map(slice_of)
+            .into_iter()
+            .collect();
+        puddle.link_prev(&mut prev_links);
+        puddle.set_next_links(
+            self.next_change(slice_end - 1)?
+                .map(slice_of)
+                .into_iter()
+                .collect(),
+        );
+        Ok(Some(puddle))
+    }
+
+    /// Puddle of the slice `time` lies in
+    fn puddle_at(&self, time: Option<Toffset>) -> Result<Option<Arc<Puddle>>, Waverr> {
+        match time {
+            Some(time) => {
+                self.get_puddle(self.signal_id, time & !(Puddle::max_puddle_length() - 1))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl PuddleSource for DerivedSource {
+    fn get_puddle(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let (first, last) = self.time_range;
+        if signal_id != self.signal_id
+            || slice_base > last
+            || slice_base + Puddle::max_puddle_length() <= first
+        {
+            return Ok(None);
+        }
+        // derived puddles hold a single signal, and are cached by its id rather than by the
+        // base id of its block, which other derived waves may share
+        let key = (slice_base, self.signal_id);
+        let cached = self
+            .cache
+            .lock()
+            .expect("puddle cache lock poisoned")
+            .get(key);
+        if cached.is_some() {
+            return Ok(cached);
+        }
+        let puddle = match self.build_puddle(slice_base)? {
+            Some(puddle) => Arc::new(puddle),
+            None => return Ok(None),
+        };
+        self.cache
+            .lock()
+            .expect("puddle cache lock poisoned")
+            .insert(key, puddle.clone());
+        Ok(Some(puddle))
+    }
+
+    fn time_range(&self) -> (Toffset, Toffset) {
+        self.time_range
+    }
+
+    /// Slices the derived value doesn't change in aren't evaluated one at a time; the change
+    /// before them is searched for instead
+    fn puddle_before(
+        &self,
+        _signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let change = self.prev_change(slice_base)?;
+        self.puddle_at(change)
+    }
+
+    fn puddle_from(
+        &self,
+        _signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        // the start of the dump is always a change
+        let change = match slice_base.checked_sub(1) {
+            Some(time) => self.next_change(time)?,
+            None => Some(self.time_range.0),
+        };
+        self.puddle_at(change)
+    }
+}
+
+/// Bits of a value, most significant first; unknown values are unknown throughout
+fn bits(value: Option<&BigUint>, width: usize) -> Vec<Value> {
+    (0..width as u64)
+        .rev()
+        .map(|bit| match value {
+            Some(value) if value.bit(bit) => Value::V1,
+            Some(_) => Value::V0,
+            None => Value::X,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use crate::wavedb::WaveDb;
+    use std::path::PathBuf;
+    use test_case::test_case;
+
+    fn create_wdb(vcd: &str) -> WaveDb {
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds");
+        path_to_vcd.push(vcd);
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+
+        WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb")
+    }
+
+    /// Value of a wave's droplet data, if it has no unknown bits
+    fn known_value(data: &[u8], width: usize) -> Option<u64> {
+        let (value, zx) = data.split_at(width.div_ceil(8));
+        if zx.iter().any(|byte| *byte != 0) {
+            return None;
+        }
+        Some(
+            value
+                .iter()
+                .rev()
+                .fold(0, |acc, byte| acc << 8 | *byte as u64),
+        )
+    }
+
+    #[test_case("hi = bus[15:8]", ("hi", "bus[15:8]"); "named")]
+    #[test_case("match = a == b", ("match", "a == b"); "named comparison")]
+    #[test_case("a <= b", ("a <= b", "a <= b"); "comparison")]
+    #[test_case(" = a", ("a", "a"); "empty name")]
+    fn definition_tests(definition: &str, parsed: (&str, &str)) {
+        assert_eq!(parse_definition(definition), parsed);
+    }
+
+    #[test_case("vga.vcd", "TOP", "{vga_hs, x_addr[3:0]} + 1", 6,
+        |v| Some(((v[0]? << 4) | (v[1]? & 0xf)) + 1); "concatenation")]
+    #[test_case("vga.vcd", "TOP", "x_addr - y_addr", 10,
+        |v| Some(v[0]?.wrapping_sub(v[1]?) & 0x3ff); "difference")]
+    #[test_case("idle.vcd", "top", "!rst", 1, |v| Some((v[0]? == 0) as u64); "idle")]
+    #[test_case("idle.vcd", "top", "~cfg", 8, |v| Some(!v[0]? & 0xff); "invert")]
+    fn derived_tests(
+        vcd: &str,
+        scope: &str,
+        text: &str,
+        width: usize,
+        oracle: fn(&[Option<u64>]) -> Option<u64>,
+    ) {
+        let wdb = create_wdb(vcd);
+        let expression = Expression::parse(text).unwrap();
+        let waves: Vec<Arc<InMemWave>> = expression
+            .signals()
+            .iter()
+            .map(|signal| wdb.get_imw(format!("{}.{}", scope, signal)).unwrap())
+            .collect();
+        let derived = derive_wave(
+            text.to_string(),
+            DERIVED_ID_BASE,
+            expression.clone(),
+            waves.clone(),
+            Arc::default(),
+        )
+        .unwrap();
+        assert_eq!(derived.get_width(), width);
+
+        let full = derived.window(0, Toffset::MAX).unwrap();
+        let changes: Vec<(Toffset, Option<u64>)> = full
+            .all_data()
+            .map(|(time, data)| (time, known_value(data, width)))
+            .collect();
+        assert!(changes.windows(2).all(|pair| pair[0].1 != pair[1].1));
+
+        let windows: Vec<InMemWave> = waves
+            .iter()
+            .map(|wave| wave.window(0, Toffset::MAX).unwrap())
+            .collect();
+        for wave in windows.iter() {
+            for (time, _) in wave.all_data() {
+                let values: Vec<Option<u64>> = windows
+                    .iter()
+                    .map(|wave| {
+                        wave.get_prev_time(time + 1)
+                            .and_then(|(_, data)| known_value(data, wave.get_width()))
+                    })
+                    .collect();
+                let derived_value = full
+                    .get_prev_time(time + 1)
+                    .and_then(|(_, data)| known_value(data, width));
+                assert_eq!(derived_value, oracle(&values), "at {}", time);
+            }
+        }
+
+        // values are carried into windows, however long the wave has been idle
+        for begin in [100, 5000, 20_000, 40_000].iter() {
+            let window = derived.window(*begin, begin + 10).unwrap();
+            assert_eq!(window.get_prev_time(*begin), full.get_prev_time(*begin));
+        }
+
+        // puddles evicted from the cache are built again
+        let uncached = derive_wave(
+            text.to_string(),
+            DERIVED_ID_BASE,
+            expression,
+            waves,
+            Arc::new(Mutex::new(PuddleCache::new(0))),
+        )
+        .unwrap();
+        for begin in [0, 100, 5000, 20_000, 40_000].iter() {
+            let window = uncached.window(*begin, begin + 5000).unwrap();
+            let expected = derived.window(*begin, begin + 5000).unwrap();
+            assert!(window.all_data().eq(expected.all_data()));
+            assert_eq!(window.get_prev_time(*begin), expected.get_prev_time(*begin));
+        }
+    }
+}
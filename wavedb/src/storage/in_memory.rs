@@ -1,22 +1,243 @@
 use crate::errors::Waverr;
+use crate::formatting::{data_zx_state, EnumMap};
 use crate::puddle::{Droplet, Puddle, SignalId, Toffset};
+use crate::signals::SigType;
+use std::convert::TryInto;
 use std::sync::Arc;
 
-#[derive(Debug, Default)]
+/// Somewhere puddles can be fetched from on demand, such as a wavedb
+pub trait PuddleSource: std::fmt::Debug + Send + Sync {
+    /// Get the puddle holding `signal_id` for the time slice starting at `slice_base`;
+    /// `Ok(None)` if no such puddle was ever written
+    fn get_puddle(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr>;
+
+    /// First and last timestamps that puddles can exist for
+    fn time_range(&self) -> (Toffset, Toffset);
+
+    /// Closest puddle of `signal_id`'s block in a slice before `slice_base`. Slices in which
+    /// the whole block was idle have no puddle, and are stepped over one at a time unless the
+    /// source can find the puddle more directly
+    fn puddle_before(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let first_base = self.time_range().0 & !(Puddle::max_puddle_length() - 1);
+        let mut base = slice_base;
+        while base > first_base {
+            base -= Puddle::max_puddle_length();
+            if let Some(puddle) = self.get_puddle(signal_id, base)? {
+                return Ok(Some(puddle));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Closest puddle of `signal_id`'s block in a slice at or after `slice_base`
+    fn puddle_from(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let last = self.time_range().1;
+        let mut base = slice_base;
+        while base <= last {
+            if let Some(puddle) = self.get_puddle(signal_id, base)? {
+                return Ok(Some(puddle));
+            }
+            base += Puddle::max_puddle_length();
+        }
+        Ok(None)
+    }
+}
+
+/// Puddles that are already in memory; handy for tests
+impl PuddleSource for Vec<Arc<Puddle>> {
+    fn get_puddle(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let base_sigid = signal_id - signal_id % Puddle::signals_per_puddle();
+        Ok(self
+            .iter()
+            .find(|puddle| {
+                puddle.puddle_base() == slice_base && puddle.get_base_sigid() == base_sigid
+            })
+            .cloned())
+    }
+
+    fn time_range(&self) -> (Toffset, Toffset) {
+        let begin = self.iter().map(|puddle| puddle.puddle_base()).min();
+        let end = self.iter().map(|puddle| puddle.puddle_end() - 1).max();
+        (begin.unwrap_or(0), end.unwrap_or(0))
+    }
+}
+
+/// Timestamp and payload of a change, copied out of the puddle it came from
+pub type CarriedValue = (Toffset, Vec<u8>);
+
+/// A single time slice of a wave's window
+#[derive(Debug, Clone)]
+pub enum WaveSlice {
+    /// Puddle holding the changes of the wave's signal block during this slice
+    Active(Arc<Puddle>),
+    /// No signal in the wave's block changed during this slice, so no puddle exists for it;
+    /// the signal holds the last value it changed to before the slice, if there was one
+    Idle {
+        base: Toffset,
+        last: Option<CarriedValue>,
+    },
+}
+
+impl WaveSlice {
+    pub fn base(&self) -> Toffset {
+        match self {
+            WaveSlice::Active(puddle) => puddle.puddle_base(),
+            WaveSlice::Idle { base, .. } => *base,
+        }
+    }
+
+    pub fn puddle(&self) -> Option<&Arc<Puddle>> {
+        match self {
+            WaveSlice::Active(puddle) => Some(puddle),
+            WaveSlice::Idle { .. } => None,
+        }
+    }
+}
+
+/// Changes of a wave within one column of a summarized range; see [`InMemWave::summarize`]
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnChanges<'a> {
+    /// The wave changed once in the column
+    Single(Toffset, Droplet<'a>),
+    /// The wave changed `changes` times within `[begin, end)`, the last time to `last`
+    Busy {
+        begin: Toffset,
+        end: Toffset,
+        changes: usize,
+        last: Droplet<'a>,
+    },
+}
+
+/// Kind of change searched for by [`InMemWave::next_edge`] and [`InMemWave::prev_edge`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// Any change of value
+    Any,
+    /// A single bit wave going to 1
+    Rising,
+    /// A single bit wave going to 0
+    Falling,
+}
+
+impl Edge {
+    /// Whether a change from `before` to `after` is this kind of edge; the value before the
+    /// first change of a wave is unknown
+    fn is_edge(&self, before: Option<&[u8]>, after: &[u8]) -> bool {
+        let before_level = before.and_then(bit_level);
+        match self {
+            Edge::Any => before != Some(after),
+            Edge::Rising => bit_level(after) == Some(true) && before_level != Some(true),
+            Edge::Falling => bit_level(after) == Some(false) && before_level != Some(false),
+        }
+    }
+}
+
+/// Level of a single bit value; unknown and high impedance bits have none
+fn bit_level(data: &[u8]) -> Option<bool> {
+    if data_zx_state(data, 1).is_some() {
+        return None;
+    }
+    data.first().map(|byte| byte & 1 == 1)
+}
+
+/// Puddles of the active slices in `slices`; idle slices are skipped
+fn puddles(slices: &[WaveSlice]) -> impl DoubleEndedIterator<Item = &Arc<Puddle>> + '_ {
+    slices.iter().filter_map(WaveSlice::puddle)
+}
+
+fn carry(puddle: &Puddle, droplet: Droplet) -> CarriedValue {
+    (
+        puddle.puddle_base() + droplet.get_timestamp() as Toffset,
+        droplet.get_data().to_vec(),
+    )
+}
+
+/// First change of `sigid` within `puddle`
+fn first_change(puddle: &Puddle, sigid: SignalId) -> Option<CarriedValue> {
+    let droplet = puddle.get_cursor(sigid).ok()?.next()?;
+    Some(carry(puddle, droplet))
+}
+
+/// Last change of `sigid` within `puddle`
+fn last_change(puddle: &Puddle, sigid: SignalId) -> Option<CarriedValue> {
+    let droplet = puddle.get_cursor(sigid).ok()?.next_back()?;
+    Some(carry(puddle, droplet))
+}
+
+/// Reals are stored as little endian `f64`s
+fn decode_real(data: &[u8]) -> Option<f64> {
+    Some(f64::from_le_bytes(data.try_into().ok()?))
+}
+
+/// In memory DS for wave content
+///
+/// Only the puddles covering the current window are held; [`InMemWave::window`] creates a
+/// handle over another window, fetching its puddles from the source
+#[derive(Debug)]
 pub struct InMemWave {
     name: String,
+    /// Dotted path of the signal through the module hierarchy, such as `TOP.vga.x_addr`; the
+    /// name, for waves that aren't dumped signals
+    path: String,
     signal_id: SignalId,
     width: u32,
-    puddles: Vec<Arc<Puddle>>,
+    source: Arc<dyn PuddleSource>,
+    /// Time range the resident puddles were fetched for
+    window: (Toffset, Toffset),
+    slices: Vec<WaveSlice>,
+    /// Last change before the first slice of the window
+    carried_in: Option<CarriedValue>,
+    /// Puddle the carried in change was copied out of
+    carried_in_puddle: Option<Arc<Puddle>>,
+    /// First change after the last slice of the window
+    carried_out: Option<CarriedValue>,
+    literals: Option<Vec<String>>,
+    sig_type: SigType,
+    /// Names the user gave to values of this signal
+    enum_map: Option<Arc<EnumMap>>,
+}
+
+impl Default for InMemWave {
+    fn default() -> InMemWave {
+        InMemWave {
+            name: String::default(),
+            path: String::default(),
+            signal_id: 0,
+            width: 0,
+            source: Arc::new(Vec::<Arc<Puddle>>::new()),
+            window: (0, 0),
+            slices: Vec::new(),
+            carried_in: None,
+            carried_in_puddle: None,
+            carried_out: None,
+            literals: None,
+            sig_type: SigType::default(),
+            enum_map: None,
+        }
+    }
 }
 
-///In memory DS for wave content; created from a Vector of Arcs to puddles
 impl InMemWave {
-    pub fn all_data(&self) -> Box<dyn Iterator<Item = (u32, &[u8])> + '_> {
+    pub fn all_data(&self) -> Box<dyn Iterator<Item = (Toffset, &[u8])> + '_> {
         let sigid = self.signal_id;
         Box::new(
-            self.puddles
-                .iter()
+            puddles(&self.slices)
                 .filter_map(move |puddle| {
                     puddle
                         .get_cursor(sigid)
@@ -45,55 +266,223 @@ impl InMemWave {
         )
     }
 
+    /// Index of the resident slice `time` lies in
     fn get_idx(&self, time: Toffset) -> Option<usize> {
-        self.puddles
-            .iter()
-            .position(|puddle| puddle.puddle_base() == time & !(Puddle::max_puddle_length() - 1))
+        let base = time & !(Puddle::max_puddle_length() - 1);
+        self.slices
+            .binary_search_by_key(&base, WaveSlice::base)
+            .ok()
     }
 
+    /// Last change before `time`; changes from before the window are carried forward, so this
+    /// finds the value of signals that have been idle for a long time. Slices the signal doesn't
+    /// change in are skipped by following the links of the puddles; unlinked puddles, such as the
+    /// ones handed to [`InMemWave::new`], are stepped through one at a time
     pub fn get_prev_time(&self, time: Toffset) -> Option<(Toffset, &'_ [u8])> {
-        let idx = self.get_idx(time)?;
+        let mut idx = self.get_idx(time)?;
         let sigid = self.signal_id;
-        self.puddles[0..idx + 1]
-            .iter()
-            .rev()
-            .filter_map(move |puddle| {
-                puddle
-                    .get_cursor(sigid)
-                    .ok()
-                    .map(|cursor| (cursor, puddle.puddle_base()))
-            })
-            .flat_map(|(cursor, base)| cursor.into_iter().rev().zip(std::iter::repeat(base)))
-            .map(|(droplet, base)| {
-                (
-                    base + droplet.get_timestamp() as Toffset,
-                    droplet.take_data(),
-                )
-            })
-            .filter(|(droplet_timestamp, _)| *droplet_timestamp < time)
-            .next()
+        loop {
+            let puddle = match &self.slices[idx] {
+                WaveSlice::Active(puddle) => puddle,
+                WaveSlice::Idle { last, .. } => {
+                    return last.as_ref().map(|(time, data)| (*time, data.as_slice()));
+                }
+            };
+            let base = puddle.puddle_base();
+            let prev = puddle.get_cursor(sigid).ok().and_then(|cursor| {
+                cursor
+                    .into_iter()
+                    .rev()
+                    .map(|droplet| {
+                        (
+                            base + droplet.get_timestamp() as Toffset,
+                            droplet.take_data(),
+                        )
+                    })
+                    .find(|(droplet_timestamp, _)| *droplet_timestamp < time)
+            });
+            if prev.is_some() {
+                return prev;
+            }
+            match puddle.prev_change_base(sigid) {
+                Some(base) => match self.get_idx(base) {
+                    Some(prev_idx) => idx = prev_idx,
+                    None => break,
+                },
+                None if idx > 0 => idx -= 1,
+                None => break,
+            }
+        }
+        self.carried_in
+            .as_ref()
+            .map(|(time, data)| (*time, data.as_slice()))
     }
 
+    /// First change after `time`; changes from after the window are carried back, so this
+    /// finds the next value of signals that are about to be idle for a long time. Slices are
+    /// skipped as in [`InMemWave::get_prev_time`]
     pub fn get_next_time(&self, time: Toffset) -> Option<(Toffset, &'_ [u8])> {
+        let mut idx = self.get_idx(time)?;
+        let sigid = self.signal_id;
+        while idx < self.slices.len() {
+            let puddle = match &self.slices[idx] {
+                WaveSlice::Active(puddle) => puddle,
+                WaveSlice::Idle { .. } => {
+                    idx += 1;
+                    continue;
+                }
+            };
+            let base = puddle.puddle_base();
+            let next = puddle.get_cursor(sigid).ok().and_then(|cursor| {
+                cursor
+                    .into_iter()
+                    .map(|droplet| {
+                        (
+                            base + droplet.get_timestamp() as Toffset,
+                            droplet.take_data(),
+                        )
+                    })
+                    .find(|(droplet_timestamp, _)| *droplet_timestamp > time)
+            });
+            if next.is_some() {
+                return next;
+            }
+            match puddle.next_change_base(sigid) {
+                Some(base) => match self.get_idx(base) {
+                    Some(next_idx) => idx = next_idx,
+                    None => break,
+                },
+                None => idx += 1,
+            }
+        }
+        self.carried_out
+            .as_ref()
+            .map(|(time, data)| (*time, data.as_slice()))
+    }
+
+    /// Value of the wave at `time`, along with when it changed to it; `None` if `time` is outside
+    /// of the window. The summaries of the puddles are used to find the value, so this is cheap
+    /// no matter how busy the wave is
+    pub fn value_at(&self, time: Toffset) -> Option<(Toffset, Droplet<'_>)> {
         let idx = self.get_idx(time)?;
         let sigid = self.signal_id;
-        self.puddles[idx..]
-            .iter()
-            .filter_map(move |puddle| {
-                puddle
-                    .get_cursor(sigid)
-                    .ok()
-                    .map(|cursor| (cursor, puddle.puddle_base()))
-            })
-            .flat_map(|(cursor, base)| cursor.into_iter().zip(std::iter::repeat(base)))
-            .map(|(droplet, base)| {
-                (
-                    base + droplet.get_timestamp() as Toffset,
-                    droplet.take_data(),
-                )
-            })
-            .filter(|(droplet_timestamp, _)| *droplet_timestamp > time)
+        // changes through `time` in its own slice, then any change in the slices before it
+        let through = time - self.slices[idx].base() + 1;
+        let offsets =
+            std::iter::once(through).chain(std::iter::repeat(Puddle::max_puddle_length()));
+        for (puddle, offset) in self.slices[..=idx].iter().rev().zip(offsets) {
+            if let WaveSlice::Active(puddle) = puddle {
+                if let (_, Some(droplet)) = puddle.changes_before(sigid, offset) {
+                    return Some((
+                        puddle.puddle_base() + droplet.get_timestamp() as Toffset,
+                        droplet,
+                    ));
+                }
+            }
+        }
+        let puddle = self.carried_in_puddle.as_ref()?;
+        let droplet = puddle.get_cursor(sigid).ok()?.next_back()?;
+        Some((
+            puddle.puddle_base() + droplet.get_timestamp() as Toffset,
+            droplet,
+        ))
+    }
+
+    /// First change after `time` that is an `edge`. Only single bit waves have rising and
+    /// falling edges
+    pub fn next_edge(&self, time: Toffset, edge: Edge) -> Result<Option<Toffset>, Waverr> {
+        if !self.has_edge(edge) {
+            return Ok(None);
+        }
+        self.next_change_where(time, |before, after| edge.is_edge(before, after))
+    }
+
+    /// Last change before `time` that is an `edge`
+    pub fn prev_edge(&self, time: Toffset, edge: Edge) -> Result<Option<Toffset>, Waverr> {
+        if !self.has_edge(edge) {
+            return Ok(None);
+        }
+        self.prev_change_where(time, |before, after| edge.is_edge(before, after))
+    }
+
+    /// First change after `time` for which `found` holds, given the values before and after
+    /// the change. The search isn't limited to the window; puddles past it are fetched from the
+    /// source a slice at a time
+    pub fn next_change_where<F>(
+        &self,
+        time: Toffset,
+        mut found: F,
+    ) -> Result<Option<Toffset>, Waverr>
+    where
+        F: FnMut(Option<&[u8]>, &[u8]) -> bool,
+    {
+        let from = time.saturating_add(1);
+        let mut before = self.value_before(from)?;
+        for change in self.changes_from(from) {
+            let (change, data) = change?;
+            if found(before.as_deref(), &data) {
+                return Ok(Some(change));
+            }
+            before = Some(data);
+        }
+        Ok(None)
+    }
+
+    /// Last change before `time` for which `found` holds, fetching puddles before the window as
+    /// needed
+    pub fn prev_change_where<F>(
+        &self,
+        time: Toffset,
+        mut found: F,
+    ) -> Result<Option<Toffset>, Waverr>
+    where
+        F: FnMut(Option<&[u8]>, &[u8]) -> bool,
+    {
+        let mut changes = self.changes_until(time);
+        // walking backwards, a change is only known to be found once the one before it is
+        let mut pending = changes.next().transpose()?;
+        while let Some((pending_time, pending_data)) = pending {
+            let before = changes.next().transpose()?;
+            if found(before.as_ref().map(|(_, data)| &data[..]), &pending_data) {
+                return Ok(Some(pending_time));
+            }
+            pending = before;
+        }
+        Ok(None)
+    }
+
+    /// Changes at or after `time`, in time order. They aren't limited to the window; see
+    /// [`Changes`]
+    pub fn changes_from(&self, time: Toffset) -> Changes<'_> {
+        Changes {
+            wave: self,
+            buffered: Vec::new().into_iter(),
+            resume: Some(time),
+            forward: true,
+        }
+    }
+
+    /// Changes before `time`, latest first
+    pub fn changes_until(&self, time: Toffset) -> Changes<'_> {
+        Changes {
+            wave: self,
+            buffered: Vec::new().into_iter(),
+            resume: Some(time),
+            forward: false,
+        }
+    }
+
+    /// Payload of the last change before `time`, wherever it lies
+    pub fn value_before(&self, time: Toffset) -> Result<Option<Vec<u8>>, Waverr> {
+        Ok(self
+            .changes_until(time)
             .next()
+            .transpose()?
+            .map(|(_, data)| data))
+    }
+
+    fn has_edge(&self, edge: Edge) -> bool {
+        edge == Edge::Any || self.width == 1
     }
 
     //fixme; could probably template and
@@ -104,8 +493,7 @@ impl InMemWave {
     ) -> Box<dyn Iterator<Item = (Toffset, Droplet<'_>)> + '_> {
         let sigid = self.signal_id;
         Box::new(
-            self.puddles
-                .iter()
+            puddles(&self.slices)
                 .filter(move |puddle| begin < puddle.puddle_end() && end > puddle.puddle_base())
                 .filter_map(move |puddle| {
                     puddle
@@ -115,7 +503,113 @@ impl InMemWave {
                 })
                 .flat_map(|(cursor, base)| cursor.into_iter().zip(std::iter::repeat(base)))
                 .map(|(droplet, base)| (base + droplet.get_timestamp() as Toffset, droplet))
-                .filter(move |(time, _)| *time >= begin && *time < end),
+                // changes are in time order, so stop at the first one past the range
+                .skip_while(move |(time, _)| *time < begin)
+                .take_while(move |(time, _)| *time < end),
+        )
+    }
+
+    /// Changes in `[begin, end)`, grouped into columns `column` ticks wide starting at `begin`.
+    /// Columns with a single change hold it as is, while busier columns are collapsed into one
+    /// summary, so the work done depends on the number of columns and puddles rather than on the
+    /// number of changes. Puddles are summarized at a single resolution on purpose: an edge of a
+    /// column costs one mark and at most a bucket of changes, and a puddle lying wholly inside a
+    /// column costs its last mark, so coarser marks wouldn't save any work
+    pub fn summarize(
+        &self,
+        begin: Toffset,
+        end: Toffset,
+        column: Toffset,
+    ) -> Vec<ColumnChanges<'_>> {
+        let column = column.max(1);
+        let sigid = self.signal_id;
+        let puddles: Vec<&Arc<Puddle>> = puddles(&self.slices)
+            .filter(|puddle| begin < puddle.puddle_end() && end > puddle.puddle_base())
+            .collect();
+        let mut summary = Vec::new();
+        // first puddle that may overlap the current column
+        let mut first = 0;
+        let mut col_begin = begin;
+        while col_begin < end && first < puddles.len() {
+            // skip over the columns before the next puddle
+            let next_base = puddles[first].puddle_base();
+            if next_base > col_begin {
+                col_begin += (next_base - col_begin) / column * column;
+            }
+            let col_end = col_begin.saturating_add(column).min(end);
+            let mut changes = 0;
+            let mut last = None;
+            for puddle in puddles[first..]
+                .iter()
+                .take_while(|puddle| puddle.puddle_base() < col_end)
+            {
+                let base = puddle.puddle_base();
+                let (before, _) = puddle.changes_before(sigid, col_begin.saturating_sub(base));
+                let (through, droplet) = puddle.changes_before(sigid, col_end - base);
+                if through > before {
+                    changes += (through - before) as usize;
+                    last =
+                        droplet.map(|droplet| (base + droplet.get_timestamp() as Toffset, droplet));
+                }
+            }
+            while first < puddles.len() && puddles[first].puddle_end() <= col_end {
+                first += 1;
+            }
+            match (changes, last) {
+                (1, Some((time, droplet))) => summary.push(ColumnChanges::Single(time, droplet)),
+                (_, Some((_, droplet))) => summary.push(ColumnChanges::Busy {
+                    begin: col_begin,
+                    end: col_end,
+                    changes,
+                    last: droplet,
+                }),
+                _ => {}
+            }
+            col_begin = col_end;
+        }
+        summary
+    }
+
+    /// Values of a real signal changing in `[begin, end)`
+    pub fn reals_in_range(
+        &self,
+        begin: Toffset,
+        end: Toffset,
+    ) -> Box<dyn Iterator<Item = (Toffset, f64)> + '_> {
+        Box::new(
+            self.data_in_range(begin, end)
+                .filter_map(|(time, data)| Some((time, decode_real(data)?))),
+        )
+    }
+
+    /// Value a real signal holds coming into `time`
+    pub fn real_before(&self, time: Toffset) -> Option<f64> {
+        self.get_prev_time(time)
+            .and_then(|(_, data)| decode_real(data))
+    }
+
+    /// Smallest and largest finite values a real signal takes in `[begin, end)`, including the
+    /// value it holds coming into the range; used to scale analog plots
+    pub fn real_bounds(&self, begin: Toffset, end: Toffset) -> Option<(f64, f64)> {
+        self.real_before(begin)
+            .into_iter()
+            .chain(self.reals_in_range(begin, end).map(|(_, value)| value))
+            .filter(|value| value.is_finite())
+            .fold(None, |bounds, value| match bounds {
+                Some((min, max)) => Some((value.min(min), value.max(max))),
+                None => Some((value, value)),
+            })
+    }
+
+    /// Values of a string signal changing in `[begin, end)`
+    pub fn strings_in_range(
+        &self,
+        begin: Toffset,
+        end: Toffset,
+    ) -> Box<dyn Iterator<Item = (Toffset, &str)> + '_> {
+        Box::new(
+            self.data_in_range(begin, end)
+                .filter_map(|(time, data)| Some((time, std::str::from_utf8(data).ok()?))),
         )
     }
 
@@ -127,30 +621,275 @@ impl InMemWave {
         self.name.as_str()
     }
 
+    pub fn get_path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// Literal names of the values of an enumerated signal, if it has any
+    pub fn get_literals(&self) -> Option<&[String]> {
+        self.literals.as_deref()
+    }
+
+    pub fn get_sig_type(&self) -> SigType {
+        self.sig_type
+    }
+
+    pub fn get_signal_id(&self) -> SignalId {
+        self.signal_id
+    }
+
+    /// Names given to the values of this signal, if there are any
+    pub fn get_enum_map(&self) -> Option<&Arc<EnumMap>> {
+        self.enum_map.as_ref()
+    }
+
+    /// Time range covered by the resident puddles
+    pub fn get_window(&self) -> (Toffset, Toffset) {
+        self.window
+    }
+
+    /// First and last timestamps that the source of this wave has puddles for
+    pub fn time_range(&self) -> (Toffset, Toffset) {
+        self.source.time_range()
+    }
+
+    /// Time slices of the window, including idle ones
+    pub fn get_slices(&self) -> &[WaveSlice] {
+        &self.slices
+    }
+
+    /// Create a wave with all of `puddles` resident
     pub fn new(
         name_str: String,
         signal_id: SignalId,
         width: u32,
         puddles: Vec<Arc<Puddle>>,
     ) -> Result<InMemWave, Waverr> {
+        let source = Arc::new(puddles.clone());
         Ok(InMemWave {
+            path: name_str.clone(),
             name: name_str,
             width,
             signal_id,
-            puddles,
+            window: source.time_range(),
+            source,
+            slices: puddles.into_iter().map(WaveSlice::Active).collect(),
+            carried_in: None,
+            carried_in_puddle: None,
+            carried_out: None,
+            literals: None,
+            sig_type: SigType::from_width(width as usize),
+            enum_map: None,
         })
     }
+
+    /// Create a wave with no puddles resident yet; call [`InMemWave::window`] to load some
+    pub fn from_source(
+        name_str: String,
+        signal_id: SignalId,
+        width: u32,
+        source: Arc<dyn PuddleSource>,
+    ) -> InMemWave {
+        InMemWave {
+            path: name_str.clone(),
+            name: name_str,
+            width,
+            signal_id,
+            source,
+            window: (0, 0),
+            slices: Vec::new(),
+            carried_in: None,
+            carried_in_puddle: None,
+            carried_out: None,
+            literals: None,
+            sig_type: SigType::from_width(width as usize),
+            enum_map: None,
+        }
+    }
+
+    /// Create a handle to this wave with the puddles covering `[begin, end)` resident
+    pub fn window(&self, begin: Toffset, end: Toffset) -> Result<InMemWave, Waverr> {
+        let (first, last) = self.source.time_range();
+        let begin = begin.max(first);
+        let end = end.min(last + 1);
+        let mut slices = Vec::new();
+        let (mut carried_in, mut carried_in_puddle, mut carried_out) = (None, None, None);
+        if begin < end {
+            let mut slice_base = begin & !(Puddle::max_puddle_length() - 1);
+            // the closest puddle of this signal's block before the current slice
+            let mut nearest = self.source.puddle_before(self.signal_id, slice_base)?;
+            if let Some(puddle) = nearest.as_ref() {
+                carried_in_puddle = self.puddle_through(puddle)?;
+                carried_in = carried_in_puddle
+                    .as_deref()
+                    .and_then(|puddle| last_change(puddle, self.signal_id));
+            }
+            let mut carried = carried_in.clone();
+            while slice_base < end {
+                match self.source.get_puddle(self.signal_id, slice_base)? {
+                    Some(puddle) => {
+                        carried = last_change(&puddle, self.signal_id).or(carried);
+                        nearest = Some(puddle.clone());
+                        slices.push(WaveSlice::Active(puddle));
+                    }
+                    None => slices.push(WaveSlice::Idle {
+                        base: slice_base,
+                        last: carried.clone(),
+                    }),
+                }
+                slice_base += Puddle::max_puddle_length();
+            }
+            carried_out = self.first_change_from(nearest.as_deref(), slice_base)?;
+        }
+        Ok(InMemWave {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            signal_id: self.signal_id,
+            width: self.width,
+            source: self.source.clone(),
+            window: (begin, end),
+            slices,
+            carried_in,
+            carried_in_puddle,
+            carried_out,
+            literals: self.literals.clone(),
+            sig_type: self.sig_type,
+            enum_map: self.enum_map.clone(),
+        })
+    }
+
+    /// `puddle` if this signal changes in it, or else the puddle it last changed in before
+    fn puddle_through(&self, puddle: &Arc<Puddle>) -> Result<Option<Arc<Puddle>>, Waverr> {
+        if last_change(puddle, self.signal_id).is_some() {
+            return Ok(Some(puddle.clone()));
+        }
+        match puddle.prev_change_base(self.signal_id) {
+            Some(base) => self.source.get_puddle(self.signal_id, base),
+            None => Ok(None),
+        }
+    }
+
+    /// First change of this signal at or after `slice_base`, given `nearest`, the closest
+    /// puddle of the block before `slice_base`. No puddles lie between the two, so its links
+    /// lead straight to the next puddle this signal changes in
+    fn first_change_from(
+        &self,
+        nearest: Option<&Puddle>,
+        slice_base: Toffset,
+    ) -> Result<Option<CarriedValue>, Waverr> {
+        let next_base = match nearest {
+            Some(puddle) => puddle.next_change_base(self.signal_id),
+            None => match self.source.puddle_from(self.signal_id, slice_base)? {
+                Some(puddle) => match first_change(&puddle, self.signal_id) {
+                    Some(first) => return Ok(Some(first)),
+                    None => puddle.next_change_base(self.signal_id),
+                },
+                None => None,
+            },
+        };
+        match next_base {
+            Some(base) => Ok(self
+                .source
+                .get_puddle(self.signal_id, base)?
+                .and_then(|next| first_change(&next, self.signal_id))),
+            None => Ok(None),
+        }
+    }
+
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn with_literals(mut self, literals: Option<Vec<String>>) -> Self {
+        self.literals = literals;
+        self
+    }
+
+    pub fn with_sig_type(mut self, sig_type: SigType) -> Self {
+        self.sig_type = sig_type;
+        self
+    }
+
+    pub fn with_enum_map(mut self, enum_map: Option<Arc<EnumMap>>) -> Self {
+        self.enum_map = enum_map;
+        self
+    }
+}
+
+/// Changes of a wave walked from some time on, or back from it. Puddles are fetched from the
+/// wave's source a slice at a time, and slices the wave is idle in are skipped over, so only the
+/// changes of one slice are held at once however far the walk goes
+#[derive(Debug)]
+pub struct Changes<'a> {
+    wave: &'a InMemWave,
+    /// Changes of the current slice that weren't walked yet, in walking order
+    buffered: std::vec::IntoIter<CarriedValue>,
+    /// Where to pick up once the buffered changes run out; inclusive walking forwards and
+    /// exclusive walking backwards
+    resume: Option<Toffset>,
+    forward: bool,
+}
+
+impl Changes<'_> {
+    fn fill_forward(&mut self, from: Toffset) -> Result<(), Waverr> {
+        let start = from.max(self.wave.source.time_range().0);
+        let window = self.wave.window(start, start.saturating_add(1))?;
+        let changes: Vec<CarriedValue> = window
+            .data_in_range(from, Toffset::MAX)
+            .map(|(time, data)| (time, data.to_vec()))
+            .collect();
+        self.buffered = changes.into_iter();
+        self.resume = window.carried_out.as_ref().map(|(next, _)| *next);
+        Ok(())
+    }
+
+    fn fill_backward(&mut self, until: Toffset) -> Result<(), Waverr> {
+        // the dump may end before `until`
+        let end = until.saturating_sub(1).min(self.wave.source.time_range().1);
+        let window = self.wave.window(end, end.saturating_add(1))?;
+        let mut changes: Vec<CarriedValue> = window
+            .data_in_range(0, until)
+            .map(|(time, data)| (time, data.to_vec()))
+            .collect();
+        changes.reverse();
+        self.buffered = changes.into_iter();
+        self.resume = window.carried_in.as_ref().map(|(prev, _)| prev + 1);
+        Ok(())
+    }
+}
+
+impl Iterator for Changes<'_> {
+    type Item = Result<CarriedValue, Waverr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.buffered.next() {
+                return Some(Ok(change));
+            }
+            let resume = self.resume.take()?;
+            if !self.forward && resume == 0 {
+                return None;
+            }
+            let filled = if self.forward {
+                self.fill_forward(resume)
+            } else {
+                self.fill_backward(resume)
+            };
+            if let Err(err) = filled {
+                return Some(Err(err));
+            }
+        }
+    }
 }
 
 impl std::fmt::Display for InMemWave {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.get_name())?;
         let width = self.get_width();
-        let value = 0;
         if width > 1 {
             write!(f, " [{}:0]", width - 1)?;
         }
-        write!(f, " = {}", value)?;
         Ok(())
     }
 }
@@ -181,6 +920,24 @@ mod tests {
         WaveDb::from_vcd(path_to_wikivcd, db.path()).expect("could not create wavedb")
     }
 
+    /// Utility to create a wavedb with long stretches in which nothing changes
+    fn create_idle_wdb() -> WaveDb {
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/idle.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+
+        WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb")
+    }
+
+    /// Utility to create a wavedb whose timestamps straddle u32::MAX
+    fn create_long_sim_wdb() -> WaveDb {
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/long_sim.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+
+        WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb")
+    }
+
     #[test]
     fn sanity_imw() {
         let signal_width = 16;
@@ -189,7 +946,7 @@ mod tests {
             .map(|idx| build_dummy_puddles(idx * Puddle::max_puddle_length(), 20, signal_width))
             .collect();
         let imw_0 = InMemWave::new("sig_0".into(), 0, signal_width as u32, puddles).unwrap();
-        let first_puddle_fragment: Vec<(u32, &[u8])> = imw_0.data_in_range(0, 1000).collect();
+        let first_puddle_fragment: Vec<(Toffset, &[u8])> = imw_0.data_in_range(0, 1000).collect();
 
         assert_eq!(first_puddle_fragment.len(), 1000);
         for (time, payload) in first_puddle_fragment {
@@ -198,10 +955,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn summarize_busy_columns() {
+        let signal_width = 16;
+        let puddles: Vec<Arc<Puddle>> = (0..2)
+            .map(|idx| build_dummy_puddles(idx * Puddle::max_puddle_length(), 20, signal_width))
+            .collect();
+        let imw = InMemWave::new("sig_0".into(), 0, signal_width as u32, puddles).unwrap();
+
+        // every tick changes, so columns wider than a tick are all busy
+        let summary = imw.summarize(100, 8192, 1000);
+        assert_eq!(summary.len(), 9);
+        for (idx, column) in summary.iter().enumerate() {
+            match column {
+                ColumnChanges::Busy {
+                    begin,
+                    end,
+                    changes,
+                    last,
+                } => {
+                    assert_eq!(*begin, 100 + idx as Toffset * 1000);
+                    assert_eq!(*end, (begin + 1000).min(8192));
+                    assert_eq!(*changes as Toffset, end - begin);
+                    // the value of every change is its time within the puddle
+                    let last = u16::from_le_bytes(last.get_data().try_into().unwrap());
+                    assert_eq!(last as Toffset, (end - 1) % Puddle::max_puddle_length());
+                }
+                ColumnChanges::Single(..) => panic!("column {} should be busy", idx),
+            }
+        }
+
+        let summary = imw.summarize(4090, 4100, 1);
+        let times: Vec<Toffset> = summary
+            .iter()
+            .map(|column| match column {
+                ColumnChanges::Single(time, _) => *time,
+                ColumnChanges::Busy { .. } => panic!("single ticks can't be busy"),
+            })
+            .collect();
+        assert_eq!(times, (4090..4100).collect::<Vec<Toffset>>());
+    }
+
+    #[test]
+    fn edges_match_changes() {
+        let wdb = create_vga_wdb();
+        for (signal, edge) in [
+            ("TOP.clock", Edge::Rising),
+            ("TOP.vga_hs", Edge::Falling),
+            ("TOP.x_addr", Edge::Any),
+        ]
+        .iter()
+        {
+            let wave = wdb.get_imw(signal.to_string()).unwrap();
+            let all: Vec<(Toffset, Vec<u8>)> = wave
+                .window(0, Toffset::MAX)
+                .unwrap()
+                .all_data()
+                .map(|(time, data)| (time, data.to_vec()))
+                .collect();
+            let edges: Vec<Toffset> = all
+                .iter()
+                .enumerate()
+                .filter(|(idx, (_, data))| {
+                    let before = idx.checked_sub(1).map(|prev| all[prev].1.as_slice());
+                    edge.is_edge(before, data)
+                })
+                .map(|(_, (time, _))| *time)
+                .collect();
+            assert!(edges.len() > 2, "{} has too few edges", signal);
+
+            let last = all.last().unwrap().0;
+            let times = [
+                0,
+                5,
+                1234,
+                4096,
+                40_000,
+                edges[1],
+                last - 1,
+                last,
+                last + 10,
+            ];
+            for time in times.iter() {
+                let next = edges.iter().find(|edge| *edge > time).cloned();
+                let prev = edges.iter().rev().find(|edge| *edge < time).cloned();
+                let context = format!("{} around {}", signal, time);
+                assert_eq!(wave.next_edge(*time, *edge).unwrap(), next, "{}", context);
+                assert_eq!(wave.prev_edge(*time, *edge).unwrap(), prev, "{}", context);
+            }
+        }
+    }
+
+    #[test]
+    fn value_at_matches_changes() {
+        let wdb = create_vga_wdb();
+        for signal in ["TOP.clock", "TOP.x_addr", "TOP.vga_hs"].iter() {
+            let wave = wdb.get_imw(signal.to_string()).unwrap();
+            let all: Vec<(Toffset, Vec<u8>)> = wave
+                .window(0, Toffset::MAX)
+                .unwrap()
+                .all_data()
+                .map(|(time, data)| (time, data.to_vec()))
+                .collect();
+            let window = wave.window(20_000, 30_000).unwrap();
+            for time in (20_000..30_000).step_by(37) {
+                let baseline = all.iter().rev().find(|(change, _)| *change <= time);
+                let value = window
+                    .value_at(time)
+                    .map(|(change, droplet)| (change, droplet.get_data().to_vec()));
+                assert_eq!(value.as_ref(), baseline, "{} at {}", signal, time);
+            }
+            assert!(window.value_at(40_000).is_none());
+        }
+
+        // the value of an idle signal is carried in from before the window
+        let wdb = create_idle_wdb();
+        let cfg = wdb.get_imw("top.cfg".to_string()).unwrap();
+        let window = cfg.window(45_000, 46_000).unwrap();
+        let (change, droplet) = window.value_at(45_500).unwrap();
+        assert_eq!((change, droplet.get_data()), (30000, &[0b1010_0101u8][..]));
+    }
+
+    #[test]
+    fn edges_skip_idle_slices() {
+        let wdb = create_idle_wdb();
+        let clk = wdb.get_imw("top.clk".to_string()).unwrap();
+        // clk stops at 1 from 1995 until 40000
+        assert_eq!(clk.next_edge(1995, Edge::Falling).unwrap(), Some(40000));
+        assert_eq!(clk.next_edge(1995, Edge::Rising).unwrap(), Some(40005));
+        assert_eq!(clk.prev_edge(40000, Edge::Rising).unwrap(), Some(1995));
+        assert_eq!(clk.prev_edge(40000, Edge::Falling).unwrap(), Some(1990));
+        assert_eq!(clk.prev_edge(5, Edge::Falling).unwrap(), Some(0));
+        assert_eq!(clk.prev_edge(0, Edge::Any).unwrap(), None);
+
+        let cfg = wdb.get_imw("top.cfg".to_string()).unwrap();
+        assert_eq!(cfg.next_edge(0, Edge::Any).unwrap(), Some(30000));
+        assert_eq!(cfg.next_edge(30000, Edge::Any).unwrap(), None);
+        assert_eq!(cfg.prev_edge(50000, Edge::Any).unwrap(), Some(30000));
+        assert_eq!(cfg.next_edge(0, Edge::Rising).unwrap(), None);
+
+        let rst = wdb.get_imw("top.rst".to_string()).unwrap();
+        assert_eq!(rst.next_edge(0, Edge::Rising).unwrap(), Some(50000));
+        assert_eq!(rst.prev_edge(50000, Edge::Falling).unwrap(), Some(100));
+        assert_eq!(rst.next_edge(50000, Edge::Any).unwrap(), None);
+    }
+
+    #[test]
+    fn summarize_matches_changes() {
+        let wdb = create_vga_wdb();
+        for signal in ["TOP.clock", "TOP.x_addr", "TOP.vga_hs"].iter() {
+            let wave = wdb.get_imw(signal.to_string()).unwrap();
+            let wave = wave.window(0, 100_000).unwrap();
+            for column in [1, 7, 64, 1000, 5000].iter() {
+                let summary = wave.summarize(13, 90_000, *column);
+                let changes: usize = summary
+                    .iter()
+                    .map(|column| match column {
+                        ColumnChanges::Single(..) => 1,
+                        ColumnChanges::Busy { changes, .. } => *changes,
+                    })
+                    .sum();
+                assert_eq!(changes, wave.data_in_range(13, 90_000).count());
+            }
+        }
+    }
+
     #[test]
     fn vga_clock_in_range() {
         let wdb = create_vga_wdb();
-        let clock_wave = wdb.get_imw("TOP.clock".into()).expect("signal isn't here!");
+        let clock_wave = wdb
+            .get_imw("TOP.clock".into())
+            .expect("signal isn't here!")
+            .window(0, 40000)
+            .expect("could not load puddles");
         let mut last_time = 0;
         for (time, payload) in clock_wave.data_in_range(0, 40000) {
             assert!(payload.len() == 1);
@@ -216,7 +1142,9 @@ mod tests {
 
         let clock_wave = wdb
             .get_imw("TOP.x_addr".into())
-            .expect("signal isn't here!");
+            .expect("signal isn't here!")
+            .window(0, 10000)
+            .expect("could not load puddles");
         let mut expected_val = 0;
         for (time, payload) in clock_wave.data_in_range(0, 10000) {
             log::info!("payload is {:?}", payload);
@@ -240,7 +1168,9 @@ mod tests {
 
         let clock_wave = wdb
             .get_imw("TOP.x_addr".into())
-            .expect("signal isn't here!");
+            .expect("signal isn't here!")
+            .window(16000, 20000)
+            .expect("could not load puddles");
         let (toffset, payload) = clock_wave.get_prev_time(16029).expect("prev failed");
         assert_eq!(toffset, 16010);
         let val: u16 = u16::from_le_bytes(
@@ -259,4 +1189,97 @@ mod tests {
         assert_eq!(toffset, 19250);
         assert_eq!(val, 0x1);
     }
+
+    #[test]
+    fn long_sim_counter_past_u32_max() {
+        let wdb = create_long_sim_wdb();
+        let counter = wdb
+            .get_imw("top.counter".into())
+            .expect("signal isn't here!");
+        let wrap_point = u32::MAX as Toffset;
+        let counter = counter
+            .window(wrap_point - 5, wrap_point + 4096)
+            .expect("could not load puddles");
+
+        let (toffset, payload) = counter.get_next_time(wrap_point).expect("next time failed");
+        let val = u16::from_le_bytes(payload.try_into().expect("should be a u16"));
+        assert_eq!(toffset, wrap_point + 10);
+        assert_eq!(val, 411);
+
+        let (toffset, payload) = counter.get_prev_time(toffset).expect("prev time failed");
+        let val = u16::from_le_bytes(payload.try_into().expect("should be a u16"));
+        assert_eq!(toffset, wrap_point);
+        assert_eq!(val, 410);
+
+        let mut expected_val = 410;
+        for (_, payload) in counter.data_in_range(wrap_point - 5, wrap_point + 4096) {
+            let val = u16::from_le_bytes(payload.try_into().expect("should be a u16"));
+            assert_eq!(val, expected_val);
+            expected_val += 1;
+        }
+    }
+
+    #[test]
+    fn window_only_holds_its_slices() {
+        let signal_width = 16;
+        let puddles: Vec<Arc<Puddle>> = (0..5)
+            .map(|idx| build_dummy_puddles(idx * Puddle::max_puddle_length(), 20, signal_width))
+            .collect();
+        let imw = InMemWave::from_source("sig_3".into(), 3, signal_width as u32, Arc::new(puddles));
+        assert_eq!(imw.all_data().count(), 0);
+
+        let begin = Puddle::max_puddle_length() + 100;
+        let end = 2 * Puddle::max_puddle_length() + 100;
+        let window = imw.window(begin, end).unwrap();
+        assert_eq!(window.get_window(), (begin, end));
+        assert_eq!(window.slices.len(), 2);
+        assert_eq!(window.data_in_range(begin, end).count(), 4096);
+        // windows past the end of the source are clamped to it
+        let tail = imw
+            .window(begin, 100 * Puddle::max_puddle_length())
+            .unwrap();
+        assert_eq!(tail.get_window(), (begin, 5 * Puddle::max_puddle_length()));
+        assert_eq!(tail.slices.len(), 4);
+    }
+
+    #[test]
+    fn idle_slices_carry_last_value() {
+        let signal_width = 16;
+        let slice_len = Puddle::max_puddle_length();
+        // nothing changes during slices 1 and 2
+        let puddles: Vec<Arc<Puddle>> = [0, 3]
+            .iter()
+            .map(|idx| build_dummy_puddles(idx * slice_len, 20, signal_width))
+            .collect();
+        let imw = InMemWave::from_source("sig_3".into(), 3, signal_width as u32, Arc::new(puddles));
+
+        let window = imw.window(slice_len + 10, 3 * slice_len + 100).unwrap();
+        let bases: Vec<Toffset> = window.get_slices().iter().map(WaveSlice::base).collect();
+        assert_eq!(bases, vec![slice_len, 2 * slice_len, 3 * slice_len]);
+        for slice in &window.get_slices()[0..2] {
+            match slice {
+                WaveSlice::Idle { last, .. } => {
+                    assert_eq!(last, &Some((slice_len - 1, 4095u16.to_le_bytes().to_vec())))
+                }
+                WaveSlice::Active(_) => panic!("slice {} should be idle", slice.base()),
+            }
+        }
+        assert_eq!(window.all_data().count(), slice_len as usize);
+        assert_eq!(
+            window
+                .data_in_range(slice_len + 10, 3 * slice_len + 100)
+                .count(),
+            100
+        );
+
+        let (time, payload) = window.get_prev_time(2 * slice_len + 5).unwrap();
+        assert_eq!((time, payload), (slice_len - 1, &4095u16.to_le_bytes()[..]));
+        let (time, payload) = window.get_next_time(2 * slice_len + 5).unwrap();
+        assert_eq!((time, payload), (3 * slice_len, &0u16.to_le_bytes()[..]));
+
+        // the value from before the window is carried in, even when it starts on an active slice
+        let window = imw.window(3 * slice_len, 3 * slice_len + 100).unwrap();
+        let (time, _) = window.get_prev_time(3 * slice_len).unwrap();
+        assert_eq!(time, slice_len - 1);
+    }
 }
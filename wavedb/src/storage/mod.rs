@@ -1 +1,3 @@
+pub mod derived;
 pub mod in_memory;
+pub mod puddle_cache;
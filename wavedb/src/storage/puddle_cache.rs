@@ -0,0 +1,182 @@
+/*! LRU cache of decoded puddles, bounded by an approximate memory budget.
+
+Windows of [`InMemWave`]s fetch their puddles through this cache, so that panning back and forth
+over a signal doesn't go back to sled (and decompress) for every frame.
+
+[`InMemWave`]: crate::storage::in_memory::InMemWave
+!*/
+use crate::puddle::{Puddle, SignalId, Toffset};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Budget used until one is configured; 256MiB
+pub const DEFAULT_CACHE_BUDGET: usize = 256 << 20;
+
+/// Puddles are identified by the base of their time slice and their base signal id
+pub type PuddleKey = (Toffset, SignalId);
+
+struct CacheEntry {
+    puddle: Arc<Puddle>,
+    size: usize,
+    last_used: u64,
+}
+
+pub struct PuddleCache {
+    budget: usize,
+    used: usize,
+    /// Monotonic counter, bumped on every access
+    clock: u64,
+    entries: HashMap<PuddleKey, CacheEntry>,
+    /// Keys ordered by their last access, oldest first
+    recency: BTreeMap<u64, PuddleKey>,
+}
+
+impl Default for PuddleCache {
+    fn default() -> PuddleCache {
+        PuddleCache::new(DEFAULT_CACHE_BUDGET)
+    }
+}
+
+impl std::fmt::Debug for PuddleCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PuddleCache")
+            .field("budget", &self.budget)
+            .field("used", &self.used)
+            .field("puddles", &self.entries.len())
+            .finish()
+    }
+}
+
+impl PuddleCache {
+    pub fn new(budget: usize) -> PuddleCache {
+        PuddleCache {
+            budget,
+            used: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    /// Approximate number of bytes held by cached puddles
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Change the budget, evicting puddles if the cache is now over it
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    pub fn get(&mut self, key: PuddleKey) -> Option<Arc<Puddle>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(&key)?;
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(self.clock, key);
+        entry.last_used = self.clock;
+        Some(entry.puddle.clone())
+    }
+
+    /// Cache `puddle`, evicting the least recently used puddles to stay within budget. Puddles
+    /// that are larger than the whole budget are not cached
+    pub fn insert(&mut self, key: PuddleKey, puddle: Arc<Puddle>) {
+        let size = puddle.mem_size();
+        if size > self.budget {
+            return;
+        }
+        self.clock += 1;
+        if let Some(old) = self.entries.insert(
+            key,
+            CacheEntry {
+                puddle,
+                size,
+                last_used: self.clock,
+            },
+        ) {
+            self.recency.remove(&old.last_used);
+            self.used -= old.size;
+        }
+        self.recency.insert(self.clock, key);
+        self.used += size;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.budget {
+            let oldest = match self.recency.keys().next() {
+                Some(oldest) => *oldest,
+                None => return,
+            };
+            let key = self.recency.remove(&oldest).expect("key was just found");
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used -= entry.size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(dead_code, unused_macros, unused_imports, unused_variables)]
+mod tests {
+    use super::*;
+    use crate::puddle::builder::tests::build_dummy_puddles;
+
+    fn puddle(slice: Toffset) -> Arc<Puddle> {
+        build_dummy_puddles(slice * Puddle::max_puddle_length(), 2, 8)
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let size = puddle(0).mem_size();
+        let mut cache = PuddleCache::new(size * 3);
+        for slice in 0..3 {
+            cache.insert((slice, 0), puddle(slice));
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.used(), size * 3);
+
+        // touching slice 0 makes slice 1 the oldest
+        assert!(cache.get((0, 0)).is_some());
+        cache.insert((3, 0), puddle(3));
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get((1, 0)).is_none());
+        assert!(cache.get((0, 0)).is_some());
+        assert!(cache.get((3, 0)).is_some());
+
+        cache.set_budget(size);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get((3, 0)).is_some());
+        assert!(cache.used() <= cache.budget());
+    }
+
+    #[test]
+    fn oversized_puddles_are_not_cached() {
+        let mut cache = PuddleCache::new(16);
+        cache.insert((0, 0), puddle(0));
+        assert!(cache.is_empty());
+        assert_eq!(cache.used(), 0);
+    }
+
+    #[test]
+    fn reinserting_replaces() {
+        let size = puddle(0).mem_size();
+        let mut cache = PuddleCache::new(size * 4);
+        cache.insert((0, 0), puddle(0));
+        cache.insert((0, 0), puddle(0));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used(), size);
+    }
+}
@@ -0,0 +1,322 @@
+/*! Simulation timescale, as declared by `$timescale` in a signal dump.
+
+Every timestamp stored in wavedb is a count of simulation ticks; [`Timescale`] maps those ticks
+to real time so that the wave2 application can display them in engineering units.
+!*/
+use crate::puddle::Toffset;
+use serde::{Deserialize, Serialize};
+
+/// Units that frequencies are displayed in, along with their size in Hz
+const FREQUENCY_UNITS: [(f64, &str); 5] = [
+    (1.0, "Hz"),
+    (1e3, "kHz"),
+    (1e6, "MHz"),
+    (1e9, "GHz"),
+    (1e12, "THz"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Units of time that can be declared by a dump or used for display
+pub enum TimeUnit {
+    Fs,
+    Ps,
+    Ns,
+    Us,
+    Ms,
+    S,
+}
+
+impl TimeUnit {
+    pub const ALL: [TimeUnit; 6] = [
+        TimeUnit::Fs,
+        TimeUnit::Ps,
+        TimeUnit::Ns,
+        TimeUnit::Us,
+        TimeUnit::Ms,
+        TimeUnit::S,
+    ];
+
+    /// Number of femtoseconds in one of this unit
+    pub const fn as_fs(&self) -> u128 {
+        match self {
+            TimeUnit::Fs => 1,
+            TimeUnit::Ps => 1_000,
+            TimeUnit::Ns => 1_000_000,
+            TimeUnit::Us => 1_000_000_000,
+            TimeUnit::Ms => 1_000_000_000_000,
+            TimeUnit::S => 1_000_000_000_000_000,
+        }
+    }
+
+    pub const fn suffix(&self) -> &'static str {
+        match self {
+            TimeUnit::Fs => "fs",
+            TimeUnit::Ps => "ps",
+            TimeUnit::Ns => "ns",
+            TimeUnit::Us => "us",
+            TimeUnit::Ms => "ms",
+            TimeUnit::S => "s",
+        }
+    }
+
+    fn from_suffix(suffix: &str) -> Option<TimeUnit> {
+        TimeUnit::ALL
+            .iter()
+            .find(|unit| unit.suffix() == suffix)
+            .cloned()
+    }
+}
+
+impl From<vcd::TimescaleUnit> for TimeUnit {
+    fn from(unit: vcd::TimescaleUnit) -> TimeUnit {
+        match unit {
+            vcd::TimescaleUnit::FS => TimeUnit::Fs,
+            vcd::TimescaleUnit::PS => TimeUnit::Ps,
+            vcd::TimescaleUnit::NS => TimeUnit::Ns,
+            vcd::TimescaleUnit::US => TimeUnit::Us,
+            vcd::TimescaleUnit::MS => TimeUnit::Ms,
+            vcd::TimescaleUnit::S => TimeUnit::S,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.suffix())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+/// Duration of a single simulation tick, e.g. `$timescale 100 ns $end` is magnitude 100, unit ns
+pub struct Timescale {
+    pub magnitude: u32,
+    pub unit: TimeUnit,
+}
+
+impl Default for Timescale {
+    /// Dumps that don't declare a timescale are treated as 1ns
+    fn default() -> Timescale {
+        Timescale {
+            magnitude: 1,
+            unit: TimeUnit::Ns,
+        }
+    }
+}
+
+impl From<(u32, vcd::TimescaleUnit)> for Timescale {
+    fn from((magnitude, unit): (u32, vcd::TimescaleUnit)) -> Timescale {
+        Timescale {
+            magnitude,
+            unit: unit.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Timescale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.magnitude, self.unit)
+    }
+}
+
+impl Timescale {
+    /// Timescale of `10^exponent` seconds, the form FST headers use; exponents outside of the
+    /// representable range are clamped
+    pub fn from_exponent(exponent: i8) -> Timescale {
+        let exponent = (exponent as i32).max(-15);
+        let unit_idx = ((exponent + 15) / 3).min(TimeUnit::ALL.len() as i32 - 1);
+        let magnitude_exp = (exponent + 15 - unit_idx * 3).min(9);
+        Timescale {
+            magnitude: 10u32.pow(magnitude_exp as u32),
+            unit: TimeUnit::ALL[unit_idx as usize],
+        }
+    }
+
+    /// Number of femtoseconds in a single simulation tick
+    pub fn tick_fs(&self) -> u128 {
+        self.magnitude as u128 * self.unit.as_fs()
+    }
+
+    /// Convert a count of simulation ticks to femtoseconds
+    pub fn to_fs(&self, time: Toffset) -> u128 {
+        time as u128 * self.tick_fs()
+    }
+
+    /// Pick the largest unit that a span of `span` ticks can be expressed in without dropping below
+    /// 1; used to select display units as the wave window zooms in and out
+    pub fn auto_unit(&self, span: Toffset) -> TimeUnit {
+        let span_fs = self.to_fs(span);
+        TimeUnit::ALL
+            .iter()
+            .rev()
+            .find(|unit| span_fs >= unit.as_fs())
+            .cloned()
+            .unwrap_or(TimeUnit::Fs)
+    }
+
+    /// Render `time` (in ticks) in `unit`, with at most 3 fractional digits
+    pub fn format_time(&self, time: Toffset, unit: TimeUnit) -> String {
+        let time_fs = self.to_fs(time);
+        let divisor = unit.as_fs();
+        let whole = time_fs / divisor;
+        // round to the nearest thousandth of the display unit
+        let thousandths = ((time_fs % divisor) * 1000 + divisor / 2) / divisor;
+        let (whole, thousandths) = if thousandths == 1000 {
+            (whole + 1, 0)
+        } else {
+            (whole, thousandths)
+        };
+        if thousandths == 0 {
+            format!("{}{}", whole, unit)
+        } else {
+            let frac = format!("{:03}", thousandths);
+            format!("{}.{}{}", whole, frac.trim_end_matches('0'), unit)
+        }
+    }
+
+    /// Format `time` in whatever unit is most legible for it
+    pub fn format_time_auto(&self, time: Toffset) -> String {
+        self.format_time(time, self.auto_unit(time))
+    }
+
+    /// Render the frequency of something that repeats every `period` ticks, e.g. `8.333MHz`,
+    /// with at most 3 fractional digits; a zero period has no frequency
+    pub fn format_frequency(&self, period: Toffset) -> Option<String> {
+        let period_fs = self.to_fs(period);
+        if period_fs == 0 {
+            return None;
+        }
+        let hz = TimeUnit::S.as_fs() as f64 / period_fs as f64;
+        let (scale, suffix) = FREQUENCY_UNITS
+            .iter()
+            .rev()
+            .find(|(scale, _)| hz >= *scale)
+            .unwrap_or(&FREQUENCY_UNITS[0]);
+        let scaled = format!("{:.3}", hz / scale);
+        Some(format!(
+            "{}{}",
+            scaled.trim_end_matches('0').trim_end_matches('.'),
+            suffix
+        ))
+    }
+
+    /// Parse user input such as `1.5us`, `200 ps` or `40` into simulation ticks.
+    /// Bare numbers are interpreted as ticks; values are rounded to the nearest tick
+    pub fn parse_time(&self, input: &str) -> Option<Toffset> {
+        let input = input.trim();
+        let split = input
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(input.len());
+        let (number, suffix) = input.split_at(split);
+        let suffix = suffix.trim();
+        if suffix.is_empty() {
+            return number.parse::<Toffset>().ok();
+        }
+
+        let unit = TimeUnit::from_suffix(suffix.to_lowercase().as_str())?;
+        let (whole, frac) = match number.find('.') {
+            Some(idx) => (&number[..idx], &number[idx + 1..]),
+            None => (number, ""),
+        };
+        if whole.is_empty() && frac.is_empty() {
+            return None;
+        }
+        let whole: u128 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+        let mut time_fs = whole.checked_mul(unit.as_fs())?;
+        let mut place = unit.as_fs();
+        for digit in frac.chars() {
+            place /= 10;
+            time_fs += digit.to_digit(10)? as u128 * place;
+        }
+        let tick_fs = self.tick_fs();
+        let ticks = (time_fs + tick_fs / 2) / tick_fs;
+        if ticks > Toffset::MAX as u128 {
+            None
+        } else {
+            Some(ticks as Toffset)
+        }
+    }
+}
+
+/// Smallest step of the 1-2-5 sequence (1, 2, 5, 10, 20, 50, ...) that is at least `min_step`
+/// ticks; used to space the delimiters of the wave window's ruler
+pub fn step_1_2_5(min_step: f64) -> Toffset {
+    let mut decade: Toffset = 1;
+    loop {
+        for multiple in [1, 2, 5].iter() {
+            let step = decade.saturating_mul(*multiple);
+            if step as f64 >= min_step || step == Toffset::MAX {
+                return step;
+            }
+        }
+        decade = decade.saturating_mul(10);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{step_1_2_5, TimeUnit, Timescale};
+    use crate::puddle::Toffset;
+    use test_case::test_case;
+
+    fn ts(magnitude: u32, unit: TimeUnit) -> Timescale {
+        Timescale { magnitude, unit }
+    }
+
+    #[test_case(ts(1, TimeUnit::Ns), 1500, TimeUnit::Us, "1.5us"; "ns to us")]
+    #[test_case(ts(1, TimeUnit::Ns), 2000, TimeUnit::Us, "2us"; "whole us")]
+    #[test_case(ts(100, TimeUnit::Ns), 2211, TimeUnit::Us, "221.1us"; "100ns ticks")]
+    #[test_case(ts(1, TimeUnit::Ps), 1_234_567, TimeUnit::Ns, "1234.567ns"; "ps to ns")]
+    #[test_case(ts(1, TimeUnit::Ps), 1_999_999, TimeUnit::Us, "2us"; "rounds up")]
+    #[test_case(ts(10, TimeUnit::Fs), 7, TimeUnit::Ps, "0.07ps"; "fractional only")]
+    fn format_tests(timescale: Timescale, time: Toffset, unit: TimeUnit, baseline: &'static str) {
+        assert_eq!(timescale.format_time(time, unit), baseline);
+    }
+
+    #[test_case(ts(1, TimeUnit::Ps), 999, TimeUnit::Ps; "stay in ps")]
+    #[test_case(ts(1, TimeUnit::Ps), 1000, TimeUnit::Ns; "promote to ns")]
+    #[test_case(ts(100, TimeUnit::Ns), 20, TimeUnit::Us; "scaled ticks")]
+    #[test_case(ts(1, TimeUnit::Ns), 0, TimeUnit::Fs; "zero span")]
+    fn auto_unit_tests(timescale: Timescale, span: Toffset, unit: TimeUnit) {
+        assert_eq!(timescale.auto_unit(span), unit);
+    }
+
+    #[test_case(ts(1, TimeUnit::Ps), "1.5ns", Some(1500); "fractional ns")]
+    #[test_case(ts(1, TimeUnit::Ns), "200 ps", Some(0); "below a tick")]
+    #[test_case(ts(1, TimeUnit::Ns), "40", Some(40); "bare ticks")]
+    #[test_case(ts(100, TimeUnit::Ns), "2US", Some(20); "upper case unit")]
+    #[test_case(ts(1, TimeUnit::Ns), "2 parsecs", None; "bad unit")]
+    #[test_case(ts(1, TimeUnit::Ns), "ns", None; "no number")]
+    fn parse_tests(timescale: Timescale, input: &str, baseline: Option<Toffset>) {
+        assert_eq!(timescale.parse_time(input), baseline);
+    }
+
+    #[test_case(ts(1, TimeUnit::Ns), 120, Some("8.333MHz"); "mhz")]
+    #[test_case(ts(1, TimeUnit::Ns), 1000, Some("1MHz"); "whole mhz")]
+    #[test_case(ts(1, TimeUnit::Ps), 400, Some("2.5GHz"); "ghz")]
+    #[test_case(ts(1, TimeUnit::Ms), 2000, Some("0.5Hz"); "below a hz")]
+    #[test_case(ts(1, TimeUnit::Ns), 0, None; "zero period")]
+    fn frequency_tests(timescale: Timescale, period: Toffset, baseline: Option<&str>) {
+        assert_eq!(timescale.format_frequency(period).as_deref(), baseline);
+    }
+
+    #[test_case(-9, ts(1, TimeUnit::Ns); "ns")]
+    #[test_case(-10, ts(100, TimeUnit::Ps); "100ps")]
+    #[test_case(-20, ts(1, TimeUnit::Fs); "clamped to fs")]
+    #[test_case(2, ts(100, TimeUnit::S); "100s")]
+    fn exponent_tests(exponent: i8, baseline: Timescale) {
+        assert_eq!(Timescale::from_exponent(exponent), baseline);
+    }
+
+    #[test_case(0.3, 1; "below a tick")]
+    #[test_case(1.0, 1; "exactly a tick")]
+    #[test_case(1.5, 2; "two")]
+    #[test_case(3.0, 5; "five")]
+    #[test_case(7.0, 10; "next decade")]
+    #[test_case(120.0, 200; "two hundred")]
+    #[test_case(500.0, 500; "exactly five hundred")]
+    #[test_case(5001.0, 10000; "ten thousand")]
+    #[test_case(1e30, Toffset::MAX; "saturates")]
+    fn step_tests(min_step: f64, baseline: Toffset) {
+        assert_eq!(step_1_2_5(min_step), baseline);
+    }
+}
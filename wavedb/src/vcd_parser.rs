@@ -1,5 +1,6 @@
 use crate::errors;
 use crate::hier_map::HierMap;
+use crate::timescale::Timescale;
 use std::fs::File;
 use std::io;
 use std::path::PathBuf;
@@ -35,6 +36,15 @@ impl<R: io::Read> WaveParser<R> {
         }
     }
 
+    /// Timescale declared by the vcd header; dumps without a `$timescale` fall back to the default
+    pub fn timescale(&self) -> Timescale {
+        self.header
+            .as_ref()
+            .and_then(|header| header.timescale)
+            .map(Timescale::from)
+            .unwrap_or_default()
+    }
+
     pub fn create_hiermap(&mut self) -> Result<HierMap, errors::Waverr> {
         if let Some(header) = self.header.take() {
             Ok(HierMap::from(header))
@@ -1,21 +1,35 @@
 use crate::errors::Waverr;
+use crate::formatting::{self, EnumMap};
+use crate::fst_parser::FstParser;
+use crate::ghw_parser::GhwParser;
 use crate::hier_map::{HierMap, SignalItem};
-use crate::puddle::builder::PuddleBuilder;
-use crate::puddle::{Puddle, SignalId};
-use crate::storage::in_memory::InMemWave;
+use crate::compression::{Compression, SizeReport, TreeSize};
+use crate::ingest::{self, IngestOptions, IngestProgress};
+use crate::puddle::{Puddle, SignalId, Toffset};
+use crate::storage::in_memory::{InMemWave, PuddleSource};
+use crate::storage::puddle_cache::PuddleCache;
+use crate::timescale::Timescale;
 use crate::vcd_parser::WaveParser;
-use crate::MAX_PUDDLE_WIDTH;
 use log::info;
 use serde::{Deserialize, Serialize};
 use sled::Db;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::*;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use vcd::Command;
+
+/// Version of the on-disk layout of a wavedb; bump this whenever the encoding of puddles, the
+/// config or the hier map changes. Databases written before this was tracked are version 0
+const WDB_FORMAT_VERSION: u32 = 5;
+
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct WdbConfig {
     db_name: String,
-    time_range: (u32, u32),
+    time_range: (Toffset, Toffset),
+    #[serde(default)]
+    compression: Compression,
+    #[serde(default)]
+    timescale: Timescale,
 }
 
 ///DB for holding buckets
@@ -26,16 +40,39 @@ pub struct WaveDb {
     db: Db,
     //TODO: think about what should be wanted from a cfg file
     config: WdbConfig,
-    puddle_cache: HashMap<SignalId, Arc<Puddle>>,
+    /// Decoded puddles shared by every wave handed out by this wavedb
+    puddle_cache: Arc<Mutex<PuddleCache>>,
     pub hier_map: Arc<HierMap>,
 }
 
+/// Attempts at opening a sled db that is still locked
+const OPEN_ATTEMPTS: u32 = 50;
+
+/// Open the sled db at `path`. Sled lets go of the lock on a db from its background threads, so
+/// a db that was only just dropped can stay locked for a moment after, for instance when a dump
+/// is reloaded
+fn open_db(path: &Path) -> sled::Result<Db> {
+    let mut attempt = 1;
+    loop {
+        match sled::open(path) {
+            Err(sled::Error::Io(err))
+                if err.kind() == std::io::ErrorKind::Other && attempt < OPEN_ATTEMPTS =>
+            {
+                log::warn!("retrying to open {:?}: {}", path, err);
+                attempt += 1;
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            opened => return opened,
+        }
+    }
+}
+
 impl WaveDb {
     fn new(db_name: String, db_path: Option<&Path>) -> WaveDb {
         WaveDb {
-            db: sled::open(db_path.unwrap_or_else(|| db_name.as_ref())).unwrap(),
+            db: open_db(db_path.unwrap_or_else(|| db_name.as_ref())).unwrap(),
             hier_map: Arc::default(),
-            puddle_cache: HashMap::default(),
+            puddle_cache: Arc::default(),
             config: WdbConfig {
                 db_name,
                 ..WdbConfig::default()
@@ -49,14 +86,7 @@ impl WaveDb {
             .map(|signal| signal.clone())
     }
 
-    fn get_time_slices(&self) -> std::iter::StepBy<std::ops::Range<u32>> {
-        info!("END TIME IS {}", self.config.time_range.0);
-        ((self.config.time_range.0 / MAX_PUDDLE_WIDTH) * MAX_PUDDLE_WIDTH
-            ..(self.config.time_range.1 / MAX_PUDDLE_WIDTH + 1) * MAX_PUDDLE_WIDTH)
-            .step_by(MAX_PUDDLE_WIDTH as usize)
-    }
-
-    fn set_time_range(&mut self, range: (u32, u32)) {
+    fn set_time_range(&mut self, range: (Toffset, Toffset)) {
         self.config.time_range = range;
     }
 
@@ -77,6 +107,51 @@ impl WaveDb {
         Ok(())
     }
 
+    fn format_version(&self) -> Result<u32, Waverr> {
+        match self.db.get("format_version")? {
+            Some(rawbytes) => Ok(bincode::deserialize(rawbytes.as_ref())?),
+            None => Ok(0),
+        }
+    }
+
+    fn save_format_version(&self) -> Result<(), Waverr> {
+        self.db
+            .insert("format_version", bincode::serialize(&WDB_FORMAT_VERSION)?)?;
+        Ok(())
+    }
+
+    fn check_format_version(&self) -> Result<(), Waverr> {
+        let found = self.format_version()?;
+        if found != WDB_FORMAT_VERSION {
+            return Err(Waverr::WdbVersionErr {
+                found,
+                expected: WDB_FORMAT_VERSION,
+            });
+        }
+        Ok(())
+    }
+
+    /// Load a previously built wavedb if there is one. Databases in an outdated format are
+    /// cleared out, so that they get rebuilt from the signal dump
+    fn recover(&mut self) -> Result<bool, Waverr> {
+        if !self.was_recovered() {
+            return Ok(false);
+        }
+        if let Err(err) = self.check_format_version() {
+            info!("Rebuilding {}: {}", self.config.db_name, err);
+            for name in self.db.tree_names() {
+                if name != self.db.name() {
+                    self.db.drop_tree(name)?;
+                }
+            }
+            self.db.clear()?;
+            return Ok(false);
+        }
+        self.load_config()?;
+        self.load_idmap()?;
+        Ok(true)
+    }
+
     fn save_idmap(&self) -> Result<(), Waverr> {
         self.db
             .insert("id_map", bincode::serialize(self.hier_map.as_ref())?)?;
@@ -92,16 +167,21 @@ impl WaveDb {
         }
     }
 
-    pub fn get_bounds(&self) -> (u32, u32) {
+    pub fn get_bounds(&self) -> (Toffset, Toffset) {
         self.config.time_range
     }
 
+    pub fn get_timescale(&self) -> Timescale {
+        self.config.timescale
+    }
+
     pub fn was_recovered(&self) -> bool {
         self.db.was_recovered()
     }
 
     pub fn open_wdb(wdb_path: &Path) -> Result<WaveDb, Waverr> {
         let mut wdb = WaveDb::new("TempName".into(), Some(wdb_path));
+        wdb.check_format_version()?;
         wdb.load_config()?;
         wdb.load_idmap()?;
         Ok(wdb)
@@ -111,138 +191,360 @@ impl WaveDb {
         self.hier_map.clone()
     }
 
-    //TODO: parallelize this, make this less nasty, etc
+    /// Build a wavedb from a signal dump, picking the parser from the file extension; anything
+    /// that isn't an `.fst` or `.ghw` is treated as a vcd
+    pub fn from_file(file_path: PathBuf, wdb_path: &Path) -> Result<WaveDb, Waverr> {
+        WaveDb::from_file_with_options(file_path, wdb_path, IngestOptions::default(), |_| {})
+    }
+
+    /// Like [`WaveDb::from_file`], calling `progress` periodically as the dump is converted
+    pub fn from_file_with_options<F: FnMut(IngestProgress)>(
+        file_path: PathBuf,
+        wdb_path: &Path,
+        options: IngestOptions,
+        mut progress: F,
+    ) -> Result<WaveDb, Waverr> {
+        match file_path.extension().and_then(|ext| ext.to_str()) {
+            Some("fst") => WaveDb::load_fst(file_path, wdb_path, options, &mut progress),
+            Some("ghw") => WaveDb::load_ghw(file_path, wdb_path, options, &mut progress),
+            _ => WaveDb::load_vcd(file_path, wdb_path, options, &mut progress),
+        }
+    }
+
+    fn wdb_name(file_path: &Path) -> String {
+        if let Some(file) = file_path.file_stem() {
+            file.to_str().unwrap().to_string()
+        } else {
+            file_path.to_str().unwrap().to_string()
+        }
+    }
+
     pub fn from_vcd(vcd_file_path: PathBuf, wdb_path: &Path) -> Result<WaveDb, Waverr> {
+        WaveDb::load_vcd(vcd_file_path, wdb_path, IngestOptions::default(), &mut |_| {})
+    }
+
+    pub fn from_fst(fst_file_path: PathBuf, wdb_path: &Path) -> Result<WaveDb, Waverr> {
+        WaveDb::load_fst(fst_file_path, wdb_path, IngestOptions::default(), &mut |_| {})
+    }
+
+    pub fn from_ghw(ghw_file_path: PathBuf, wdb_path: &Path) -> Result<WaveDb, Waverr> {
+        WaveDb::load_ghw(ghw_file_path, wdb_path, IngestOptions::default(), &mut |_| {})
+    }
+
+    fn load_vcd(
+        vcd_file_path: PathBuf,
+        wdb_path: &Path,
+        options: IngestOptions,
+        progress: &mut dyn FnMut(IngestProgress),
+    ) -> Result<WaveDb, Waverr> {
         let mut parser = WaveParser::new(vcd_file_path.clone())?;
-        let wdb_name = {
-            if let Some(vcd_file) = vcd_file_path.file_stem() {
-                vcd_file.to_str().unwrap().to_string()
-            } else {
-                vcd_file_path.to_str().unwrap().to_string()
-            }
-        };
-        let mut wdb = WaveDb::new(wdb_name, Some(wdb_path));
-        if wdb.was_recovered() {
-            wdb.load_config()?;
-            wdb.load_idmap()?;
+        let mut wdb = WaveDb::new(WaveDb::wdb_name(&vcd_file_path), Some(wdb_path));
+        if wdb.recover()? {
             return Ok(wdb);
         }
-        let mut first_time = None;
-        let mut global_time: u32 = 0;
-        let mut current_range = (global_time, global_time + MAX_PUDDLE_WIDTH);
-        let mut inflight_puddles: HashMap<SignalId, PuddleBuilder> = HashMap::new();
+        wdb.config.timescale = parser.timescale();
         wdb.hier_map = Arc::new(parser.create_hiermap()?);
-        for item in parser {
-            match item {
-                Ok(Command::Timestamp(time)) => {
-                    let time = time as u32;
-                    if time % MAX_PUDDLE_WIDTH < global_time % MAX_PUDDLE_WIDTH {
-                        for (_, puddle) in inflight_puddles.into_iter() {
-                            wdb.insert_puddle(puddle.into())?;
-                        }
-                        inflight_puddles = HashMap::new();
-                        let rounded_time = time - (time % MAX_PUDDLE_WIDTH);
-                        current_range = (rounded_time, rounded_time + MAX_PUDDLE_WIDTH)
-                    }
-                    if first_time.is_none() {
-                        first_time = Some(time);
-                    }
-                    global_time = time;
-                }
-                //TODO: collapse these arms if possible? good way to share this code?
-                Ok(command) => {
-                    match command {
-                        //TODO: add a get id function to the vcd lib that returns an option
-                        Command::ChangeScalar(id, ..)
-                        | Command::ChangeVector(id, ..)
-                        | Command::ChangeReal(id, ..)
-                        | Command::ChangeString(id, ..) => {
-                            let base_id = id.0 as u32 - id.0 as u32 % Puddle::signals_per_puddle();
-                            let puddle_builder = inflight_puddles
-                                .entry(base_id)
-                                .or_insert_with(|| PuddleBuilder::new(current_range.0));
-                            puddle_builder.add_signal(command, global_time)?;
-                        }
-                        Command::Begin(_) => {}
-                        Command::End(_) => {}
-                        _ => return Err(Waverr::VcdCommandErr(command)),
-                    }
-                }
-                Err(_) => {
-                    return Err(Waverr::VcdErr("Malformed vcd"));
-                }
-            }
-        }
-        for (_, puddle) in inflight_puddles.into_iter() {
-            wdb.insert_puddle(puddle.into())?;
+        wdb.ingest(
+            parser.map(|item| item.map_err(|_| Waverr::VcdErr("Malformed vcd"))),
+            options,
+            progress,
+        )?;
+        Ok(wdb)
+    }
+
+    fn load_fst(
+        fst_file_path: PathBuf,
+        wdb_path: &Path,
+        options: IngestOptions,
+        progress: &mut dyn FnMut(IngestProgress),
+    ) -> Result<WaveDb, Waverr> {
+        let mut wdb = WaveDb::new(WaveDb::wdb_name(&fst_file_path), Some(wdb_path));
+        if wdb.recover()? {
+            return Ok(wdb);
         }
+        let mut parser = FstParser::new(fst_file_path)?;
+        wdb.config.timescale = parser.timescale();
+        wdb.hier_map = Arc::new(parser.create_hiermap()?);
+        wdb.ingest(parser, options, progress)?;
+        Ok(wdb)
+    }
 
-        wdb.set_time_range((
-            first_time.expect("No timestamp present in VCD!"),
-            global_time,
-        ));
-        wdb.dump_config()?;
-        wdb.save_idmap()?;
-        wdb.db.flush()?;
+    fn load_ghw(
+        ghw_file_path: PathBuf,
+        wdb_path: &Path,
+        options: IngestOptions,
+        progress: &mut dyn FnMut(IngestProgress),
+    ) -> Result<WaveDb, Waverr> {
+        let mut wdb = WaveDb::new(WaveDb::wdb_name(&ghw_file_path), Some(wdb_path));
+        if wdb.recover()? {
+            return Ok(wdb);
+        }
+        let mut parser = GhwParser::new(ghw_file_path)?;
+        wdb.config.timescale = parser.timescale();
+        wdb.hier_map = Arc::new(parser.create_hiermap()?);
+        wdb.ingest(parser, options, progress)?;
         Ok(wdb)
     }
 
-    fn insert_puddle(&self, puddle: Puddle) -> Result<(), Waverr> {
-        let tree: sled::Tree = self.db.open_tree(puddle.get_btree_idx().to_le_bytes())?;
-        let serialized = serde_json::to_string(&puddle)?;
+    /// Convert value changes into puddles and persist them, along with the config and hier map
+    fn ingest<I>(
+        &mut self,
+        commands: I,
+        options: IngestOptions,
+        progress: &mut dyn FnMut(IngestProgress),
+    ) -> Result<(), Waverr>
+    where
+        I: Iterator<Item = Result<Command, Waverr>>,
+    {
+        self.config.compression = options.compression;
+        let time_range = ingest::ingest(commands, &self.db, options, progress)?;
+        self.set_time_range(time_range);
+        self.dump_config()?;
+        self.save_idmap()?;
+        self.save_format_version()?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Cap the memory held by decoded puddles, evicting least recently used ones if needed
+    pub fn set_cache_budget(&self, bytes: usize) {
+        self.puddle_cache
+            .lock()
+            .expect("puddle cache lock poisoned")
+            .set_budget(bytes);
+    }
 
-        info!(
-            "Inserted a puddle at index {:?} with key {:?}",
-            puddle.get_btree_idx(),
-            puddle.get_base_sigid()
-        );
-        if let Ok(Some(_)) = tree.insert(puddle.get_base_sigid().to_le_bytes(), serialized.as_str())
-        {
-            // is problematic; implies that this value was previously set and we are
-            // overwriting it. We should write only once per bucket
-            return Err(Waverr::PuddleErr {
-                time: puddle.get_btree_idx(),
-                base_sigid: puddle.get_base_sigid(),
-                context: "This puddle already exists! We should never double insert",
+    /// Cache that puddles of this wavedb are kept in; derived waves keep theirs in it too
+    pub(crate) fn puddle_cache(&self) -> Arc<Mutex<PuddleCache>> {
+        self.puddle_cache.clone()
+    }
+
+    fn puddle_store(&self) -> Result<PuddleStore, Waverr> {
+        Ok(PuddleStore {
+            puddles: self.db.open_tree(ingest::PUDDLES_TREE)?,
+            changes: self.db.open_tree(ingest::CHANGES_TREE)?,
+            compression: self.config.compression,
+            time_range: self.config.time_range,
+            cache: self.puddle_cache.clone(),
+        })
+    }
+
+    /// Raw vs stored size of the puddles in every time slice, for weighing compression options
+    pub fn size_report(&self) -> Result<SizeReport, Waverr> {
+        let mut trees: BTreeMap<Toffset, TreeSize> = BTreeMap::new();
+        for entry in self.db.open_tree(ingest::PUDDLES_TREE)?.iter() {
+            let (key, stored) = entry?;
+            let btree_idx = ingest::key_slice(key.as_ref());
+            let tree_size = trees.entry(btree_idx).or_insert_with(|| TreeSize {
+                btree_idx,
+                ..TreeSize::default()
             });
+            tree_size.puddles += 1;
+            tree_size.stored_bytes += stored.len() as u64;
+            tree_size.raw_bytes +=
+                self.config.compression.decompress(stored.as_ref())?.len() as u64;
         }
-        Ok(())
+        Ok(SizeReport {
+            compression: self.config.compression,
+            trees: trees.values().copied().collect(),
+        })
+    }
+
+    fn enum_map_key(signal_id: SignalId) -> Vec<u8> {
+        [&b"enum_map/"[..], &signal_id.to_le_bytes()].concat()
     }
 
-    fn retrieve_puddle(&self, id: u32, ts_start: u32) -> Result<Arc<Puddle>, Waverr> {
-        let tree = self.db.open_tree(ts_start.to_le_bytes())?;
-        let base_id = id - id % Puddle::signals_per_puddle();
-        if let Some(puddle) = tree.get(base_id.to_le_bytes())? {
-            let puddle: Puddle = serde_json::from_slice(puddle.as_ref())?;
-            return Ok(Arc::new(puddle));
+    /// Names given to the values of a signal, persisted along with the wavedb
+    pub fn get_enum_map(&self, signal_id: SignalId) -> Result<Option<EnumMap>, Waverr> {
+        match self.db.get(WaveDb::enum_map_key(signal_id))? {
+            Some(rawbytes) => Ok(Some(bincode::deserialize(rawbytes.as_ref())?)),
+            None => Ok(None),
         }
-        Err(Waverr::PuddleErr {
-            time: ts_start,
-            base_sigid: base_id,
-            context: "failed to retrieve puddle",
-        })
+    }
+
+    /// Persist the value names of a signal; an empty map removes them
+    pub fn set_enum_map(&self, signal_id: SignalId, enum_map: &EnumMap) -> Result<(), Waverr> {
+        let key = WaveDb::enum_map_key(signal_id);
+        if enum_map.is_empty() {
+            self.db.remove(key)?;
+        } else {
+            self.db.insert(key, bincode::serialize(enum_map)?)?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Attach the enum maps of a TOML file (see [`formatting::parse_enum_maps`]) to their
+    /// signals, returning every signal that got one
+    pub fn load_enum_maps(&self, path: &Path) -> Result<Vec<(SignalId, EnumMap)>, Waverr> {
+        let text = std::fs::read_to_string(path).map_err(Waverr::IoErr)?;
+        formatting::parse_enum_maps(text.as_str())?
+            .into_iter()
+            .map(|(signal, enum_map)| {
+                let signal_id = self.get_sigitem(signal.as_str())?.id;
+                self.set_enum_map(signal_id, &enum_map)?;
+                Ok((signal_id, enum_map))
+            })
+            .collect()
     }
 
     pub fn get_imw_sigitem(&self, sigitem: SignalItem) -> Result<Arc<InMemWave>, Arc<Waverr>> {
+        let path = sigitem.path.clone().unwrap_or_else(|| sigitem.name.clone());
+        self.imw_at(sigitem, path)
+    }
+
+    pub fn get_imw(&self, sig: String) -> Result<Arc<InMemWave>, Arc<Waverr>> {
+        let sigitem = self.get_sigitem(sig.as_str())?;
+        self.imw_at(sigitem, sig)
+    }
+
+    fn imw_at(&self, sigitem: SignalItem, path: String) -> Result<Arc<InMemWave>, Arc<Waverr>> {
         let SignalItem {
             name: sig_name,
             id: sig_id,
             width,
+            literals,
+            sig_type,
+            ..
         } = sigitem;
 
-        let puddles = self
-            .get_time_slices()
-            .map(|start_slice| self.retrieve_puddle(sig_id, start_slice).unwrap())
-            .collect();
+        let enum_map = self.get_enum_map(sig_id).map_err(Arc::new)?;
+        let store = Arc::new(self.puddle_store().map_err(Arc::new)?);
+        Ok(Arc::new(
+            InMemWave::from_source(sig_name, sig_id, width, store)
+                .with_path(path)
+                .with_literals(literals)
+                .with_sig_type(sig_type)
+                .with_enum_map(enum_map.map(Arc::new)),
+        ))
+    }
+}
+
+/// Puddles of a wavedb, fetched from sled through the wavedb's puddle cache
+#[derive(Debug)]
+struct PuddleStore {
+    /// See [`ingest::PUDDLES_TREE`]
+    puddles: sled::Tree,
+    /// See [`ingest::CHANGES_TREE`]
+    changes: sled::Tree,
+    compression: Compression,
+    time_range: (Toffset, Toffset),
+    cache: Arc<Mutex<PuddleCache>>,
+}
 
-        InMemWave::new(sig_name, sig_id, width, puddles)
-            .map_err(Arc::new)
-            .map(Arc::new)
+impl PuddleStore {
+    fn retrieve_puddle(
+        &self,
+        base_id: SignalId,
+        ts_start: Toffset,
+    ) -> Result<Option<Puddle>, Waverr> {
+        match self.puddles.get(ingest::puddle_key(base_id, ts_start))? {
+            Some(puddle) => {
+                let raw = self.compression.decompress(puddle.as_ref())?;
+                let mut puddle = Puddle::from_bytes(raw.as_slice())?;
+                puddle.set_next_links(self.next_links(base_id, ts_start)?);
+                Ok(Some(puddle))
+            }
+            None => Ok(None),
+        }
     }
 
-    pub fn get_imw(&self, sig: String) -> Result<Arc<InMemWave>, Arc<Waverr>> {
-        let sigitem = self.get_sigitem(sig.as_str())?;
-        self.get_imw_sigitem(sigitem)
+    /// Base of the next slice after `ts_start` that each signal of the block changes in
+    fn next_links(
+        &self,
+        base_id: SignalId,
+        ts_start: Toffset,
+    ) -> Result<HashMap<SignalId, Toffset>, Waverr> {
+        let mut next_links = HashMap::new();
+        for signal_id in base_id..base_id + Puddle::signals_per_puddle() {
+            let later = ingest::change_key(signal_id, ts_start + 1)
+                ..=ingest::change_key(signal_id, Toffset::MAX);
+            if let Some(entry) = self.changes.range(later).next() {
+                let (key, _) = entry?;
+                next_links.insert(signal_id, ingest::key_slice(key.as_ref()));
+            }
+        }
+        Ok(next_links)
+    }
+
+    /// Slice of the first puddle of `signal_id`'s block within `range`, walking it backwards if
+    /// `backwards` is set
+    fn find_slice(
+        &self,
+        signal_id: SignalId,
+        range: std::ops::Range<Toffset>,
+        backwards: bool,
+    ) -> Result<Option<Toffset>, Waverr> {
+        let base_sigid = signal_id - signal_id % Puddle::signals_per_puddle();
+        let mut keys = self.puddles.range(
+            ingest::puddle_key(base_sigid, range.start)..ingest::puddle_key(base_sigid, range.end),
+        );
+        let found = if backwards {
+            keys.next_back()
+        } else {
+            keys.next()
+        };
+        match found {
+            Some(entry) => Ok(Some(ingest::key_slice(entry?.0.as_ref()))),
+            None => Ok(None),
+        }
+    }
+}
+
+impl PuddleSource for PuddleStore {
+    fn get_puddle(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        let base_sigid = signal_id - signal_id % Puddle::signals_per_puddle();
+        let key = (slice_base, base_sigid);
+        let cached = self
+            .cache
+            .lock()
+            .expect("puddle cache lock poisoned")
+            .get(key);
+        if cached.is_some() {
+            return Ok(cached);
+        }
+        // don't hold the lock while decoding; another wave may fetch the same puddle meanwhile,
+        // which only costs a redundant decode
+        let puddle = match self.retrieve_puddle(base_sigid, slice_base)? {
+            Some(puddle) => Arc::new(puddle),
+            None => return Ok(None),
+        };
+        self.cache
+            .lock()
+            .expect("puddle cache lock poisoned")
+            .insert(key, puddle.clone());
+        Ok(Some(puddle))
+    }
+
+    /// Idle slices have no entries, so the puddle is a single range lookup away
+    fn puddle_before(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        match self.find_slice(signal_id, 0..slice_base, true)? {
+            Some(base) => self.get_puddle(signal_id, base),
+            None => Ok(None),
+        }
+    }
+
+    fn puddle_from(
+        &self,
+        signal_id: SignalId,
+        slice_base: Toffset,
+    ) -> Result<Option<Arc<Puddle>>, Waverr> {
+        match self.find_slice(signal_id, slice_base..Toffset::MAX, false)? {
+            Some(base) => self.get_puddle(signal_id, base),
+            None => Ok(None),
+        }
+    }
+
+    fn time_range(&self) -> (Toffset, Toffset) {
+        self.time_range
     }
 }
 
@@ -250,6 +552,7 @@ impl WaveDb {
 #[allow(dead_code, unused_macros, unused_imports, unused_variables)]
 mod tests {
     use crate::signals::SigType;
+    use crate::timescale::TimeUnit;
     use crate::wavedb::*;
     use crate::*;
     use log::info;
@@ -303,6 +606,8 @@ mod tests {
             Err(err) => panic!("Unhandled error case: {:?}", err),
         };
         let var = actualdb.get_imw("logic.data".into()).unwrap();
+        // waves read their puddles lazily, so they keep the db open as well
+        drop(var);
         drop(actualdb);
 
         // we need to test what happens when we're loading wdb from disk
@@ -332,14 +637,642 @@ mod tests {
         let wdb = WaveDb::from_vcd(path_to_wikivcd, Path::new("/tmp/vcddb"))
             .expect("could not create wavedb");
 
-        let var = wdb
-            .get_imw("TOP.clock".into())
-            .expect("signal doesn't exist and it definitely should!!");
+        let var = whole_wave(&wdb, "TOP.clock");
 
-        let val: (u32, &[u8]) = var.all_data().next().unwrap();
+        let val: (Toffset, &[u8]) = var.all_data().next().unwrap();
         info!("len is val.1: {}", val.0);
         //assert!(val.1.len() == 8);
 
         std::fs::remove_dir_all("/tmp/vcddb");
     }
+
+    #[test]
+    fn wdb_timescale() {
+        let mut path_to_wikivcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_wikivcd.push("test_vcds/wikipedia.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_wikivcd.clone(), db.path()).expect("could not create wavedb");
+        let expected = Timescale {
+            magnitude: 100,
+            unit: TimeUnit::Ns,
+        };
+        assert_eq!(wdb.get_timescale(), expected);
+        drop(wdb);
+
+        let wdb = WaveDb::open_wdb(db.path()).expect("could not reopen wavedb");
+        assert_eq!(wdb.get_timescale(), expected);
+    }
+
+    #[test]
+    fn wdb_fst_matches_vcd() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let mut path_to_fst = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_fst.push("test_vcds/vga.fst");
+        let vcd_db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let fst_db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let vcd_wdb =
+            WaveDb::from_vcd(path_to_vcd, vcd_db.path()).expect("could not create wavedb");
+        let fst_wdb =
+            WaveDb::from_file(path_to_fst, fst_db.path()).expect("could not create wavedb");
+
+        assert_eq!(vcd_wdb.get_bounds(), fst_wdb.get_bounds());
+        assert_eq!(vcd_wdb.get_timescale(), fst_wdb.get_timescale());
+        for signal in &["TOP.clock", "TOP.vga.x_cnt", "TOP.vga.vga_hs", "TOP.vga.state_idle"] {
+            let vcd_sig = whole_wave(&vcd_wdb, signal);
+            let fst_sig = whole_wave(&fst_wdb, signal);
+            let vcd_data: Vec<(Toffset, Vec<u8>)> = vcd_sig
+                .all_data()
+                .map(|(time, payload)| (time, payload.to_vec()))
+                .collect();
+            let fst_data: Vec<(Toffset, Vec<u8>)> = fst_sig
+                .all_data()
+                .map(|(time, payload)| (time, payload.to_vec()))
+                .collect();
+            assert!(!vcd_data.is_empty());
+            assert_eq!(vcd_data, fst_data, "{} differs between vcd and fst", signal);
+        }
+    }
+
+    #[test]
+    fn wdb_from_ghw() {
+        init_test_logger();
+        let mut path_to_ghw = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_ghw.push("test_vcds/fsm.ghw");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_file(path_to_ghw, db.path()).expect("could not create wavedb");
+
+        assert_eq!(wdb.get_bounds(), (0, 40000));
+        assert_eq!(
+            wdb.get_timescale(),
+            Timescale {
+                magnitude: 1,
+                unit: TimeUnit::Fs,
+            }
+        );
+
+        let state = whole_wave(&wdb, "top.state");
+        assert_eq!(
+            state.get_literals().unwrap(),
+            &["idle".to_string(), "busy".to_string(), "done".to_string()]
+        );
+        let state_data: Vec<(Toffset, Vec<u8>)> = state
+            .all_data()
+            .take(3)
+            .map(|(time, payload)| (time, payload.to_vec()))
+            .collect();
+        assert_eq!(state_data, vec![(0, vec![0]), (3000, vec![1]), (5000, vec![2])]);
+
+        let data = whole_wave(&wdb, "top.data");
+        assert!(data.get_literals().is_none());
+        let (time, payload) = data.get_next_time(0).unwrap();
+        assert_eq!((time, payload[0]), (3000, 0x11));
+    }
+
+    #[test]
+    fn wdb_from_long_sim() {
+        init_test_logger();
+
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/long_sim.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd.clone(), db.path()).expect("could not create wavedb");
+
+        let (start, end) = wdb.get_bounds();
+        assert_eq!(start, (1 << 32) - 4096);
+        assert_eq!(end, (1 << 32) + 8191);
+        drop(wdb);
+
+        // the wider puddle bases have to survive a round trip through disk as well
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not reopen wavedb");
+        assert_eq!(wdb.get_bounds(), (start, end));
+        let clock = whole_wave(&wdb, "top.clk");
+        let mut last_time = 0;
+        for (time, _) in clock.all_data() {
+            assert!(last_time < time);
+            last_time = time;
+        }
+        assert!(last_time > u32::MAX as Toffset);
+    }
+
+    #[test]
+    fn wdb_outdated_format() {
+        init_test_logger();
+        let mut path_to_wikivcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_wikivcd.push("test_vcds/wikipedia.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb =
+            WaveDb::from_vcd(path_to_wikivcd.clone(), db.path()).expect("could not create wavedb");
+        assert_eq!(wdb.format_version().unwrap(), WDB_FORMAT_VERSION);
+        let baseline = all_data(&wdb, "logic.data");
+
+        // wdbs written before the format was versioned have no version key at all
+        wdb.db.remove("format_version").unwrap();
+        wdb.db.flush().unwrap();
+        drop(wdb);
+        match WaveDb::open_wdb(db.path()) {
+            Err(Waverr::WdbVersionErr { found, expected }) => {
+                assert_eq!((found, expected), (0, WDB_FORMAT_VERSION))
+            }
+            other => panic!("outdated wdb should not open: {:?}", other.map(|_| ())),
+        }
+
+        // but they are rebuilt when loaded from the original dump
+        let wdb = WaveDb::from_vcd(path_to_wikivcd, db.path()).expect("could not rebuild wavedb");
+        assert_eq!(wdb.format_version().unwrap(), WDB_FORMAT_VERSION);
+        assert_eq!(all_data(&wdb, "logic.data"), baseline);
+        drop(wdb);
+        WaveDb::open_wdb(db.path()).expect("rebuilt wdb should open");
+    }
+
+    #[test]
+    fn wdb_enum_maps() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/idle.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb =
+            WaveDb::from_vcd(path_to_vcd.clone(), db.path()).expect("could not create wavedb");
+
+        let maps = db.path().join("enums.toml");
+        std::fs::write(&maps, "[\"top.cfg\"]\n0x5a = \"BOOT\"\n0xa5 = \"RUN\"\n").unwrap();
+        let loaded = wdb.load_enum_maps(maps.as_path()).unwrap();
+        let cfg_id = wdb.get_sigitem("top.cfg").unwrap().id;
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0, cfg_id);
+
+        let cfg = wdb.get_imw("top.cfg".into()).unwrap();
+        assert_eq!(cfg.get_enum_map().unwrap().get(0xa5), Some("RUN"));
+        let window = cfg.window(0, 100).unwrap();
+        assert_eq!(window.get_enum_map().unwrap().get(0x5a), Some("BOOT"));
+        assert!(wdb
+            .get_imw("top.rst".into())
+            .unwrap()
+            .get_enum_map()
+            .is_none());
+
+        // maps entered by hand replace the loaded ones, and outlive the session
+        let mut enum_map = EnumMap::new();
+        enum_map.insert(0x5a, "RESET".into());
+        wdb.set_enum_map(cfg_id, &enum_map).unwrap();
+        drop((cfg, window, wdb));
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not reopen wavedb");
+        assert_eq!(wdb.get_enum_map(cfg_id).unwrap(), Some(enum_map));
+        wdb.set_enum_map(cfg_id, &EnumMap::new()).unwrap();
+        assert_eq!(wdb.get_enum_map(cfg_id).unwrap(), None);
+
+        std::fs::write(&maps, "[\"top.nope\"]\n0 = \"IDLE\"\n").unwrap();
+        assert!(wdb.load_enum_maps(maps.as_path()).is_err());
+    }
+
+    #[test]
+    fn wdb_compressed_puddles() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let mut reports = Vec::new();
+        let mut baseline = None;
+        for compression in Compression::ALL.iter().cloned() {
+            let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+            let options = IngestOptions {
+                compression,
+                ..IngestOptions::default()
+            };
+            let wdb = WaveDb::from_file_with_options(path_to_vcd.clone(), db.path(), options, |_| {})
+                .expect("could not create wavedb");
+            drop(wdb);
+
+            // the codec is part of the config, so reopened wdbs decompress transparently
+            let wdb = WaveDb::open_wdb(db.path()).expect("could not reopen wavedb");
+            let data = all_data(&wdb, "TOP.vga.x_cnt");
+            assert_eq!(data, *baseline.get_or_insert_with(|| data.clone()));
+            let report = wdb.size_report().unwrap();
+            info!("{}", report);
+            assert_eq!(report.compression, compression);
+            reports.push(report.total());
+        }
+
+        let (none, lz4, zstd) = (reports[0], reports[1], reports[2]);
+        assert!(none.puddles > 0);
+        assert_eq!(none.raw_bytes, none.stored_bytes);
+        for compressed in [lz4, zstd].iter() {
+            assert_eq!(compressed.puddles, none.puddles);
+            assert_eq!(compressed.raw_bytes, none.raw_bytes);
+            assert!(compressed.stored_bytes < none.stored_bytes);
+        }
+    }
+
+    #[test]
+    fn wdb_windows_through_cache() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+        let cached = |wdb: &WaveDb| wdb.puddle_cache.lock().unwrap().len();
+
+        // handing out a wave doesn't load anything
+        let x_addr = wdb.get_imw("TOP.x_addr".into()).unwrap();
+        assert_eq!(x_addr.all_data().count(), 0);
+        assert_eq!(cached(&wdb), 0);
+
+        // a window only pulls in the slices it overlaps, and the ones holding the changes right
+        // before and after it
+        let window = x_addr.window(16000, 17000).unwrap();
+        assert_eq!(cached(&wdb), 4);
+        let budget = wdb.puddle_cache.lock().unwrap().used() / 2;
+        let windowed: Vec<(Toffset, Vec<u8>)> = window
+            .data_in_range(16000, 17000)
+            .map(|(time, payload)| (time, payload.to_vec()))
+            .collect();
+        let baseline: Vec<(Toffset, Vec<u8>)> = all_data(&wdb, "TOP.x_addr")
+            .into_iter()
+            .filter(|(time, _)| (16000..17000).contains(time))
+            .collect();
+        assert!(!baseline.is_empty());
+        assert_eq!(windowed, baseline);
+
+        // shrinking the budget evicts, but windows can still be reloaded from disk
+        wdb.set_cache_budget(budget);
+        assert!(cached(&wdb) <= 3);
+        assert!(wdb.puddle_cache.lock().unwrap().used() <= budget);
+        let reloaded: Vec<(Toffset, Vec<u8>)> = x_addr
+            .window(16000, 17000)
+            .unwrap()
+            .data_in_range(16000, 17000)
+            .map(|(time, payload)| (time, payload.to_vec()))
+            .collect();
+        assert_eq!(reloaded, baseline);
+    }
+
+    #[test]
+    fn wdb_idle_stretches() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/idle.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+        assert_eq!(wdb.get_bounds(), (0, 50000));
+
+        // slices without any activity in the block have no puddle, and are skipped over
+        assert_eq!(
+            all_data(&wdb, "top.cfg"),
+            vec![(0, vec![0x5a]), (30000, vec![0xa5])]
+        );
+        assert_eq!(
+            all_data(&wdb, "top.rst"),
+            vec![(0, vec![1]), (100, vec![0]), (50000, vec![1])]
+        );
+        let clk = all_data(&wdb, "top.clk");
+        assert_eq!(clk.len(), 800);
+        assert!(clk
+            .iter()
+            .all(|(time, _)| *time < 2000 || (40000..42000).contains(time)));
+
+        // values are carried forward across idle slices
+        let cfg = wdb.get_imw("top.cfg".into()).unwrap();
+        let idle = cfg.window(10000, 20000).unwrap();
+        assert_eq!(idle.all_data().count(), 0);
+        assert!(idle
+            .get_slices()
+            .iter()
+            .all(|slice| slice.puddle().is_none()));
+        assert_eq!(idle.get_prev_time(15000), Some((0, &[0x5a][..])));
+        assert_eq!(idle.get_next_time(15000), Some((30000, &[0xa5][..])));
+        let waking = cfg.window(10000, 35000).unwrap();
+        assert_eq!(waking.get_next_time(30000), None);
+
+        let clk = wdb.get_imw("top.clk".into()).unwrap();
+        let idle = clk.window(10000, 20000).unwrap();
+        assert_eq!(idle.get_prev_time(15000), Some((1995, &[1][..])));
+
+        // rst has no changes in the puddle written for the cfg change
+        let rst = wdb.get_imw("top.rst".into()).unwrap();
+        let quiet = rst.window(29000, 31000).unwrap();
+        assert!(quiet.get_slices()[0].puddle().is_some());
+        assert_eq!(quiet.get_prev_time(30000), Some((100, &[0][..])));
+    }
+
+    #[test]
+    fn wdb_change_links() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/idle.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+        let id = |signal: &str| wdb.get_sigitem(signal).unwrap().id;
+        let (clk, rst, cfg) = (id("top.clk"), id("top.rst"), id("top.cfg"));
+        let store = wdb.puddle_store().unwrap();
+        let slice = |idx: Toffset| {
+            store
+                .get_puddle(clk, idx * MAX_PUDDLE_WIDTH)
+                .unwrap()
+                .unwrap_or_else(|| panic!("slice {} should have a puddle", idx))
+        };
+
+        // only cfg changes in slice 7; the others link across it
+        let cfg_change = slice(7);
+        for signal in [clk, rst, cfg].iter() {
+            assert_eq!(cfg_change.prev_change_base(*signal), Some(0));
+        }
+        assert_eq!(cfg_change.next_change_base(clk), Some(9 * MAX_PUDDLE_WIDTH));
+        assert_eq!(
+            cfg_change.next_change_base(rst),
+            Some(12 * MAX_PUDDLE_WIDTH)
+        );
+        assert_eq!(cfg_change.next_change_base(cfg), None);
+        assert_eq!(
+            cfg_change.get_droplet(rst, 0).err(),
+            Some(Some(12 * MAX_PUDDLE_WIDTH))
+        );
+
+        let first = slice(0);
+        assert_eq!(first.prev_change_base(clk), None);
+        assert_eq!(first.next_change_base(cfg), Some(7 * MAX_PUDDLE_WIDTH));
+        let last = slice(12);
+        assert_eq!(last.prev_change_base(clk), Some(10 * MAX_PUDDLE_WIDTH));
+        assert_eq!(last.next_change_base(rst), None);
+    }
+
+    /// Counts the puddles fetched from a store; neighbouring puddles are found through the
+    /// store's index, as `PuddleStore` does, but fetched through the count
+    #[derive(Debug)]
+    struct CountingStore {
+        store: PuddleStore,
+        gets: std::sync::atomic::AtomicUsize,
+    }
+
+    impl PuddleSource for CountingStore {
+        fn get_puddle(
+            &self,
+            signal_id: SignalId,
+            slice_base: Toffset,
+        ) -> Result<Option<Arc<Puddle>>, Waverr> {
+            self.gets.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.store.get_puddle(signal_id, slice_base)
+        }
+
+        fn time_range(&self) -> (Toffset, Toffset) {
+            self.store.time_range()
+        }
+
+        fn puddle_before(
+            &self,
+            signal_id: SignalId,
+            slice_base: Toffset,
+        ) -> Result<Option<Arc<Puddle>>, Waverr> {
+            match self.store.find_slice(signal_id, 0..slice_base, true)? {
+                Some(base) => self.get_puddle(signal_id, base),
+                None => Ok(None),
+            }
+        }
+
+        fn puddle_from(
+            &self,
+            signal_id: SignalId,
+            slice_base: Toffset,
+        ) -> Result<Option<Arc<Puddle>>, Waverr> {
+            match self
+                .store
+                .find_slice(signal_id, slice_base..Toffset::MAX, false)?
+            {
+                Some(base) => self.get_puddle(signal_id, base),
+                None => Ok(None),
+            }
+        }
+    }
+
+    #[test]
+    fn wdb_links_jump_idle_stretches() {
+        init_test_logger();
+        const IDLE_SLICES: Toffset = 1000;
+        let scratch = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let vcd = scratch.path().join("sparse.vcd");
+        let wake = IDLE_SLICES * MAX_PUDDLE_WIDTH + 5;
+        std::fs::write(
+            &vcd,
+            format!(
+                "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! irq $end\n\
+                 $upscope $end\n$enddefinitions $end\n#0\n0!\n#5\n1!\n#{}\n0!\n",
+                wake
+            ),
+        )
+        .unwrap();
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(vcd, db.path()).expect("could not create wavedb");
+        let store = Arc::new(CountingStore {
+            store: wdb.puddle_store().unwrap(),
+            gets: Default::default(),
+        });
+        let gets = || store.gets.load(std::sync::atomic::Ordering::Relaxed);
+        let irq_id = wdb.get_sigitem("top.irq").unwrap().id;
+        let irq = InMemWave::from_source("irq".into(), irq_id, 1, store.clone());
+        let trees = wdb.db.tree_names();
+
+        // carrying values into and out of a window in the middle of the stretch goes straight to
+        // the puddles on either side of it
+        let middle = IDLE_SLICES / 2 * MAX_PUDDLE_WIDTH;
+        let window = irq.window(middle, middle + 1).unwrap();
+        assert_eq!(window.get_prev_time(middle), Some((5, &[1][..])));
+        assert_eq!(window.get_next_time(middle), Some((wake, &[0][..])));
+        assert!(gets() <= 4, "{} puddle lookups for one window", gets());
+
+        // as does walking the changes across it
+        let before = gets();
+        assert_eq!(irq.next_change_where(5, |_, _| true).unwrap(), Some(wake));
+        assert_eq!(irq.prev_change_where(wake, |_, _| true).unwrap(), Some(5));
+        assert!(gets() - before <= 16, "{} puddle lookups", gets() - before);
+
+        // looking up slices without a puddle doesn't write anything
+        assert!(store.get_puddle(irq_id, middle).unwrap().is_none());
+        assert_eq!(wdb.db.tree_names(), trees);
+    }
+
+    #[test]
+    fn wdb_real_and_string_signals() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/analog.vcd");
+        let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let wdb = WaveDb::from_vcd(path_to_vcd, db.path()).expect("could not create wavedb");
+
+        let vout = wdb.get_imw("tb.vout".into()).unwrap();
+        assert_eq!(vout.get_sig_type(), SigType::Float);
+        let vout = vout.window(0, 10001).unwrap();
+        let reals: Vec<(Toffset, f64)> = vout.reals_in_range(0, 10001).collect();
+        assert_eq!(reals.len(), 200);
+        assert_eq!(reals[0], (0, 0.0));
+        assert_eq!(reals[1], (50, 0.1797));
+        assert!(reals.iter().all(|(_, volts)| volts.abs() <= 1.8));
+
+        // the value at 100 is carried into the range
+        assert_eq!(vout.real_before(120), Some(0.357605));
+        assert_eq!(vout.real_bounds(120, 260), Some((0.357605, 0.862966)));
+        let (min, max) = vout.real_bounds(0, 10001).unwrap();
+        assert!(min < -1.79 && max > 1.79);
+        assert_eq!(vout.real_bounds(0, 0), None);
+
+        let state = wdb.get_imw("tb.state".into()).unwrap();
+        assert!(matches!(state.get_sig_type(), SigType::Str(_)));
+        // the window straddles a few time slices
+        let state = state.window(2000, 9000).unwrap();
+        let states: Vec<(Toffset, &str)> = state.strings_in_range(2000, 9000).collect();
+        assert_eq!(
+            states,
+            vec![
+                (2500, "HOLD"),
+                (3750, "RAMP_DOWN"),
+                (5000, "IDLE"),
+                (6250, "RAMP_UP"),
+                (7500, "HOLD"),
+                (8750, "RAMP_DOWN"),
+            ]
+        );
+        assert_eq!(state.get_prev_time(2400), Some((1250, &b"RAMP_UP"[..])));
+        assert_eq!(state.get_next_time(4096), Some((5000, &b"IDLE"[..])));
+
+        let en = wdb.get_imw("tb.en".into()).unwrap();
+        assert_eq!(en.get_sig_type(), SigType::Bit);
+    }
+
+    /// Ingest the vcd at `path_to_vcd` with a fixed number of puddle building workers, recording
+    /// every progress report
+    fn ingest_vcd(
+        path_to_vcd: PathBuf,
+        wdb_path: &Path,
+        workers: usize,
+    ) -> (WaveDb, Vec<IngestProgress>) {
+        let mut parser = WaveParser::new(path_to_vcd).expect("could not open vcd");
+        let mut wdb = WaveDb::new("ingest_test".into(), Some(wdb_path));
+        wdb.config.timescale = parser.timescale();
+        wdb.hier_map = Arc::new(parser.create_hiermap().unwrap());
+        let mut reports = Vec::new();
+        let options = IngestOptions {
+            workers,
+            ..IngestOptions::default()
+        };
+        wdb.ingest(
+            parser.map(|item| item.map_err(|_| Waverr::VcdErr("Malformed vcd"))),
+            options,
+            &mut |report| reports.push(report),
+        )
+        .expect("could not ingest vcd");
+        (wdb, reports)
+    }
+
+    /// Load every puddle of `signal`
+    fn whole_wave(wdb: &WaveDb, signal: &str) -> InMemWave {
+        let (start, end) = wdb.get_bounds();
+        wdb.get_imw(signal.to_string())
+            .expect("signal should exist")
+            .window(start, end + 1)
+            .expect("could not load puddles")
+    }
+
+    fn all_data(wdb: &WaveDb, signal: &str) -> Vec<(Toffset, Vec<u8>)> {
+        whole_wave(wdb, signal)
+            .all_data()
+            .map(|(time, payload)| (time, payload.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn wdb_pipeline_matches_single_worker() {
+        init_test_logger();
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let serial_db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let pipelined_db =
+            tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let (serial, _) = ingest_vcd(path_to_vcd.clone(), serial_db.path(), 1);
+        let (pipelined, reports) = ingest_vcd(path_to_vcd, pipelined_db.path(), 4);
+
+        assert_eq!(serial.get_bounds(), pipelined.get_bounds());
+        for signal in &["TOP.clock", "TOP.vga.x_cnt", "TOP.vga.vga_hs", "TOP.vga.state_idle"] {
+            let serial_data = all_data(&serial, signal);
+            assert!(!serial_data.is_empty());
+            assert_eq!(serial_data, all_data(&pipelined, signal), "{} differs", signal);
+        }
+
+        // the final report always comes through, and accounts for every puddle
+        let last = reports.last().expect("no progress was reported");
+        assert_eq!(last.time, pipelined.get_bounds().1);
+        assert!(last.changes > 0);
+        let num_puddles = pipelined.db.open_tree(ingest::PUDDLES_TREE).unwrap().len();
+        assert_eq!(last.puddles_written, num_puddles as u64);
+    }
+
+    /// Throughput of ingesting vga.vcd replicated end to end many times, with a single worker
+    /// versus the default worker count; throughput is logged. Run with
+    /// `cargo test --release bench_vcd_ingest -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_vcd_ingest_throughput() {
+        init_test_logger();
+        const COPIES: u64 = 64;
+        let mut path_to_vcd = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path_to_vcd.push("test_vcds/vga.vcd");
+        let vga = std::fs::read_to_string(path_to_vcd).unwrap();
+        let split = vga.find("$enddefinitions $end").unwrap() + "$enddefinitions $end".len();
+        let (header, body) = vga.split_at(split);
+        let span = body
+            .lines()
+            .filter_map(|line| line.strip_prefix('#'))
+            .filter_map(|time| time.trim().parse::<Toffset>().ok())
+            .max()
+            .unwrap()
+            + 1;
+
+        let scratch = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+        let big_vcd = scratch.path().join("big_vga.vcd");
+        let mut contents = String::from(header);
+        for copy in 0..COPIES {
+            for line in body.lines() {
+                match line.strip_prefix('#') {
+                    Some(time) => {
+                        let time: Toffset = time.trim().parse().unwrap();
+                        contents.push_str(&format!("#{}", time + copy * span));
+                    }
+                    None => contents.push_str(line),
+                }
+                contents.push('\n');
+            }
+        }
+        std::fs::write(&big_vcd, &contents).unwrap();
+        let megabytes = contents.len() as f64 / (1024.0 * 1024.0);
+        drop(contents);
+
+        let mut wdbs = Vec::new();
+        let mut scratch_dbs = Vec::new();
+        for workers in [1, IngestOptions::default().workers].iter().cloned() {
+            let db = tempfile::TempDir::new().expect("Temp file could not be created! Shucks");
+            let start = std::time::Instant::now();
+            let (wdb, _) = ingest_vcd(big_vcd.clone(), db.path(), workers);
+            let elapsed = start.elapsed().as_secs_f64();
+            info!(
+                "{} worker(s): {:.1}MB in {:.2}s, {:.1}MB/s",
+                workers,
+                megabytes,
+                elapsed,
+                megabytes / elapsed
+            );
+            wdbs.push(wdb);
+            scratch_dbs.push(db);
+        }
+
+        let (serial, pipelined) = (&wdbs[0], &wdbs[1]);
+        assert_eq!(serial.get_bounds(), pipelined.get_bounds());
+        assert_eq!(serial.get_bounds().1, (COPIES - 1) * span + span - 1);
+        let signals = [
+            "TOP.clock",
+            "TOP.vga.x_cnt",
+            "TOP.vga.vga_hs",
+            "TOP.vga.state_idle",
+        ];
+        for signal in signals.iter() {
+            let data = all_data(serial, signal);
+            assert_eq!(data, all_data(pipelined, signal), "{} differs", signal);
+        }
+    }
 }